@@ -8,6 +8,8 @@ fn main() {
     println!("cargo:rerun-if-changed=proto/worker.proto");
     tonic_prost_build::configure()
         .build_server(false)
+        // So requests can be written to the telemetry audit log as JSON.
+        .type_attribute(".", "#[derive(serde::Serialize)]")
         .compile_protos(&["proto/worker.proto"], &["proto"])
         .unwrap();
     tauri_build::build();
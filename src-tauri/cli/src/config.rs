@@ -0,0 +1,597 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use voxelproxy_core::{
+    auth, controller::RoutingPolicy, inspector::InspectorFilter, mcpr,
+    protocols::table::PacketCategory, proxy,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Auto,
+    Manual,
+    /// Play a recording from `--capture-dir` back as the server
+    Replay,
+    /// Convert a recording to a ReplayMod replay (`.mcpr`) and exit
+    Export,
+}
+
+/// Command-line flags. Every flag overrides the same key from `--config`.
+#[derive(Parser, Debug)]
+#[command(
+    name = "voxelproxy-cli",
+    version,
+    about = "VoxelProxy без графического интерфейса"
+)]
+pub struct Args {
+    /// Path to a TOML config file
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Session mode
+    #[arg(short, long, value_enum)]
+    pub mode: Option<Mode>,
+
+    /// Upstream server address (manual mode), e.g. `mc.example.com` or `1.2.3.4:25566`
+    /// or the server name shown in ReplayMod (export mode)
+    #[arg(short, long)]
+    pub server: Option<String>,
+
+    /// Address the proxy listens on
+    #[arg(short, long)]
+    pub bind: Option<SocketAddr>,
+
+    /// Intercept Wi-Fi hotspot traffic with WinDivert (auto mode, Windows only)
+    #[arg(long)]
+    pub windivert: bool,
+
+    /// Lowest destination port intercepted by WinDivert
+    #[arg(long)]
+    pub port_min: Option<u16>,
+
+    /// Highest destination port intercepted by WinDivert
+    #[arg(long)]
+    pub port_max: Option<u16>,
+
+    /// Directory with extra protocol definitions (`*.toml` / `*.json`)
+    #[arg(long)]
+    pub versions_dir: Option<PathBuf>,
+
+    /// Minecraft account file for online-mode servers (written by `--login`)
+    #[arg(long)]
+    pub account: Option<PathBuf>,
+
+    /// Sign in with a Microsoft account before starting and save it to `--account`
+    #[arg(long)]
+    pub login: bool,
+
+    /// Session server the account joins through
+    #[arg(long)]
+    pub session_server: Option<String>,
+
+    /// Packet categories the inactive client sends upstream too, e.g. `chat,commands`
+    /// (chat, commands, movement, combat, interaction, inventory)
+    #[arg(long, value_delimiter = ',')]
+    pub route: Vec<PacketCategory>,
+
+    /// After a hand-over, have the server resend the inventory with a click it
+    /// rejects; anti-cheats may flag it
+    #[arg(long)]
+    pub resync_clicks: bool,
+
+    /// Directory to record every session's packets to, one pcapng file per session
+    #[arg(long)]
+    pub capture_dir: Option<PathBuf>,
+
+    /// Recording the server plays back (replay mode) or to convert (export mode)
+    #[arg(long)]
+    pub recording: Option<PathBuf>,
+
+    /// Playback speed relative to the recording; 0 plays without pauses (replay mode)
+    #[arg(long)]
+    pub speed: Option<f64>,
+
+    /// Where to write the replay; the recording's name with `.mcpr` by default (export mode)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Print every packet the session handles, or only the listed ones, by table
+    /// key or id, e.g. `--inspect move_pos,position,0x1A`
+    #[arg(long, num_args = 0.., value_delimiter = ',')]
+    pub inspect: Option<Vec<String>>,
+
+    /// Most packets printed per second by `--inspect`; the rest are counted
+    #[arg(long)]
+    pub inspect_rate: Option<u32>,
+
+    /// Serve Prometheus metrics at `http://<ADDR>/metrics`, e.g. `127.0.0.1:9100`
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+}
+
+/// Contents of the `--config` file. All keys are optional.
+///
+/// ```toml
+/// mode = "manual"
+/// server = "mc.example.com"
+/// bind = "0.0.0.0:25565"
+/// versions_dir = "versions"
+/// account = "account.json"
+/// session_server = "https://sessionserver.mojang.com"
+/// route = ["chat", "commands"]
+/// resync_clicks = false
+/// capture_dir = "captures"
+/// metrics_addr = "127.0.0.1:9100"
+///
+/// [auto]
+/// windivert = false
+/// port_min = 25560
+/// port_max = 25570
+///
+/// [replay]
+/// recording = "captures/session-1700000000000.pcapng"
+/// speed = 2.0
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    mode: Option<Mode>,
+    server: Option<String>,
+    bind: Option<SocketAddr>,
+    versions_dir: Option<PathBuf>,
+    account: Option<PathBuf>,
+    session_server: Option<String>,
+    route: Option<Vec<PacketCategory>>,
+    resync_clicks: Option<bool>,
+    capture_dir: Option<PathBuf>,
+    metrics_addr: Option<SocketAddr>,
+    #[serde(default)]
+    auto: AutoFileConfig,
+    #[serde(default)]
+    replay: ReplayFileConfig,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct AutoFileConfig {
+    windivert: Option<bool>,
+    port_min: Option<u16>,
+    port_max: Option<u16>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct ReplayFileConfig {
+    recording: Option<PathBuf>,
+    speed: Option<f64>,
+}
+
+/// Everything resolved from flags and the config file.
+#[derive(Debug)]
+pub struct Config {
+    pub settings: Settings,
+    pub versions_dir: Option<PathBuf>,
+    pub account: Option<AccountConfig>,
+    pub routing: RoutingPolicy,
+    pub resync_clicks: bool,
+    pub capture_dir: Option<PathBuf>,
+    pub inspector: Option<InspectorFilter>,
+    pub metrics_addr: Option<SocketAddr>,
+}
+
+/// Where the online-mode account lives and how it signs in.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AccountConfig {
+    pub path: PathBuf,
+    /// Run the device-code login instead of reading `path`.
+    pub login: bool,
+    pub session_server: String,
+}
+
+/// Fully resolved session settings for one run.
+#[derive(Debug, PartialEq)]
+pub enum Settings {
+    Manual {
+        server: String,
+        bind: SocketAddr,
+    },
+    Auto {
+        windivert: bool,
+        port_min: u16,
+        port_max: u16,
+        bind: SocketAddr,
+    },
+    /// A manual session against a local server playing `recording` back.
+    Replay {
+        recording: PathBuf,
+        speed: f64,
+        bind: SocketAddr,
+    },
+    /// Conversion of `recording` into a ReplayMod replay at `output`.
+    Export {
+        recording: PathBuf,
+        output: PathBuf,
+        /// Shown as the server in ReplayMod's replay list.
+        server_name: String,
+    },
+}
+
+const DEFAULT_PORT_MIN: u16 = 25560;
+const DEFAULT_PORT_MAX: u16 = 25570;
+
+impl Config {
+    pub fn load(args: Args) -> anyhow::Result<Self> {
+        let file = match &args.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Не удалось прочитать {}", path.display()))?;
+                toml::from_str(&text)
+                    .with_context(|| format!("Ошибка в файле настроек {}", path.display()))?
+            }
+            None => FileConfig::default(),
+        };
+        let versions_dir = args.versions_dir.clone().or(file.versions_dir.clone());
+        let capture_dir = args.capture_dir.clone().or(file.capture_dir.clone());
+        let metrics_addr = args.metrics_addr.or(file.metrics_addr);
+        let resync_clicks = args.resync_clicks || file.resync_clicks.unwrap_or(false);
+        let session_server = args
+            .session_server
+            .clone()
+            .or(file.session_server.clone())
+            .unwrap_or_else(|| auth::DEFAULT_SESSION_SERVER.to_string());
+        let account = match args.account.clone().or(file.account.clone()) {
+            Some(path) => Some(AccountConfig {
+                path,
+                login: args.login,
+                session_server,
+            }),
+            None if args.login => {
+                anyhow::bail!("Для --login нужен путь к файлу аккаунта (--account)")
+            }
+            None => None,
+        };
+        let inactive = if args.route.is_empty() {
+            file.route.clone().unwrap_or_default()
+        } else {
+            args.route.clone()
+        };
+        let inspector = match &args.inspect {
+            Some(packets) => Some(inspector_filter(packets, args.inspect_rate)?),
+            None if args.inspect_rate.is_some() => {
+                anyhow::bail!("--inspect-rate задаётся вместе с --inspect")
+            }
+            None => None,
+        };
+        Ok(Config {
+            settings: Settings::merge(args, file)?,
+            versions_dir,
+            account,
+            routing: RoutingPolicy { inactive },
+            resync_clicks,
+            capture_dir,
+            inspector,
+            metrics_addr,
+        })
+    }
+}
+
+/// Splits `--inspect` entries into packet ids (decimal or `0x` hex) and table
+/// keys.
+fn inspector_filter(packets: &[String], rate: Option<u32>) -> anyhow::Result<InspectorFilter> {
+    let mut filter = InspectorFilter {
+        max_per_second: rate.unwrap_or(0),
+        ..Default::default()
+    };
+    for packet in packets {
+        let id = match packet.strip_prefix("0x") {
+            Some(hex) => i32::from_str_radix(hex, 16).ok(),
+            None => packet.parse().ok(),
+        };
+        match id {
+            Some(id) => filter.packet_ids.push(id),
+            None if packet.starts_with(|c: char| c.is_ascii_lowercase()) => {
+                filter.names.push(packet.clone())
+            }
+            None => anyhow::bail!("Неизвестный пакет для --inspect: {}", packet),
+        }
+    }
+    Ok(filter)
+}
+
+impl Settings {
+    fn merge(args: Args, file: FileConfig) -> anyhow::Result<Self> {
+        let bind = args
+            .bind
+            .or(file.bind)
+            .unwrap_or_else(proxy::default_bind_addr);
+
+        match args.mode.or(file.mode) {
+            Some(Mode::Manual) => {
+                let Some(server) = args.server.or(file.server) else {
+                    anyhow::bail!("Для ручного режима нужен адрес сервера (--server)");
+                };
+                Ok(Settings::Manual { server, bind })
+            }
+            Some(Mode::Auto) => {
+                let port_min = args
+                    .port_min
+                    .or(file.auto.port_min)
+                    .unwrap_or(DEFAULT_PORT_MIN);
+                let port_max = args
+                    .port_max
+                    .or(file.auto.port_max)
+                    .unwrap_or(DEFAULT_PORT_MAX);
+                if port_min > port_max {
+                    anyhow::bail!("port_min ({}) больше port_max ({})", port_min, port_max);
+                }
+                Ok(Settings::Auto {
+                    windivert: args.windivert || file.auto.windivert.unwrap_or(false),
+                    port_min,
+                    port_max,
+                    bind,
+                })
+            }
+            Some(Mode::Replay) => {
+                let Some(recording) = args.recording.or(file.replay.recording) else {
+                    anyhow::bail!("Для воспроизведения нужен файл записи (--recording)");
+                };
+                let speed = args.speed.or(file.replay.speed).unwrap_or(1.0);
+                if !(speed >= 0.0 && speed.is_finite()) {
+                    anyhow::bail!("Скорость воспроизведения должна быть не меньше 0");
+                }
+                Ok(Settings::Replay {
+                    recording,
+                    speed,
+                    bind,
+                })
+            }
+            Some(Mode::Export) => {
+                let Some(recording) = args.recording.or(file.replay.recording) else {
+                    anyhow::bail!("Для экспорта нужен файл записи (--recording)");
+                };
+                let output = args
+                    .output
+                    .unwrap_or_else(|| recording.with_extension(mcpr::EXTENSION));
+                Ok(Settings::Export {
+                    recording,
+                    output,
+                    server_name: args
+                        .server
+                        .or(file.server)
+                        .unwrap_or_else(|| "VoxelProxy".into()),
+                })
+            }
+            None => anyhow::bail!("Не указан режим (--mode auto|manual|replay|export)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Args {
+        Args::parse_from(std::iter::once("voxelproxy-cli").chain(list.iter().copied()))
+    }
+
+    #[test]
+    fn manual_from_flags() {
+        let settings = Settings::merge(
+            args(&["--mode", "manual", "--server", "mc.example.com"]),
+            FileConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            settings,
+            Settings::Manual {
+                server: "mc.example.com".into(),
+                bind: proxy::default_bind_addr(),
+            }
+        );
+    }
+
+    #[test]
+    fn manual_requires_server() {
+        assert!(Settings::merge(args(&["--mode", "manual"]), FileConfig::default()).is_err());
+    }
+
+    #[test]
+    fn flags_override_file() {
+        let file: FileConfig = toml::from_str(
+            "mode = \"manual\"\nserver = \"a.example\"\nbind = \"127.0.0.1:1000\"\n",
+        )
+        .unwrap();
+        let settings = Settings::merge(args(&["--server", "b.example"]), file).unwrap();
+        assert_eq!(
+            settings,
+            Settings::Manual {
+                server: "b.example".into(),
+                bind: "127.0.0.1:1000".parse().unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn auto_from_file() {
+        let file: FileConfig =
+            toml::from_str("mode = \"auto\"\n[auto]\nwindivert = true\nport_min = 25000\n")
+                .unwrap();
+        let settings = Settings::merge(args(&[]), file).unwrap();
+        assert_eq!(
+            settings,
+            Settings::Auto {
+                windivert: true,
+                port_min: 25000,
+                port_max: DEFAULT_PORT_MAX,
+                bind: proxy::default_bind_addr(),
+            }
+        );
+    }
+
+    #[test]
+    fn versions_dir_from_flag() {
+        let config = Config::load(args(&[
+            "--mode",
+            "manual",
+            "--server",
+            "a.example",
+            "--versions-dir",
+            "v",
+        ]))
+        .unwrap();
+        assert_eq!(config.versions_dir, Some(PathBuf::from("v")));
+    }
+
+    #[test]
+    fn account_from_flags() {
+        let config = Config::load(args(&[
+            "--mode",
+            "manual",
+            "--server",
+            "a.example",
+            "--account",
+            "account.json",
+            "--login",
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.account,
+            Some(AccountConfig {
+                path: PathBuf::from("account.json"),
+                login: true,
+                session_server: auth::DEFAULT_SESSION_SERVER.to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn route_from_flag() {
+        let config = Config::load(args(&[
+            "--mode",
+            "manual",
+            "--server",
+            "a.example",
+            "--route",
+            "chat,commands",
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.routing.inactive,
+            [PacketCategory::Chat, PacketCategory::Commands]
+        );
+        assert!(Args::try_parse_from(["voxelproxy-cli", "--route", "chta"]).is_err());
+    }
+
+    #[test]
+    fn replay_from_file() {
+        let file: FileConfig =
+            toml::from_str("mode = \"replay\"\n[replay]\nrecording = \"a.pcapng\"\n").unwrap();
+        let settings = Settings::merge(args(&["--speed", "4"]), file).unwrap();
+        assert_eq!(
+            settings,
+            Settings::Replay {
+                recording: PathBuf::from("a.pcapng"),
+                speed: 4.0,
+                bind: proxy::default_bind_addr(),
+            }
+        );
+        assert!(Settings::merge(args(&["--mode", "replay"]), FileConfig::default()).is_err());
+        let out = Settings::merge(
+            args(&["--mode", "replay", "--recording", "a", "--speed=-1"]),
+            FileConfig::default(),
+        );
+        assert!(out.is_err());
+    }
+
+    #[test]
+    fn export_next_to_the_recording() {
+        let settings = Settings::merge(
+            args(&["--mode", "export", "--recording", "captures/a.pcapng"]),
+            FileConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            settings,
+            Settings::Export {
+                recording: PathBuf::from("captures/a.pcapng"),
+                output: PathBuf::from("captures/a.mcpr"),
+                server_name: "VoxelProxy".into(),
+            }
+        );
+        assert!(Settings::merge(args(&["--mode", "export"]), FileConfig::default()).is_err());
+    }
+
+    #[test]
+    fn inspect_from_flags() {
+        let config = Config::load(args(&[
+            "--mode",
+            "manual",
+            "--server",
+            "a.example",
+            "--inspect",
+            "move_pos,0x1A,7",
+            "--inspect-rate",
+            "20",
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.inspector,
+            Some(InspectorFilter {
+                packet_ids: vec![0x1A, 7],
+                names: vec!["move_pos".into()],
+                max_per_second: 20,
+                ..Default::default()
+            })
+        );
+        let all = Config::load(args(&["--mode", "manual", "--server", "a", "--inspect"]));
+        assert_eq!(all.unwrap().inspector, Some(InspectorFilter::default()));
+        let out = Config::load(args(&[
+            "--mode",
+            "manual",
+            "--server",
+            "a",
+            "--inspect-rate",
+            "5",
+        ]));
+        assert!(out.is_err());
+    }
+
+    #[test]
+    fn resync_clicks_are_opt_in() {
+        let manual = ["--mode", "manual", "--server", "a.example"];
+        assert!(!Config::load(args(&manual)).unwrap().resync_clicks);
+        let flag = [&manual[..], &["--resync-clicks"]].concat();
+        assert!(Config::load(args(&flag)).unwrap().resync_clicks);
+        let file: FileConfig = toml::from_str("resync_clicks = true\n").unwrap();
+        assert_eq!(file.resync_clicks, Some(true));
+    }
+
+    #[test]
+    fn metrics_addr_from_file() {
+        let file: FileConfig = toml::from_str("metrics_addr = \"127.0.0.1:9100\"\n").unwrap();
+        assert_eq!(file.metrics_addr, Some("127.0.0.1:9100".parse().unwrap()));
+    }
+
+    #[test]
+    fn login_requires_account_file() {
+        let out = Config::load(args(&["--mode", "manual", "--server", "a", "--login"]));
+        assert!(out.is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(toml::from_str::<FileConfig>("servr = \"typo\"\n").is_err());
+    }
+
+    #[test]
+    fn rejects_inverted_port_range() {
+        let out = Settings::merge(
+            args(&["--mode", "auto", "--port-min", "2", "--port-max", "1"]),
+            FileConfig::default(),
+        );
+        assert!(out.is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use std::{process::ExitCode, sync::Arc};
+
+use clap::Parser;
+use tokio::{net::TcpListener, sync::Mutex};
+use voxelproxy_core::{
+    Sink,
+    auth::{Account, DeviceLogin, UpstreamAuth},
+    inspector::PacketInspector,
+    logger::Logger,
+    mcpr,
+    metrics::{self, SessionMetrics},
+    protocols::definitions,
+    replay::Replay,
+    session::{self, SessionOptions},
+};
+
+mod config;
+mod sink;
+
+use config::{AccountConfig, Args, Config, Settings};
+use sink::StdoutSink;
+
+/// Invalid flags or config file (clap uses the same code for usage errors).
+const EXIT_CONFIG: u8 = 2;
+/// The session ended with an error.
+const EXIT_SESSION: u8 = 1;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let Config {
+        settings,
+        versions_dir,
+        account,
+        routing,
+        resync_clicks,
+        capture_dir,
+        inspector: inspect,
+        metrics_addr,
+    } = match Config::load(Args::parse()) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("[ERR ] {:#}", e);
+            return ExitCode::from(EXIT_CONFIG);
+        }
+    };
+
+    let sink: Sink = Arc::new(StdoutSink::default());
+    let log = Logger::new(&*sink);
+
+    if let Some(dir) = &versions_dir {
+        if !dir.is_dir() {
+            log.error(format!("Папка с версиями не найдена: {}", dir.display()));
+            return ExitCode::from(EXIT_CONFIG);
+        }
+        definitions::load_dir(dir, &log);
+    }
+
+    let auth = match &account {
+        Some(account) => match load_account(account, &log).await {
+            Ok(auth) => Some(auth),
+            Err(e) => {
+                log.error(format!("{:#}", e));
+                return ExitCode::from(EXIT_CONFIG);
+            }
+        },
+        None => None,
+    };
+    let session_metrics = SessionMetrics::default();
+    if let Some(addr) = metrics_addr {
+        match TcpListener::bind(addr).await {
+            Ok(listener) => {
+                log.info(format!("Метрики: http://{}/metrics", addr));
+                tokio::spawn(metrics::serve(listener, session_metrics.clone()));
+            }
+            Err(e) => {
+                log.error(format!("Не удалось открыть {} для метрик: {}", addr, e));
+                return ExitCode::from(EXIT_CONFIG);
+            }
+        }
+    }
+
+    let inspector = PacketInspector::default();
+    if let Some(filter) = inspect {
+        inspector.start(filter);
+    }
+    let options = SessionOptions {
+        auth,
+        routing,
+        resync_clicks,
+        capture_dir,
+        inspector,
+        metrics: session_metrics,
+        ..Default::default()
+    };
+
+    let session = async {
+        match settings {
+            Settings::Manual { server, bind } => {
+                log.info(format!("Ручной режим: {} → {}", bind, server));
+                session::run_manual_mode(server, bind, sink.clone(), options).await
+            }
+            Settings::Auto {
+                windivert,
+                port_min,
+                port_max,
+                bind,
+            } => {
+                log.info(format!("Авторежим на {}", bind));
+                session::run_automatic_mode(
+                    windivert,
+                    port_min,
+                    port_max,
+                    bind,
+                    sink.clone(),
+                    Arc::new(Mutex::new(false)),
+                    options,
+                )
+                .await
+            }
+            Settings::Replay {
+                recording,
+                speed,
+                bind,
+            } => {
+                let replay = Replay::load(&recording)?;
+                let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+                let upstream = listener.local_addr()?;
+                log.info(format!(
+                    "Воспроизведение {} (протокол {}) на {}",
+                    recording.display(),
+                    replay.protocol(),
+                    bind
+                ));
+                tokio::spawn(Arc::new(replay).serve(listener, speed, sink.clone()));
+                session::run_manual_mode(upstream.to_string(), bind, sink.clone(), options).await
+            }
+            Settings::Export {
+                recording,
+                output,
+                server_name,
+            } => {
+                mcpr::export(&recording, &output, &server_name)?;
+                log.success(format!("Повтор ReplayMod сохранён: {}", output.display()));
+                Ok(())
+            }
+        }
+    };
+
+    // Dropping the session future on a signal aborts it the same way the desktop
+    // app's "Stop" button does: sockets close and both clients are marked offline.
+    tokio::select! {
+        result = session => match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                log.error(format!("{}", e));
+                ExitCode::from(EXIT_SESSION)
+            }
+        },
+        signal = shutdown_signal() => {
+            log.info(format!("Получен {}, завершение", signal));
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// Signs in (with `--login`) or refreshes the saved account, then writes the
+/// new tokens back so the next run starts from them.
+async fn load_account(config: &AccountConfig, log: &Logger<'_>) -> anyhow::Result<UpstreamAuth> {
+    let account = if config.login {
+        let login = DeviceLogin::start().await?;
+        log.info(format!(
+            "Откройте {} и введите код {}",
+            login.verification_uri, login.user_code
+        ));
+        login.finish().await?
+    } else {
+        let saved = Account::load(&config.path)?;
+        match saved.refresh().await {
+            Ok(account) => account,
+            Err(e) => {
+                log.warn(format!("Не удалось обновить токен: {:#}", e));
+                saved
+            }
+        }
+    };
+    account.save(&config.path)?;
+    log.success(format!("Аккаунт: {}", account.name));
+
+    Ok(UpstreamAuth {
+        account,
+        session_server: config.session_server.clone(),
+    })
+}
+
+/// Resolves with the name of the first shutdown signal received.
+#[cfg(unix)]
+async fn shutdown_signal() -> &'static str {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut term = signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+        _ = term.recv() => "SIGTERM",
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> &'static str {
+    let _ = tokio::signal::ctrl_c().await;
+    "Ctrl+C"
+}
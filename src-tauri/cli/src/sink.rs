@@ -0,0 +1,99 @@
+use std::{sync::Mutex, time::Duration};
+
+use voxelproxy_core::{
+    ClientId, EventSink, LogLevel,
+    inspector::{Outcome, PacketEvent},
+    latency::Latency,
+};
+
+/// Prints engine events to the terminal: errors go to stderr, everything else
+/// to stdout, one line per event.
+#[derive(Default)]
+pub struct StdoutSink {
+    /// Last reported `online` state of (primary, secondary). The engine re-sends
+    /// `online: false` when a session ends; the UI ignores the repeat, so do we.
+    online: Mutex<(bool, bool)>,
+}
+
+impl EventSink for StdoutSink {
+    fn log(&self, level: LogLevel, message: String) {
+        match level {
+            LogLevel::Info => println!("[INFO] {}", message),
+            LogLevel::Success => println!("[ OK ] {}", message),
+            LogLevel::Warn => println!("[WARN] {}", message),
+            LogLevel::Error => eprintln!("[ERR ] {}", message),
+        }
+    }
+
+    fn client_status(&self, which: ClientId, online: bool) {
+        {
+            let mut state = self.online.lock().unwrap();
+            let slot = match which {
+                ClientId::Primary => &mut state.0,
+                ClientId::Secondary => &mut state.1,
+            };
+            if *slot == online {
+                return;
+            }
+            *slot = online;
+        }
+        let which = match which {
+            ClientId::Primary => "Основной",
+            ClientId::Secondary => "Дополнительный",
+        };
+        let status = if online {
+            "подключён"
+        } else {
+            "отключён"
+        };
+        println!("[INFO] {} клиент {}", which, status);
+    }
+
+    fn nickname(&self, nickname: String) {
+        println!("[INFO] Ник: {}", nickname);
+    }
+
+    fn server_addr(&self, addr: String) {
+        println!("[INFO] Сервер: {}", addr);
+    }
+
+    fn packet(&self, event: PacketEvent) {
+        let route = match event.client {
+            Some(ClientId::Primary) => "основной → сервер",
+            Some(ClientId::Secondary) => "дополнительный → сервер",
+            None => "сервер → клиенты",
+        };
+        let outcome = match event.outcome {
+            Outcome::Relayed => "передан",
+            Outcome::Mirrored => "передан и отражён неактивному",
+            Outcome::Skipped => "задержан",
+            Outcome::Dropped => "отброшен",
+        };
+        let name = event
+            .name
+            .map(|name| format!(" {}", name))
+            .unwrap_or_default();
+        let suppressed = if event.suppressed > 0 {
+            format!(" (пропущено ещё {})", event.suppressed)
+        } else {
+            String::new()
+        };
+        println!(
+            "[PKT ] {} {:?} 0x{:02X}{} {} Б: {}{}",
+            route, event.state, event.packet_id, name, event.size, outcome, suppressed
+        );
+    }
+
+    fn latency(&self, latency: Latency) {
+        let millis = |rtt: Option<Duration>| {
+            rtt.map(|rtt| format!("{} мс", rtt.as_millis()))
+                .unwrap_or_else(|| "—".to_string())
+        };
+        println!(
+            "[PING] сервер {}, основной {}, дополнительный {}",
+            millis(latency.upstream),
+            millis(latency.primary),
+            millis(latency.secondary)
+        );
+    }
+}
@@ -0,0 +1,6 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use voxelproxy_core_fuzz::{HandlerInput, run_handlers};
+
+fuzz_target!(|input: HandlerInput| run_handlers(input));
@@ -0,0 +1,113 @@
+//! Harnesses shared by the libFuzzer targets in `fuzz_targets/` and the
+//! corpus replay in `tests/regressions.rs`.
+//!
+//! ```sh
+//! cd src-tauri/core/fuzz
+//! cargo +nightly fuzz run handlers
+//! cargo +nightly fuzz run login
+//! cargo test    # replays corpus/ on stable
+//! ```
+//!
+//! Inputs that once crashed a target belong in `corpus/<target>/` so the
+//! replay keeps them fixed.
+
+use arbitrary::Arbitrary;
+use mc_protocol::packet::RawPacket;
+use voxelproxy_core::{
+    ClientId,
+    protocols::{Version, VersionProtocol},
+    proxy::relay_login,
+};
+
+/// One step of a session as the controller would drive it.
+#[derive(Arbitrary, Debug)]
+pub enum Step {
+    /// A frame read from a client.
+    Client { secondary: bool, data: Vec<u8> },
+    /// A frame read from the upstream server.
+    Server { data: Vec<u8> },
+    /// A client connection dropped.
+    Disconnect { secondary: bool },
+    /// A client connected to the running session in place of one that left.
+    Join { secondary: bool },
+    /// The threshold negotiated during login.
+    Threshold(Option<u16>),
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct HandlerInput {
+    pub steps: Vec<Step>,
+}
+
+fn client(secondary: bool) -> ClientId {
+    if secondary {
+        ClientId::Secondary
+    } else {
+        ClientId::Primary
+    }
+}
+
+/// Replays `input` against every supported version, tracking the active client
+/// and liveness flags the same way `Controller::run` does.
+pub fn run_handlers(input: HandlerInput) {
+    for protocol in Version::supported_protocols() {
+        let mut version = Version::from_protocol(protocol).expect("listed protocol");
+        version.handle_login_success(RawPacket::new(vec![0x02]));
+        let mut active = ClientId::Primary;
+        let mut online = [true, true];
+
+        for step in &input.steps {
+            let both_active = online[0] && online[1];
+            match step {
+                Step::Client { secondary, data } => {
+                    if !online[*secondary as usize] {
+                        continue;
+                    }
+                    let id = client(*secondary);
+                    version.handle_c2s(
+                        &RawPacket::new(data.clone()),
+                        id,
+                        id == active,
+                        both_active,
+                    );
+                }
+                Step::Server { data } => {
+                    version.handle_s2c(&RawPacket::new(data.clone()), both_active);
+                }
+                Step::Disconnect { secondary } => {
+                    online[*secondary as usize] = false;
+                    if !(online[0] || online[1]) {
+                        break;
+                    }
+                    if active == client(*secondary) {
+                        active = active.opposite();
+                        version.handle_client_disconnect(active);
+                    }
+                }
+                Step::Join { secondary } => {
+                    if !online[*secondary as usize]
+                        && version.handle_late_join(client(*secondary)).is_ok()
+                    {
+                        online[*secondary as usize] = true;
+                    }
+                }
+                Step::Threshold(threshold) => {
+                    version.update_threshold(threshold.map(i32::from));
+                }
+            }
+        }
+    }
+}
+
+/// Feeds `data` to the login relay as everything the server sends before the
+/// connection closes.
+pub fn run_login(data: &[u8]) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("tokio runtime");
+    runtime.block_on(async {
+        let mut remote = data;
+        let (mut primary, mut secondary) = (tokio::io::sink(), tokio::io::sink());
+        let _ = relay_login(&mut remote, &mut primary, &mut secondary, None).await;
+    });
+}
@@ -0,0 +1,36 @@
+//! Replays every saved input through its harness without libFuzzer, so past
+//! crashes stay fixed on a stable toolchain.
+
+use std::{fs, path::Path};
+
+use arbitrary::{Arbitrary, Unstructured};
+use voxelproxy_core_fuzz::{HandlerInput, run_handlers, run_login};
+
+fn corpus(target: &str) -> Vec<Vec<u8>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("corpus")
+        .join(target);
+    let inputs: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("{}: {}", dir.display(), e))
+        .map(|entry| fs::read(entry.unwrap().path()).unwrap())
+        .collect();
+    assert!(!inputs.is_empty(), "{} is empty", dir.display());
+    inputs
+}
+
+#[test]
+fn handlers() {
+    for data in corpus("handlers") {
+        // Same decoding `fuzz_target!` applies to a typed input.
+        if let Ok(input) = HandlerInput::arbitrary_take_rest(Unstructured::new(&data)) {
+            run_handlers(input);
+        }
+    }
+}
+
+#[test]
+fn login() {
+    for data in corpus("login") {
+        run_login(&data);
+    }
+}
@@ -0,0 +1,359 @@
+//! Logging in to online-mode servers on the player's behalf.
+//!
+//! An [`Account`] comes from a Microsoft device-code login ([`DeviceLogin`]):
+//! the player types a short code on microsoft.com, and the Microsoft token is
+//! traded through Xbox Live for a Minecraft one. When an upstream server sends
+//! `EncryptionRequest`, the proxy reports the join to the session server with
+//! [`join_server`], exactly as the vanilla client does.
+
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, anyhow};
+use openssl::sha::Sha1;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+/// Mojang's session server. Tests and self-hosted auth servers point
+/// [`UpstreamAuth::session_server`] elsewhere.
+pub const DEFAULT_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+/// Client id of the vanilla launcher, the only one Xbox Live accepts
+/// `MBI_SSL` tickets from without a registered redirect.
+const MSA_CLIENT_ID: &str = "00000000402b5328";
+const MSA_SCOPE: &str = "service::user.auth.xboxlive.com::MBI_SSL";
+const MSA_DEVICE_CODE_URL: &str = "https://login.live.com/oauth20_connect.srf";
+const MSA_TOKEN_URL: &str = "https://login.live.com/oauth20_token.srf";
+const XBL_URL: &str = "https://user.auth.xboxlive.com/user/authenticate";
+const XSTS_URL: &str = "https://xsts.auth.xboxlive.com/xsts/authorize";
+const MC_LOGIN_URL: &str = "https://api.minecraftservices.com/authentication/login_with_xbox";
+const MC_PROFILE_URL: &str = "https://api.minecraftservices.com/minecraft/profile";
+
+/// A Minecraft: Java Edition account the proxy joins servers with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub name: String,
+    pub uuid: Uuid,
+    /// Minecraft access token; expires about a day after it was issued.
+    pub access_token: String,
+    /// Microsoft refresh token used by [`Account::refresh`].
+    pub refresh_token: String,
+}
+
+/// What a session needs to join online-mode servers.
+#[derive(Debug, Clone)]
+pub struct UpstreamAuth {
+    pub account: Account,
+    /// Base URL of the session server, e.g. [`DEFAULT_SESSION_SERVER`].
+    pub session_server: String,
+}
+
+/// A started device-code login, waiting for the player to enter
+/// [`DeviceLogin::user_code`] at [`DeviceLogin::verification_uri`].
+pub struct DeviceLogin {
+    pub user_code: String,
+    pub verification_uri: String,
+    device_code: String,
+    interval: Duration,
+    expires_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct DeviceCodeResponse {
+    user_code: String,
+    device_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+#[derive(Deserialize)]
+struct MsaToken {
+    access_token: String,
+    refresh_token: String,
+}
+
+#[derive(Deserialize)]
+struct MsaError {
+    error: String,
+}
+
+impl DeviceLogin {
+    /// Asks Microsoft for a code to show the player.
+    pub async fn start() -> anyhow::Result<Self> {
+        let response: DeviceCodeResponse = reqwest::Client::new()
+            .post(MSA_DEVICE_CODE_URL)
+            .form(&[
+                ("client_id", MSA_CLIENT_ID),
+                ("scope", MSA_SCOPE),
+                ("response_type", "device_code"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Некорректный ответ Microsoft")?;
+
+        Ok(Self {
+            user_code: response.user_code,
+            verification_uri: response.verification_uri,
+            device_code: response.device_code,
+            interval: Duration::from_secs(response.interval.max(1)),
+            expires_at: Instant::now() + Duration::from_secs(response.expires_in),
+        })
+    }
+
+    /// Waits for the player to confirm the code, then completes the login.
+    pub async fn finish(self) -> anyhow::Result<Account> {
+        let client = reqwest::Client::new();
+        let mut interval = self.interval;
+
+        let token = loop {
+            if Instant::now() >= self.expires_at {
+                anyhow::bail!("Код входа истёк, начните вход заново");
+            }
+            tokio::time::sleep(interval).await;
+
+            let response = client
+                .post(MSA_TOKEN_URL)
+                .form(&[
+                    ("client_id", MSA_CLIENT_ID),
+                    ("device_code", self.device_code.as_str()),
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ])
+                .send()
+                .await?;
+            if response.status().is_success() {
+                break response.json::<MsaToken>().await?;
+            }
+
+            let error: MsaError = response
+                .json()
+                .await
+                .context("Некорректный ответ Microsoft")?;
+            match error.error.as_str() {
+                "authorization_pending" => {}
+                "slow_down" => interval += Duration::from_secs(5),
+                "authorization_declined" | "access_denied" => {
+                    anyhow::bail!("Вход отклонён пользователем")
+                }
+                "expired_token" => anyhow::bail!("Код входа истёк, начните вход заново"),
+                other => anyhow::bail!("Ошибка входа Microsoft: {}", other),
+            }
+        };
+
+        minecraft_login(&client, token).await
+    }
+}
+
+impl Account {
+    /// Reads an account written by [`Account::save`].
+    pub fn load(path: &Path) -> anyhow::Result<Account> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Не удалось прочитать {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("Повреждён файл аккаунта {}", path.display()))
+    }
+
+    /// Writes the account, tokens included, as JSON.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Не удалось сохранить {}", path.display()))
+    }
+
+    /// Trades the refresh token for a new Minecraft access token. The returned
+    /// account should replace the stored one: Microsoft rotates refresh tokens.
+    pub async fn refresh(&self) -> anyhow::Result<Account> {
+        let client = reqwest::Client::new();
+        let token: MsaToken = client
+            .post(MSA_TOKEN_URL)
+            .form(&[
+                ("client_id", MSA_CLIENT_ID),
+                ("scope", MSA_SCOPE),
+                ("grant_type", "refresh_token"),
+                ("refresh_token", self.refresh_token.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()
+            .context("Сессия Microsoft истекла, войдите в аккаунт заново")?
+            .json()
+            .await?;
+
+        minecraft_login(&client, token).await
+    }
+}
+
+/// Microsoft token → Xbox Live → XSTS → Minecraft token and profile.
+async fn minecraft_login(client: &reqwest::Client, msa: MsaToken) -> anyhow::Result<Account> {
+    let xbl: Value = client
+        .post(XBL_URL)
+        .json(&json!({
+            "Properties": {
+                "AuthMethod": "RPS",
+                "SiteName": "user.auth.xboxlive.com",
+                "RpsTicket": msa.access_token,
+            },
+            "RelyingParty": "http://auth.xboxlive.com",
+            "TokenType": "JWT",
+        }))
+        .send()
+        .await?
+        .error_for_status()
+        .context("Xbox Live отклонил вход")?
+        .json()
+        .await?;
+    let xbl_token = json_str(&xbl, "/Token")?;
+
+    let response = client
+        .post(XSTS_URL)
+        .json(&json!({
+            "Properties": {
+                "SandboxId": "RETAIL",
+                "UserTokens": [xbl_token],
+            },
+            "RelyingParty": "rp://api.minecraftservices.com/",
+            "TokenType": "JWT",
+        }))
+        .send()
+        .await?;
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let body: Value = response.json().await.unwrap_or_default();
+        anyhow::bail!(match body["XErr"].as_u64() {
+            Some(2148916233) => "У аккаунта нет профиля Xbox".to_string(),
+            Some(2148916238) => "Детский аккаунт: добавьте его в семью Microsoft".to_string(),
+            code => format!("Xbox Live отклонил вход ({:?})", code),
+        });
+    }
+    let xsts: Value = response.error_for_status()?.json().await?;
+    let xsts_token = json_str(&xsts, "/Token")?;
+    let user_hash = json_str(&xsts, "/DisplayClaims/xui/0/uhs")?;
+
+    let login: Value = client
+        .post(MC_LOGIN_URL)
+        .json(&json!({
+            "identityToken": format!("XBL3.0 x={};{}", user_hash, xsts_token),
+        }))
+        .send()
+        .await?
+        .error_for_status()
+        .context("Сервис Minecraft отклонил вход")?
+        .json()
+        .await?;
+    let access_token = json_str(&login, "/access_token")?.to_string();
+
+    let response = client
+        .get(MC_PROFILE_URL)
+        .bearer_auth(&access_token)
+        .send()
+        .await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        anyhow::bail!("На аккаунте нет лицензии Minecraft: Java Edition");
+    }
+    let profile: Value = response.error_for_status()?.json().await?;
+
+    Ok(Account {
+        name: json_str(&profile, "/name")?.to_string(),
+        uuid: json_str(&profile, "/id")?.parse()?,
+        access_token,
+        refresh_token: msa.refresh_token,
+    })
+}
+
+fn json_str<'a>(value: &'a Value, pointer: &str) -> anyhow::Result<&'a str> {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("В ответе сервиса входа нет поля {}", pointer))
+}
+
+/// Tells the session server that `account` is joining the server identified by
+/// `server_hash`; the server checks this before letting the player in.
+pub async fn join_server(auth: &UpstreamAuth, server_hash: &str) -> anyhow::Result<()> {
+    let url = format!(
+        "{}/session/minecraft/join",
+        auth.session_server.trim_end_matches('/')
+    );
+    let response = reqwest::Client::new()
+        .post(url)
+        .json(&json!({
+            "accessToken": auth.account.access_token,
+            "selectedProfile": auth.account.uuid.simple().to_string(),
+            "serverId": server_hash,
+        }))
+        .send()
+        .await
+        .context("Сервер сессий недоступен")?;
+
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::UNAUTHORIZED => Err(anyhow!(
+            "Сервер сессий отклонил токен, войдите в аккаунт заново"
+        )),
+        status => Err(anyhow!("Сервер сессий ответил {}", status)),
+    }
+}
+
+/// Minecraft's server hash: SHA-1 of the server id, the shared secret and the
+/// server's public key, printed as a signed (two's complement) hex number.
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let mut sha = Sha1::new();
+    sha.update(server_id.as_bytes());
+    sha.update(shared_secret);
+    sha.update(public_key);
+    let mut digest = sha.finish();
+
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                (*byte, carry) = byte.overflowing_add(1);
+            }
+        }
+    }
+
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    let hex = hex.trim_start_matches('0');
+    if negative {
+        format!("-{}", hex)
+    } else {
+        hex.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(name: &str) -> String {
+        server_hash(name, &[], &[])
+    }
+
+    #[test]
+    fn server_hash_matches_vanilla() {
+        assert_eq!(hash("Notch"), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
+        assert_eq!(hash("jeb_"), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+        assert_eq!(hash("simon"), "88e16a1019277b15d58faf0541e11910eb756f6");
+    }
+
+    #[test]
+    fn account_round_trips_through_json() {
+        let account = Account {
+            name: "VoxelTester".to_string(),
+            uuid: Uuid::from_u128(0x1234),
+            access_token: "token".to_string(),
+            refresh_token: "refresh".to_string(),
+        };
+        let json = serde_json::to_string(&account).unwrap();
+        assert_eq!(serde_json::from_str::<Account>(&json).unwrap(), account);
+    }
+}
@@ -0,0 +1,338 @@
+//! Session recordings: every packet the proxy reads from the clients and the
+//! server, decompressed, in a pcapng file Wireshark opens as is.
+//!
+//! The file has one section and one interface of link type `USER0` (147).
+//! Each packet is an Enhanced Packet Block timestamped in microseconds, whose
+//! data is an 8-byte header and then the packet id as a VarInt and the
+//! payload, i.e. the uncompressed frame without its length prefix:
+//!
+//! | Offset | Size | Field                                              |
+//! |--------|------|----------------------------------------------------|
+//! | 0      | 1    | direction: 0 serverbound, 1 clientbound            |
+//! | 1      | 1    | client: 0 primary, 1 secondary, 255 the server     |
+//! | 2      | 1    | state: 0 Login, 1 Configuration, 2 Game            |
+//! | 3      | 1    | reserved, always 0                                 |
+//! | 4      | 4    | protocol number, big-endian                        |
+//!
+//! A recording starts with the server's `LoginSuccess`; the handshake and the
+//! rest of the login are not part of it. Packets the proxy writes itself
+//! (mirrored positions, resync clicks, `#vp` replies) are not recorded either.
+//! `tools/wireshark/voxelproxy.lua` dissects the header in Wireshark.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail};
+use mc_protocol::{
+    packet::UncompressedPacket,
+    ser::{Deserialize, Serialize},
+    varint::VarInt,
+};
+
+use crate::{ClientId, protocols::State};
+
+/// Link type of the records, `LINKTYPE_USER0`.
+pub const LINK_TYPE: u16 = 147;
+/// File extension of a recording.
+pub const EXTENSION: &str = "pcapng";
+
+const SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION: u32 = 1;
+const ENHANCED_PACKET: u32 = 6;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const SHB_USERAPPL: u16 = 4;
+const HEADER_LEN: usize = 8;
+const SERVER: u8 = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Serverbound,
+    Clientbound,
+}
+
+/// One recorded packet.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub time: SystemTime,
+    pub direction: Direction,
+    /// The client that sent a serverbound packet; `None` for the server's.
+    pub client: Option<ClientId>,
+    pub state: State,
+    pub protocol: i32,
+    pub packet: UncompressedPacket,
+}
+
+/// Writes records to `out` as pcapng.
+pub struct CaptureWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Starts the file: the section header and the interface every record
+    /// goes to.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        let mut options = Vec::new();
+        let application = format!("VoxelProxy {}", env!("CARGO_PKG_VERSION"));
+        push_option(&mut options, SHB_USERAPPL, application.as_bytes());
+        options.extend_from_slice(&[0; 4]);
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        body.extend_from_slice(&(-1i64).to_le_bytes());
+        body.extend_from_slice(&options);
+        write_block(&mut out, SECTION_HEADER, &body)?;
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&LINK_TYPE.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes());
+        write_block(&mut out, INTERFACE_DESCRIPTION, &body)?;
+
+        Ok(Self { out })
+    }
+
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        let mut data = Vec::with_capacity(HEADER_LEN + 5 + record.packet.payload.len());
+        data.push(match record.direction {
+            Direction::Serverbound => 0,
+            Direction::Clientbound => 1,
+        });
+        data.push(match record.client {
+            Some(ClientId::Primary) => 0,
+            Some(ClientId::Secondary) => 1,
+            None => SERVER,
+        });
+        data.push(match record.state {
+            State::Login => 0,
+            State::Configuration => 1,
+            State::Game => 2,
+        });
+        data.push(0);
+        data.extend_from_slice(&record.protocol.to_be_bytes());
+        VarInt(record.packet.packet_id)
+            .serialize(&mut data)
+            .map_err(io::Error::other)?;
+        data.extend_from_slice(&record.packet.payload);
+
+        let micros = record
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let mut body = Vec::with_capacity(20 + data.len() + 3);
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(micros as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&data);
+        pad(&mut body);
+        write_block(&mut self.out, ENHANCED_PACKET, &body)
+    }
+}
+
+/// A recording of the running session, written to a new file.
+pub type Capture = CaptureWriter<BufWriter<File>>;
+
+impl Capture {
+    /// Starts a recording in `dir`, named after the current time.
+    pub fn create(dir: &Path) -> io::Result<(Self, PathBuf)> {
+        fs::create_dir_all(dir)?;
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = dir.join(format!("session-{}.{}", millis, EXTENSION));
+        let writer = CaptureWriter::new(BufWriter::new(File::create(&path)?))?;
+        Ok((writer, path))
+    }
+}
+
+/// Reads back a recording written by [`CaptureWriter`]. Blocks of other types
+/// are skipped.
+pub fn read<R: Read>(mut input: R) -> anyhow::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    let mut started = false;
+    loop {
+        let mut head = [0u8; 8];
+        match input.read_exact(&mut head) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let block_type = u32::from_le_bytes(head[..4].try_into().unwrap());
+        let len = u32::from_le_bytes(head[4..].try_into().unwrap()) as usize;
+        if len < 12 || !len.is_multiple_of(4) {
+            bail!("Повреждённый блок pcapng длиной {}", len);
+        }
+        // Read what's there rather than allocate the length up front: a
+        // damaged file can claim up to 4 GiB.
+        let mut body = Vec::new();
+        input.by_ref().take(len as u64 - 8).read_to_end(&mut body)?;
+        if body.len() < len - 8 {
+            bail!("Запись обрывается посреди блока");
+        }
+        let body = &body[..len - 12];
+
+        match block_type {
+            SECTION_HEADER => {
+                if body.len() < 4
+                    || u32::from_le_bytes(body[..4].try_into().unwrap()) != BYTE_ORDER_MAGIC
+                {
+                    bail!("Поддерживаются только файлы pcapng с порядком байт little-endian");
+                }
+                started = true;
+            }
+            _ if !started => bail!("Файл не является записью pcapng"),
+            ENHANCED_PACKET => records.push(parse_packet(body)?),
+            _ => {}
+        }
+    }
+    Ok(records)
+}
+
+fn parse_packet(body: &[u8]) -> anyhow::Result<Record> {
+    if body.len() < 20 {
+        bail!("Повреждённый пакет в записи");
+    }
+    let word = |i: usize| u32::from_le_bytes(body[i..i + 4].try_into().unwrap());
+    let micros = (u64::from(word(4)) << 32) | u64::from(word(8));
+    let captured = word(12) as usize;
+    let Some(data) = body.get(20..20 + captured) else {
+        bail!("Повреждённый пакет в записи");
+    };
+    if data.len() < HEADER_LEN {
+        bail!("Пакет записи короче заголовка");
+    }
+
+    let direction = match data[0] {
+        0 => Direction::Serverbound,
+        1 => Direction::Clientbound,
+        other => bail!("Неизвестное направление пакета: {}", other),
+    };
+    let client = match data[1] {
+        0 => Some(ClientId::Primary),
+        1 => Some(ClientId::Secondary),
+        SERVER => None,
+        other => bail!("Неизвестный клиент пакета: {}", other),
+    };
+    let state = match data[2] {
+        0 => State::Login,
+        1 => State::Configuration,
+        2 => State::Game,
+        other => bail!("Неизвестное состояние пакета: {}", other),
+    };
+    let protocol = i32::from_be_bytes(data[4..8].try_into().unwrap());
+    let mut frame = &data[HEADER_LEN..];
+    let packet_id = VarInt::deserialize(&mut frame)
+        .map_err(|e| anyhow!("Повреждённый id пакета в записи: {}", e))?
+        .0;
+
+    Ok(Record {
+        time: UNIX_EPOCH + Duration::from_micros(micros),
+        direction,
+        client,
+        state,
+        protocol,
+        packet: UncompressedPacket::new(packet_id, frame.to_vec()),
+    })
+}
+
+fn push_option(options: &mut Vec<u8>, code: u16, value: &[u8]) {
+    options.extend_from_slice(&code.to_le_bytes());
+    options.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    options.extend_from_slice(value);
+    pad(options);
+}
+
+fn pad(bytes: &mut Vec<u8>) {
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+}
+
+fn write_block<W: Write>(out: &mut W, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let len = (12 + body.len()) as u32;
+    out.write_all(&block_type.to_le_bytes())?;
+    out.write_all(&len.to_le_bytes())?;
+    out.write_all(body)?;
+    out.write_all(&len.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(direction: Direction, client: Option<ClientId>, payload: &[u8]) -> Record {
+        Record {
+            time: UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456),
+            direction,
+            client,
+            state: State::Game,
+            protocol: 774,
+            packet: UncompressedPacket::new(300, payload.to_vec()),
+        }
+    }
+
+    #[test]
+    fn records_read_back() {
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        let written = [
+            record(Direction::Clientbound, None, &[1, 2, 3]),
+            record(Direction::Serverbound, Some(ClientId::Secondary), &[]),
+        ];
+        for record in &written {
+            writer.write(record).unwrap();
+        }
+        assert!(writer.out.len().is_multiple_of(4));
+
+        let read = read(writer.out.as_slice()).unwrap();
+        assert_eq!(read.len(), 2);
+        for (read, written) in read.iter().zip(&written) {
+            assert_eq!(read.time, written.time);
+            assert_eq!(read.direction, written.direction);
+            assert_eq!(read.client, written.client);
+            assert_eq!(read.state, written.state);
+            assert_eq!(read.protocol, 774);
+            assert_eq!(read.packet.packet_id, 300);
+            assert_eq!(read.packet.payload, written.packet.payload);
+        }
+    }
+
+    #[test]
+    fn packet_data_follows_the_documented_header() {
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        let start = writer.out.len();
+        writer
+            .write(&record(
+                Direction::Serverbound,
+                Some(ClientId::Primary),
+                &[9],
+            ))
+            .unwrap();
+        let block = &writer.out[start..];
+        // Block type, length, interface, timestamp, lengths, then the data.
+        assert_eq!(&block[..4], &ENHANCED_PACKET.to_le_bytes());
+        assert_eq!(&block[28..39], &[0, 0, 2, 0, 0, 0, 3, 6, 0xAC, 0x02, 9]);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(read(&b"not a capture at all"[..]).is_err());
+        assert!(read(&[][..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_a_block_longer_than_the_file() {
+        let mut file = CaptureWriter::new(Vec::new()).unwrap().out;
+        file.extend(ENHANCED_PACKET.to_le_bytes());
+        file.extend((u32::MAX - 3).to_le_bytes());
+        file.extend([0; 16]);
+        let err = read(file.as_slice()).unwrap_err().to_string();
+        assert!(err.contains("обрывается"), "{}", err);
+    }
+}
@@ -0,0 +1,96 @@
+//! `#vp ...` commands a player types in chat to steer the proxy without
+//! leaving the game. The server never sees them; the controller runs them and
+//! answers in the sender's chat.
+
+use crate::controller::ClientId;
+
+const PREFIX: &str = "#vp";
+
+pub const HELP: &str = "#vp switch [primary|secondary], #vp status, #vp kick [primary|secondary]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatCommand {
+    /// Hand control to the named client, or to the inactive one.
+    Switch(Option<ClientId>),
+    /// Who is connected and who is in control.
+    Status,
+    /// Disconnect the named client, or the inactive one.
+    Kick(Option<ClientId>),
+    Help,
+}
+
+impl ChatCommand {
+    /// Whether `message` is meant for the proxy rather than the server.
+    pub fn is_addressed(message: &str) -> bool {
+        let mut words = message.split_whitespace();
+        words
+            .next()
+            .is_some_and(|word| word.eq_ignore_ascii_case(PREFIX))
+    }
+
+    /// Parses a message [`is_addressed`](Self::is_addressed) to the proxy. The
+    /// error is the answer to show the player.
+    pub fn parse(message: &str) -> Result<ChatCommand, String> {
+        let message = message.to_lowercase();
+        let words: Vec<&str> = message.split_whitespace().skip(1).collect();
+        match words[..] {
+            [] | ["help"] => Ok(ChatCommand::Help),
+            ["status"] => Ok(ChatCommand::Status),
+            ["switch"] => Ok(ChatCommand::Switch(None)),
+            ["switch", client] => Ok(ChatCommand::Switch(Some(parse_client(client)?))),
+            ["kick"] => Ok(ChatCommand::Kick(None)),
+            ["kick", client] => Ok(ChatCommand::Kick(Some(parse_client(client)?))),
+            _ => Err(format!("Неизвестная команда. Доступны: {}", HELP)),
+        }
+    }
+}
+
+fn parse_client(word: &str) -> Result<ClientId, String> {
+    match word {
+        "primary" | "основной" | "1" => Ok(ClientId::Primary),
+        "secondary" | "второй" | "2" => Ok(ClientId::Secondary),
+        _ => Err(format!(
+            "Неизвестный клиент «{}»: укажите primary или secondary",
+            word
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_the_prefix_only_as_a_word() {
+        assert!(ChatCommand::is_addressed("#vp switch"));
+        assert!(ChatCommand::is_addressed("  #VP"));
+        assert!(!ChatCommand::is_addressed("#vpn is down"));
+        assert!(!ChatCommand::is_addressed("hello #vp"));
+        assert!(!ChatCommand::is_addressed(""));
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(ChatCommand::parse("#vp"), Ok(ChatCommand::Help));
+        assert_eq!(ChatCommand::parse("#vp status"), Ok(ChatCommand::Status));
+        assert_eq!(
+            ChatCommand::parse("#vp switch"),
+            Ok(ChatCommand::Switch(None))
+        );
+        assert_eq!(
+            ChatCommand::parse("#vp Kick Secondary"),
+            Ok(ChatCommand::Kick(Some(ClientId::Secondary)))
+        );
+        assert_eq!(
+            ChatCommand::parse("#vp switch основной"),
+            Ok(ChatCommand::Switch(Some(ClientId::Primary)))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_words() {
+        assert!(ChatCommand::parse("#vp fly").is_err());
+        assert!(ChatCommand::parse("#vp kick everyone").is_err());
+        assert!(ChatCommand::parse("#vp switch primary now").is_err());
+    }
+}
@@ -0,0 +1,917 @@
+use std::{
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::anyhow;
+use mc_protocol::packet::{RawPacket, UncompressedPacket};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::{
+        TcpStream,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    sync::mpsc::{self, Receiver, Sender, WeakSender, error::TrySendError},
+    task::JoinSet,
+    time::{MissedTickBehavior, interval},
+};
+
+use crate::{
+    capture::{Capture, Direction, Record},
+    chat_command::{self, ChatCommand},
+    inspector::{Outcome, PacketEvent, PacketInspector},
+    latency::Latency,
+    logger::Logger,
+    metrics::{STATS_INTERVAL, SessionMetrics, SessionStats, Traffic},
+    protocols::{
+        ClientDisconnectEvent, ServerBoundEvent, State, Version, VersionProtocol,
+        table::PacketCategory,
+    },
+    proxy::{IO_CHANNEL_CAPACITY, send_login_disconnect},
+    session::SessionOptions,
+    sink::Sink,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientId {
+    Primary,
+    Secondary,
+}
+
+impl ClientId {
+    pub fn opposite(&self) -> ClientId {
+        match self {
+            ClientId::Primary => ClientId::Secondary,
+            ClientId::Secondary => ClientId::Primary,
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            ClientId::Primary => "основной",
+            ClientId::Secondary => "дополнительный",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ControllerEvent {
+    ClientData(ClientId, RawPacket),
+    ClientDisconnected(ClientId),
+    ServerData(RawPacket),
+    ServerDisconnected,
+    Control(ControlMessage),
+    ClientJoined(LateClient),
+    /// The proxy's own ping of the server came back, or `None` if it was lost.
+    UpstreamLatency(Option<Duration>),
+}
+
+/// A client that connected after the session started. Its Handshake and
+/// LoginStart have been read; the rest of its login comes from the cache.
+#[derive(Debug)]
+pub struct LateClient {
+    stream: TcpStream,
+    protocol: i32,
+    /// The controller's own channel, for the client's I/O task to report to.
+    events: Sender<ControllerEvent>,
+}
+
+/// A request from outside the session, handled in the controller's loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlMessage {
+    /// Hand control to this client; the other one stays connected.
+    SwitchActive(ClientId),
+    /// Disconnect this client while the other one keeps playing.
+    Kick(ClientId),
+}
+
+/// Which of the inactive client's packets reach the server as well as the
+/// active client's, by category. Empty by default: only the active client plays.
+///
+/// The categories themselves come from the version's table, see
+/// [`Categories`](crate::protocols::table::Categories).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoutingPolicy {
+    pub inactive: Vec<PacketCategory>,
+}
+
+impl RoutingPolicy {
+    pub fn relays(&self, category: PacketCategory) -> bool {
+        self.inactive.contains(&category)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inactive.is_empty()
+    }
+}
+
+/// Handle a frontend keeps to steer whichever session is running.
+///
+/// Every new session attaches itself, so one handle outlives them all. It holds
+/// no strong reference to the session: a finished session simply stops
+/// accepting messages.
+#[derive(Debug, Clone, Default)]
+pub struct SessionControl {
+    controller: Arc<Mutex<Option<WeakSender<ControllerEvent>>>>,
+    /// Whether the session has lost a client it would take back.
+    vacant: Arc<AtomicBool>,
+}
+
+impl SessionControl {
+    /// Delivers `message` to the running session.
+    pub fn send(&self, message: ControlMessage) -> anyhow::Result<()> {
+        self.controller()
+            .ok_or_else(|| anyhow!("Нет активной сессии"))?
+            .try_send(ControllerEvent::Control(message))
+            .map_err(|_| anyhow!("Сессия не принимает команды"))
+    }
+
+    /// Whether a session is running with one client missing.
+    pub fn accepts_client(&self) -> bool {
+        self.vacant.load(Ordering::Relaxed) && self.controller().is_some()
+    }
+
+    /// Hands a client that connected mid-session to the running session,
+    /// which finishes its login itself or tells it why it can't. Gives the
+    /// stream back when there is no session to take it.
+    pub fn join(&self, stream: TcpStream, protocol: i32) -> Result<(), TcpStream> {
+        let Some(events) = self.controller() else {
+            return Err(stream);
+        };
+        let client = LateClient {
+            stream,
+            protocol,
+            events: events.clone(),
+        };
+        events
+            .try_send(ControllerEvent::ClientJoined(client))
+            .map_err(|e| match e {
+                TrySendError::Full(ControllerEvent::ClientJoined(client))
+                | TrySendError::Closed(ControllerEvent::ClientJoined(client)) => client.stream,
+                _ => unreachable!("sent a ClientJoined"),
+            })
+    }
+
+    pub(crate) fn attach(&self, tx: &Sender<ControllerEvent>) {
+        *self.controller.lock().unwrap() = Some(tx.downgrade());
+        self.vacant.store(false, Ordering::Relaxed);
+    }
+
+    fn set_vacant(&self, vacant: bool) {
+        self.vacant.store(vacant, Ordering::Relaxed);
+    }
+
+    fn controller(&self) -> Option<Sender<ControllerEvent>> {
+        self.controller
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|tx| tx.upgrade())
+    }
+}
+
+pub struct Controller {
+    active_client: ClientId,
+    /// Channel for sending packets to the Primary client.
+    primary_tx: Sender<RawPacket>,
+    /// Channel for sending packets to the Secondary client.
+    secondary_tx: Sender<RawPacket>,
+    /// Channel for sending packets to the upstream server.
+    remote_tx: Sender<RawPacket>,
+    /// Receives events (client data, disconnections, server data) from I/O tasks.
+    event_rx: Receiver<ControllerEvent>,
+    primary_active: bool,
+    secondary_active: bool,
+    /// Kicked clients whose read task hasn't reported the disconnect yet; their
+    /// slot stays closed to late joins until it does.
+    primary_closing: bool,
+    secondary_closing: bool,
+    version: Version,
+    sink: Sink,
+    control: SessionControl,
+    routing: RoutingPolicy,
+    resync_clicks: bool,
+    /// Recording of the packets read, when the session was asked for one.
+    capture: Option<Capture>,
+    inspector: PacketInspector,
+    metrics: SessionMetrics,
+    /// Counters since the session started; the gauges are filled in when
+    /// the stats are published.
+    stats: SessionStats,
+    started: Instant,
+    /// Latency last handed to the sink, which only hears of changes.
+    reported_latency: Latency,
+    /// I/O tasks of clients that joined late, aborted with the controller.
+    late_clients: JoinSet<()>,
+}
+
+impl Controller {
+    /// The primary client starts in control.
+    pub fn new(
+        primary_tx: Sender<RawPacket>,
+        secondary_tx: Sender<RawPacket>,
+        remote_tx: Sender<RawPacket>,
+        event_rx: Receiver<ControllerEvent>,
+        version: Version,
+        sink: Sink,
+        options: &SessionOptions,
+    ) -> Self {
+        let capture = options
+            .capture_dir
+            .as_deref()
+            .and_then(|dir| start_capture(dir, &Logger::new(&*sink)));
+        Self {
+            active_client: ClientId::Primary,
+            primary_tx,
+            secondary_tx,
+            remote_tx,
+            event_rx,
+            primary_active: true,
+            secondary_active: true,
+            primary_closing: false,
+            secondary_closing: false,
+            version,
+            sink,
+            control: options.control.clone(),
+            routing: options.routing.clone(),
+            resync_clicks: options.resync_clicks,
+            capture,
+            inspector: options.inspector.clone(),
+            metrics: options.metrics.clone(),
+            stats: SessionStats::default(),
+            started: Instant::now(),
+            reported_latency: Latency::default(),
+            late_clients: JoinSet::new(),
+        }
+    }
+
+    /// Main event loop. Runs until the channel closes (both I/O tasks have exited).
+    ///
+    /// Each iteration handles one of six event types:
+    /// - `ClientData`         — position sync, ping tracking, relay to server
+    /// - `ClientDisconnected` — update state, optionally switch active client & replay sync packets
+    /// - `ServerData`         — track new pings, broadcast to active clients
+    /// - `Control`            — hand control over or kick a client on request
+    /// - `ClientJoined`       — log a client in from the world cache as the inactive one
+    /// - `UpstreamLatency`    — note the server's round-trip time for the stats
+    ///
+    /// In between, the session's stats are published every [`STATS_INTERVAL`].
+    pub async fn run(mut self) {
+        let sink = self.sink.clone();
+        let log = Logger::new(&*sink);
+        sink.active_client(self.active_client);
+        if !self.routing.is_empty() {
+            let names: Vec<_> = self.routing.inactive.iter().map(|c| c.name()).collect();
+            log.info(format!(
+                "Неактивный клиент тоже отправляет на сервер: {}",
+                names.join(", ")
+            ));
+        }
+        if let Some(packet) = self.version.login_success() {
+            self.record(None, State::Login, packet, &log);
+        }
+        let mut stats_timer = interval(STATS_INTERVAL);
+        stats_timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            let event = tokio::select! {
+                event = self.event_rx.recv() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = stats_timer.tick() => {
+                    self.publish_stats();
+                    continue;
+                }
+            };
+            match event {
+                ControllerEvent::ClientData(client_id, packet) => {
+                    // A kicked client's last packets may still be in flight.
+                    if !self.is_connected(client_id) {
+                        continue;
+                    }
+                    self.traffic(client_id).serverbound.add(packet.data.len());
+                    self.capture(&packet, Some(client_id), &log);
+                    let seen = self.seen(&packet, Some(client_id));
+
+                    let event = self.version.handle_c2s(
+                        &packet,
+                        client_id,
+                        client_id == self.active_client,
+                        self.both_active(),
+                    );
+
+                    let mut skip = false;
+                    let mut mirrored = false;
+                    let mut follow_up = None;
+
+                    if let Some(event) = event {
+                        match event {
+                            ServerBoundEvent::SendToInactive(raw_packet) => {
+                                mirrored = true;
+                                self.send_to_inactive(raw_packet).await;
+                            }
+                            ServerBoundEvent::SkipRelay => skip = true,
+                            ServerBoundEvent::ProtocolMetadata { data, custom } => {
+                                sink.protocol_metadata(data, custom);
+                            }
+                            ServerBoundEvent::ChatCommand { message, ack } => {
+                                skip = true;
+                                if let Some(ack) = ack
+                                    && client_id == self.active_client
+                                    && !self.send_to_server(vec![ack], &log).await
+                                {
+                                    return;
+                                }
+                                if !self.run_chat_command(client_id, &message, &log).await {
+                                    return;
+                                }
+                            }
+                            ServerBoundEvent::ContainerClosed { mirror, resync } => {
+                                mirrored = true;
+                                for packet in mirror {
+                                    self.send_to_inactive(packet).await;
+                                }
+                                follow_up = resync.filter(|_| self.resync_clicks);
+                            }
+                        }
+                    }
+
+                    if skip {
+                        self.report(seen, Outcome::Skipped);
+                        continue;
+                    }
+
+                    let relay = client_id == self.active_client || self.routes(client_id, &packet);
+                    self.report(
+                        seen,
+                        match (relay, mirrored) {
+                            (false, _) => Outcome::Dropped,
+                            (true, true) => Outcome::Mirrored,
+                            (true, false) => Outcome::Relayed,
+                        },
+                    );
+                    if relay {
+                        self.stats.server.serverbound.add(packet.data.len());
+                        if let Err(e) = self.remote_tx.send(packet).await {
+                            log.error(format!("Ошибка отправки пакета на сервер: {}", e));
+                            return;
+                        }
+                    }
+                    if let Some(packet) = follow_up
+                        && !self.send_to_server(vec![packet], &log).await
+                    {
+                        return;
+                    }
+                }
+
+                ControllerEvent::ClientDisconnected(client_id) => {
+                    if !self.is_connected(client_id) {
+                        *self.closing(client_id) = false;
+                    } else if !self.client_gone(client_id, &log).await {
+                        return;
+                    }
+                }
+
+                ControllerEvent::ServerData(packet) => {
+                    self.capture(&packet, None, &log);
+                    let seen = self.seen(&packet, None);
+                    if let Some(event) = self.version.handle_s2c(&packet, self.both_active()) {
+                        match event {}
+                    }
+                    self.report(seen, Outcome::Relayed);
+
+                    let size = packet.data.len();
+                    self.stats.server.clientbound.add(size);
+                    if self.primary_active {
+                        self.stats.primary.clientbound.add(size);
+                        let _ = self.primary_tx.send(packet.clone()).await;
+                    }
+                    if self.secondary_active {
+                        self.stats.secondary.clientbound.add(size);
+                        let _ = self.secondary_tx.send(packet).await;
+                    }
+                }
+
+                // The upstream server is the session's lifeline: without it the proxy has
+                // nothing to relay, so end the session. `run_proxy_session` then aborts the
+                // client I/O tasks, closing their sockets, and `ClientStatusOfflineGuard`
+                // marks both clients offline. This is what tears down a session whose active
+                // client went half-open (no FIN) and therefore never produced a
+                // `ClientDisconnected` of its own.
+                ControllerEvent::ServerDisconnected => return,
+
+                ControllerEvent::Control(message) => {
+                    if let Err(e) = self.check(message) {
+                        log.warn(e);
+                    } else if !self.apply(message, &log).await {
+                        return;
+                    }
+                }
+
+                ControllerEvent::ClientJoined(client) => self.join(client, &log).await,
+
+                ControllerEvent::UpstreamLatency(rtt) => self.stats.latency.upstream = rtt,
+            }
+        }
+    }
+
+    fn both_active(&self) -> bool {
+        self.primary_active && self.secondary_active
+    }
+
+    fn is_connected(&self, client_id: ClientId) -> bool {
+        match client_id {
+            ClientId::Primary => self.primary_active,
+            ClientId::Secondary => self.secondary_active,
+        }
+    }
+
+    fn closing(&mut self, client_id: ClientId) -> &mut bool {
+        match client_id {
+            ClientId::Primary => &mut self.primary_closing,
+            ClientId::Secondary => &mut self.secondary_closing,
+        }
+    }
+
+    /// Why `message` can't be carried out right now, if it can't.
+    fn check(&self, message: ControlMessage) -> Result<(), String> {
+        match message {
+            ControlMessage::SwitchActive(client_id) if client_id == self.active_client => Err(
+                format!("Управление уже у клиента: {}", client_id.describe()),
+            ),
+            ControlMessage::SwitchActive(client_id) if !self.is_connected(client_id) => {
+                Err(format!(
+                    "Нельзя передать управление: {} клиент не подключён",
+                    client_id.describe()
+                ))
+            }
+            ControlMessage::Kick(_) if !self.both_active() => {
+                Err("Нельзя отключить единственного клиента".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Carries out a [`check`](Self::check)ed message. Returns `false` once
+    /// the session is over.
+    async fn apply(&mut self, message: ControlMessage, log: &Logger<'_>) -> bool {
+        match message {
+            ControlMessage::SwitchActive(client_id) => self.switch_active(client_id, log).await,
+            ControlMessage::Kick(client_id) => {
+                // Dropping the client's channel ends its write task, which
+                // closes the socket; the read task follows once the client
+                // hangs up.
+                let (closed, _) = mpsc::channel(1);
+                *self.client_tx_mut(client_id) = closed;
+                *self.closing(client_id) = true;
+                log.info(format!("Отключён {} клиент", client_id.describe()));
+                self.client_gone(client_id, log).await
+            }
+        }
+    }
+
+    /// Runs a `#vp` command typed by `sender` and answers in its chat.
+    /// Returns `false` once the session is over.
+    async fn run_chat_command(
+        &mut self,
+        sender: ClientId,
+        message: &str,
+        log: &Logger<'_>,
+    ) -> bool {
+        let inactive = self.active_client.opposite();
+        let control = match ChatCommand::parse(message) {
+            Ok(ChatCommand::Switch(target)) => {
+                ControlMessage::SwitchActive(target.unwrap_or(inactive))
+            }
+            Ok(ChatCommand::Kick(target)) => ControlMessage::Kick(target.unwrap_or(inactive)),
+            Ok(ChatCommand::Status) => {
+                let online = |client_id| {
+                    if self.is_connected(client_id) {
+                        "в сети"
+                    } else {
+                        "не в сети"
+                    }
+                };
+                let status = format!(
+                    "Управление: {}. Основной {}, дополнительный {}.",
+                    self.active_client.describe(),
+                    online(ClientId::Primary),
+                    online(ClientId::Secondary)
+                );
+                self.reply(sender, &status).await;
+                return true;
+            }
+            Ok(ChatCommand::Help) => {
+                self.reply(sender, &format!("Команды: {}", chat_command::HELP))
+                    .await;
+                return true;
+            }
+            Err(e) => {
+                self.reply(sender, &e).await;
+                return true;
+            }
+        };
+
+        if let Err(e) = self.check(control) {
+            self.reply(sender, &e).await;
+            return true;
+        }
+        // Answered first: a kicked sender wouldn't get to read it.
+        let answer = match control {
+            ControlMessage::SwitchActive(client_id) => {
+                format!("Управление передано: {}", client_id.describe())
+            }
+            ControlMessage::Kick(client_id) => format!("Отключён {} клиент", client_id.describe()),
+        };
+        self.reply(sender, &answer).await;
+        self.apply(control, log).await
+    }
+
+    async fn reply(&self, client_id: ClientId, text: &str) {
+        if let Some(packet) = self.version.system_chat(text) {
+            self.client_tx(client_id).send(packet).await.ok();
+        }
+    }
+
+    fn client_tx(&self, client_id: ClientId) -> &Sender<RawPacket> {
+        match client_id {
+            ClientId::Primary => &self.primary_tx,
+            ClientId::Secondary => &self.secondary_tx,
+        }
+    }
+
+    fn client_tx_mut(&mut self, client_id: ClientId) -> &mut Sender<RawPacket> {
+        match client_id {
+            ClientId::Primary => &mut self.primary_tx,
+            ClientId::Secondary => &mut self.secondary_tx,
+        }
+    }
+
+    /// Logs `client` in from the world cache and makes it the inactive client,
+    /// or turns it away with the reason.
+    async fn join(&mut self, client: LateClient, log: &Logger<'_>) {
+        let LateClient {
+            mut stream,
+            protocol,
+            events,
+        } = client;
+        let joined = self.vacancy(protocol).and_then(|client_id| {
+            let packets = self.version.handle_late_join(client_id)?;
+            Ok((client_id, packets))
+        });
+        let (client_id, packets) = match joined {
+            Ok(joined) => joined,
+            Err(e) => {
+                log.warn(format!("Клиент не принят в сессию: {}", e));
+                send_login_disconnect(&mut stream, e).await;
+                return;
+            }
+        };
+
+        let (tx, rx) = mpsc::channel(IO_CHANNEL_CAPACITY);
+        let (read_half, write_half) = stream.into_split();
+        self.late_clients.spawn(run_client(
+            read_half, write_half, client_id, events, rx, packets,
+        ));
+        *self.client_tx_mut(client_id) = tx;
+        match client_id {
+            ClientId::Primary => self.primary_active = true,
+            ClientId::Secondary => self.secondary_active = true,
+        }
+        self.control.set_vacant(false);
+        self.sink.client_status(client_id, true);
+        log.success(format!("Подключён {} клиент", client_id.describe()));
+    }
+
+    /// The slot a client of `protocol` joining now would take.
+    fn vacancy(&self, protocol: i32) -> Result<ClientId, String> {
+        if protocol != self.version.protocol() {
+            return Err("Версия клиента отличается от версии сессии".to_string());
+        }
+        let client_id = match (self.primary_active, self.secondary_active) {
+            (false, _) => ClientId::Primary,
+            (_, false) => ClientId::Secondary,
+            _ => return Err("Оба клиента уже подключены".to_string()),
+        };
+        let closing = match client_id {
+            ClientId::Primary => self.primary_closing,
+            ClientId::Secondary => self.secondary_closing,
+        };
+        if closing {
+            return Err("Прежнее подключение ещё закрывается, попробуйте снова".to_string());
+        }
+        Ok(client_id)
+    }
+
+    /// Forgets a client that left or was kicked, handing control to the other
+    /// one if needed. Returns `false` once the session is over.
+    async fn client_gone(&mut self, client_id: ClientId, log: &Logger<'_>) -> bool {
+        match client_id {
+            ClientId::Primary => self.primary_active = false,
+            ClientId::Secondary => self.secondary_active = false,
+        }
+        self.sink.client_status(client_id, false);
+
+        if !(self.primary_active || self.secondary_active) {
+            return false;
+        }
+        self.control.set_vacant(true);
+
+        if self.active_client == client_id {
+            self.active_client = client_id.opposite();
+            self.stats.handovers += 1;
+            self.sink.active_client(self.active_client);
+            log.info(format!(
+                "Активный клиент отключился, управление передано: {}",
+                self.active_client.describe()
+            ));
+
+            if let Some(ClientDisconnectEvent::SendToServer(packets)) =
+                self.version.handle_client_disconnect(self.active_client)
+            {
+                return self.send_to_server(packets, log).await;
+            }
+        }
+        true
+    }
+
+    /// Hands control to `client_id` while both clients stay connected.
+    /// Returns `false` once the session is over.
+    async fn switch_active(&mut self, client_id: ClientId, log: &Logger<'_>) -> bool {
+        self.active_client = client_id;
+        self.stats.handovers += 1;
+        self.sink.active_client(client_id);
+        log.info(format!("Управление передано: {}", client_id.describe()));
+
+        let mut event = self.version.handle_active_switch(client_id);
+        for packet in event.to_active {
+            self.client_tx(client_id).send(packet).await.ok();
+        }
+        if self.resync_clicks {
+            event.to_server.extend(event.resync);
+        }
+        self.send_to_server(event.to_server, log).await
+    }
+
+    /// Whether the inactive client's `packet` goes upstream under the routing
+    /// policy.
+    fn routes(&self, client_id: ClientId, packet: &RawPacket) -> bool {
+        !self.routing.is_empty()
+            && self
+                .version
+                .category(packet, client_id)
+                .is_some_and(|category| self.routing.relays(category))
+    }
+
+    /// Adds `packet`, read from `from` (the server when `None`), to the
+    /// recording if one is running.
+    fn capture(&mut self, packet: &RawPacket, from: Option<ClientId>, log: &Logger<'_>) {
+        if self.capture.is_some()
+            && let Some((state, packet)) = self.version.inspect(packet, from)
+        {
+            self.record(from, state, packet, log);
+        }
+    }
+
+    /// Writes one record; a failed write stops the recording.
+    fn record(
+        &mut self,
+        from: Option<ClientId>,
+        state: State,
+        packet: UncompressedPacket,
+        log: &Logger<'_>,
+    ) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+        let record = Record {
+            time: SystemTime::now(),
+            direction: match from {
+                Some(_) => Direction::Serverbound,
+                None => Direction::Clientbound,
+            },
+            client: from,
+            state,
+            protocol: self.version.protocol(),
+            packet,
+        };
+        if let Err(e) = capture.write(&record) {
+            log.error(format!("Запись пакетов остановлена: {}", e));
+            self.capture = None;
+        }
+    }
+
+    fn traffic(&mut self, client_id: ClientId) -> &mut Traffic {
+        match client_id {
+            ClientId::Primary => &mut self.stats.primary,
+            ClientId::Secondary => &mut self.stats.secondary,
+        }
+    }
+
+    /// Fills in the gauges and hands the stats to the sink and the metrics
+    /// handle.
+    fn publish_stats(&mut self) {
+        let queued = |tx: &Sender<RawPacket>| tx.max_capacity() - tx.capacity();
+        self.stats.uptime = self.started.elapsed();
+        self.stats.queues.events = self.event_rx.len();
+        self.stats.queues.primary = queued(&self.primary_tx);
+        self.stats.queues.secondary = queued(&self.secondary_tx);
+        self.stats.queues.server = queued(&self.remote_tx);
+        self.stats.pending_pings = self.version.pending_pings();
+        let latency = Latency {
+            primary: self.version.client_latency(ClientId::Primary),
+            secondary: self.version.client_latency(ClientId::Secondary),
+            ..self.stats.latency
+        };
+        if latency != self.reported_latency {
+            self.reported_latency = latency;
+            self.sink.latency(latency);
+        }
+        self.stats.latency = latency;
+        self.sink.session_stats(self.stats.clone());
+        self.metrics.publish(self.stats.clone());
+    }
+
+    /// The inspector's view of `packet`, read from `from` (the server when
+    /// `None`), while the inspector is on. Taken before the version handler
+    /// sees the packet, so a phase change is reported in the old phase.
+    fn seen(&self, packet: &RawPacket, from: Option<ClientId>) -> Option<PacketEvent> {
+        if !self.inspector.is_enabled() {
+            return None;
+        }
+        let (state, uncompressed) = self.version.inspect(packet, from)?;
+        let direction = match from {
+            Some(_) => Direction::Serverbound,
+            None => Direction::Clientbound,
+        };
+        Some(PacketEvent {
+            time: SystemTime::now(),
+            direction,
+            client: from,
+            state,
+            packet_id: uncompressed.packet_id,
+            name: self
+                .version
+                .table()
+                .name(state, direction, uncompressed.packet_id),
+            size: packet.data.len(),
+            outcome: Outcome::Relayed,
+            suppressed: 0,
+        })
+    }
+
+    fn report(&self, seen: Option<PacketEvent>, outcome: Outcome) {
+        if let Some(event) = seen {
+            self.inspector
+                .report(PacketEvent { outcome, ..event }, &*self.sink);
+        }
+    }
+
+    async fn send_to_inactive(&self, packet: RawPacket) {
+        if self.both_active() {
+            self.client_tx(self.active_client.opposite())
+                .send(packet)
+                .await
+                .ok();
+        }
+    }
+
+    async fn send_to_server(&mut self, packets: Vec<RawPacket>, log: &Logger<'_>) -> bool {
+        for packet in packets {
+            self.stats.server.serverbound.add(packet.data.len());
+            if let Err(e) = self.remote_tx.send(packet).await {
+                log.error(format!("Ошибка отправки пакета на сервер: {}", e));
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Opens a recording in `dir`, or logs why it couldn't.
+fn start_capture(dir: &Path, log: &Logger) -> Option<Capture> {
+    match Capture::create(dir) {
+        Ok((capture, path)) => {
+            log.info(format!("Запись пакетов: {}", path.display()));
+            Some(capture)
+        }
+        Err(e) => {
+            log.error(format!(
+                "Не удалось начать запись пакетов в {}: {}",
+                dir.display(),
+                e
+            ));
+            None
+        }
+    }
+}
+
+/// Drives a single client connection using two concurrent tasks:
+/// - **Read task**: reads packets from the TCP socket and sends them to the Controller
+///   as `Event::ClientData`; sends `Event::ClientDisconnected` on any read error.
+/// - **Write task**: writes `backlog` (a late client's catch-up), then receives packets
+///   from the Controller via `packet_rx` and writes them to the TCP socket; exits
+///   silently on write error.
+pub async fn run_client(
+    read_half: OwnedReadHalf,
+    write_half: OwnedWriteHalf,
+    client_id: ClientId,
+    event_tx: Sender<ControllerEvent>,
+    mut packet_rx: Receiver<RawPacket>,
+    backlog: Vec<RawPacket>,
+) {
+    let (mut client_read, mut client_write) = (read_half, write_half);
+    let _ = tokio::join!(
+        async move {
+            loop {
+                match RawPacket::read_async(&mut client_read).await {
+                    Ok(packet) => {
+                        if event_tx
+                            .send(ControllerEvent::ClientData(client_id, packet))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Err(_) => {
+                        event_tx
+                            .send(ControllerEvent::ClientDisconnected(client_id))
+                            .await
+                            .ok();
+                        break;
+                    }
+                }
+            }
+        },
+        async move {
+            for packet in backlog {
+                if packet.write_async(&mut client_write).await.is_err() {
+                    return;
+                }
+            }
+            while let Some(packet) = packet_rx.recv().await {
+                if packet.write_async(&mut client_write).await.is_err() {
+                    break;
+                }
+            }
+        }
+    );
+}
+
+/// Drives the upstream server connection using two concurrent tasks:
+/// - **Read task**: reads packets from the server and sends them to the Controller
+///   as `Event::ServerData`; exits on read error.
+/// - **Write task**: receives packets from the Controller via `packet_rx` and writes
+///   them to the server socket; exits silently on write error.
+///
+/// The halves are plain socket halves or, for an online-mode server, their
+/// AES/CFB8 wrappers, which hold ciphertext back until flushed.
+pub async fn run_server<R, W>(
+    read_half: R,
+    write_half: W,
+    event_tx: Sender<ControllerEvent>,
+    mut packet_rx: Receiver<RawPacket>,
+) where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let (mut server_read, mut server_write) = (read_half, write_half);
+    let _ = tokio::join!(
+        async move {
+            loop {
+                match RawPacket::read_async(&mut server_read).await {
+                    Ok(packet) => {
+                        if event_tx
+                            .send(ControllerEvent::ServerData(packet))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Err(_) => {
+                        event_tx
+                            .send(ControllerEvent::ServerDisconnected)
+                            .await
+                            .ok();
+                        break;
+                    }
+                }
+            }
+        },
+        async move {
+            while let Some(packet) = packet_rx.recv().await {
+                if packet.write_async(&mut server_write).await.is_err()
+                    || server_write.flush().await.is_err()
+                {
+                    break;
+                }
+            }
+        }
+    );
+}
@@ -4,7 +4,6 @@ use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 use etherparse::SlicedPacket;
-use tauri::AppHandle;
 use windivert::{
     WinDivert,
     layer::{ForwardLayer, NetworkLayer, WinDivertLayerTrait},
@@ -13,7 +12,7 @@ use windivert::{
 };
 use windows::Win32::Foundation::HANDLE;
 
-use crate::logger::Logger;
+use crate::{logger::Logger, sink::Sink};
 
 // Re-declare WinDivert FFI functions so we can call them from a different thread
 // than the one doing recv() — the Rust wrapper requires &mut self which prevents
@@ -120,7 +119,7 @@ pub(crate) fn start_redirect(
     bind_port: u16,
     port_min: u16,
     port_max: u16,
-    app: AppHandle,
+    sink: Sink,
 ) -> anyhow::Result<(NatTable, RedirectHandle)> {
     let nat: NatTable = Arc::new(Mutex::new(HashMap::new()));
 
@@ -158,13 +157,13 @@ pub(crate) fn start_redirect(
     };
 
     let nat_client = Arc::clone(&nat);
-    let app2 = app.clone();
+    let sink2 = sink.clone();
     std::thread::spawn(move || {
-        run_client_intercept_loop(wd_forward, wd_inject, bind_port, nat_client, app2)
+        run_client_intercept_loop(wd_forward, wd_inject, bind_port, nat_client, sink2)
     });
 
     let nat_return = Arc::clone(&nat);
-    std::thread::spawn(move || run_return_intercept_loop(wd_return, nat_return, app));
+    std::thread::spawn(move || run_return_intercept_loop(wd_return, nat_return, sink));
 
     Ok((nat, redirect))
 }
@@ -187,9 +186,9 @@ fn run_client_intercept_loop(
     wd_inject: WinDivert<NetworkLayer>,
     bind_port: u16,
     nat: NatTable,
-    app: AppHandle,
+    sink: Sink,
 ) {
-    let log = Logger::new(&app);
+    let log = Logger::new(&*sink);
     let mut buf = vec![0u8; 65535];
     loop {
         let packet = match wd_forward.recv(Some(&mut buf)) {
@@ -275,8 +274,8 @@ fn run_client_intercept_loop(
     }
 }
 
-fn run_return_intercept_loop(wd: WinDivert<NetworkLayer>, nat: NatTable, app: AppHandle) {
-    let log = Logger::new(&app);
+fn run_return_intercept_loop(wd: WinDivert<NetworkLayer>, nat: NatTable, sink: Sink) {
+    let log = Logger::new(&*sink);
     let mut buf = vec![0u8; 65535];
     loop {
         let packet = match wd.recv(Some(&mut buf)) {
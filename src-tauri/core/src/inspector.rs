@@ -0,0 +1,238 @@
+//! Live packet inspector: one [`PacketEvent`] per packet the controller reads,
+//! with what it did to it, for a frontend to list while a session runs.
+//!
+//! Inspection is off until a frontend [starts](PacketInspector::start) it with
+//! a filter. Packets are filtered and rate limited here, before they reach the
+//! sink, so a busy session doesn't flood the UI with events it would drop.
+
+use std::{
+    mem,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{capture::Direction, controller::ClientId, protocols::State, sink::EventSink};
+
+/// What the controller did with a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Passed on: to the server, or to the connected clients for the server's.
+    Relayed,
+    /// Passed on, and a copy sent to the inactive client (`SendToInactive`).
+    Mirrored,
+    /// Held back by the version handler (`SkipRelay`) or a `#vp` command.
+    Skipped,
+    /// The inactive client's packet, outside the routing policy.
+    Dropped,
+}
+
+/// One packet as the controller saw it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketEvent {
+    pub time: SystemTime,
+    pub direction: Direction,
+    /// The client that sent a serverbound packet; `None` for the server's.
+    pub client: Option<ClientId>,
+    pub state: State,
+    pub packet_id: i32,
+    /// Key of the packet in the version's table, if it has one.
+    pub name: Option<&'static str>,
+    /// Length of the frame as read, compressed if the session compresses.
+    pub size: usize,
+    pub outcome: Outcome,
+    /// Packets the rate limit dropped since the previous event.
+    pub suppressed: u32,
+}
+
+/// Which packets reach the sink. Empty lists and `None` let everything through.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InspectorFilter {
+    pub direction: Option<Direction>,
+    /// Only this client's serverbound packets; the server's still pass.
+    pub client: Option<ClientId>,
+    pub states: Vec<State>,
+    pub packet_ids: Vec<i32>,
+    /// Table keys, as in [`PacketEvent::name`].
+    pub names: Vec<String>,
+    /// Events per second at most; 0 for no limit.
+    pub max_per_second: u32,
+}
+
+impl InspectorFilter {
+    pub fn matches(&self, event: &PacketEvent) -> bool {
+        self.direction.is_none_or(|d| d == event.direction)
+            && (event.client.is_none() || self.client.is_none_or(|c| Some(c) == event.client))
+            && (self.states.is_empty() || self.states.contains(&event.state))
+            && (self.packet_ids.is_empty() || self.packet_ids.contains(&event.packet_id))
+            && (self.names.is_empty()
+                || event
+                    .name
+                    .is_some_and(|name| self.names.iter().any(|n| n == name)))
+    }
+}
+
+/// Handle a frontend keeps to turn inspection on and off, for the running
+/// session and the ones after it.
+#[derive(Debug, Clone, Default)]
+pub struct PacketInspector {
+    enabled: Arc<AtomicBool>,
+    inspection: Arc<Mutex<Option<Inspection>>>,
+}
+
+#[derive(Debug)]
+struct Inspection {
+    filter: InspectorFilter,
+    window: Instant,
+    sent: u32,
+    suppressed: u32,
+}
+
+impl PacketInspector {
+    /// Starts reporting the packets `filter` lets through, replacing the
+    /// previous filter.
+    pub fn start(&self, filter: InspectorFilter) {
+        *self.inspection.lock().unwrap() = Some(Inspection {
+            filter,
+            window: Instant::now(),
+            sent: 0,
+            suppressed: 0,
+        });
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn stop(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+        *self.inspection.lock().unwrap() = None;
+    }
+
+    /// Whether packets are inspected at all; the controller skips building
+    /// events while they aren't.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Hands `event` to the sink if the filter and the rate limit let it.
+    pub(crate) fn report(&self, event: PacketEvent, sink: &dyn EventSink) {
+        let admitted = match self.inspection.lock().unwrap().as_mut() {
+            Some(inspection) => inspection.admit(event, Instant::now()),
+            None => None,
+        };
+        if let Some(event) = admitted {
+            sink.packet(event);
+        }
+    }
+}
+
+impl Inspection {
+    fn admit(&mut self, mut event: PacketEvent, now: Instant) -> Option<PacketEvent> {
+        if !self.filter.matches(&event) {
+            return None;
+        }
+        let limit = self.filter.max_per_second;
+        if limit == 0 {
+            return Some(event);
+        }
+        if now.duration_since(self.window) >= Duration::from_secs(1) {
+            self.window = now;
+            self.sent = 0;
+        }
+        if self.sent >= limit {
+            self.suppressed += 1;
+            return None;
+        }
+        self.sent += 1;
+        event.suppressed = mem::take(&mut self.suppressed);
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(
+        direction: Direction,
+        client: Option<ClientId>,
+        name: Option<&'static str>,
+    ) -> PacketEvent {
+        PacketEvent {
+            time: SystemTime::UNIX_EPOCH,
+            direction,
+            client,
+            state: State::Game,
+            packet_id: 0x1D,
+            name,
+            size: 12,
+            outcome: Outcome::Relayed,
+            suppressed: 0,
+        }
+    }
+
+    fn inspection(filter: InspectorFilter, window: Instant) -> Inspection {
+        Inspection {
+            filter,
+            window,
+            sent: 0,
+            suppressed: 0,
+        }
+    }
+
+    #[test]
+    fn filter_narrows_by_client_and_name() {
+        let filter = InspectorFilter {
+            client: Some(ClientId::Secondary),
+            names: vec!["move_pos".into()],
+            ..Default::default()
+        };
+        let primary = event(
+            Direction::Serverbound,
+            Some(ClientId::Primary),
+            Some("move_pos"),
+        );
+        let secondary = event(
+            Direction::Serverbound,
+            Some(ClientId::Secondary),
+            Some("move_pos"),
+        );
+        let unnamed = event(Direction::Serverbound, Some(ClientId::Secondary), None);
+        let server = event(Direction::Clientbound, None, Some("move_pos"));
+        assert!(!filter.matches(&primary));
+        assert!(filter.matches(&secondary));
+        assert!(!filter.matches(&unnamed));
+        assert!(filter.matches(&server));
+        assert!(InspectorFilter::default().matches(&unnamed));
+    }
+
+    #[test]
+    fn rate_limit_counts_what_it_drops() {
+        let start = Instant::now();
+        let filter = InspectorFilter {
+            max_per_second: 2,
+            ..Default::default()
+        };
+        let mut inspection = inspection(filter, start);
+        let packet = || event(Direction::Clientbound, None, None);
+        assert!(inspection.admit(packet(), start).is_some());
+        assert!(inspection.admit(packet(), start).is_some());
+        assert!(inspection.admit(packet(), start).is_none());
+        assert!(inspection.admit(packet(), start).is_none());
+
+        let next = inspection
+            .admit(packet(), start + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(next.suppressed, 2);
+    }
+
+    #[test]
+    fn inspector_is_off_until_started() {
+        let inspector = PacketInspector::default();
+        assert!(!inspector.is_enabled());
+        inspector.start(InspectorFilter::default());
+        assert!(inspector.clone().is_enabled());
+        inspector.stop();
+        assert!(!inspector.is_enabled());
+    }
+}
@@ -0,0 +1,337 @@
+//! Round-trip times of the session's links, so lag can be pinned on the
+//! upstream server or on one client's connection to the proxy.
+//!
+//! A client's latency is how long it takes to echo the server's keep-alives
+//! back through the proxy. The server's is measured by the proxy itself: every
+//! [`UPSTREAM_PING_INTERVAL`] it opens a status connection, like the
+//! multiplayer screen does, and times the ping there.
+
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use mc_protocol::{
+    packet::{RawPacket, UncompressedPacket},
+    varint::VarInt,
+};
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::WeakSender,
+    time::{interval, timeout},
+};
+
+use crate::{
+    controller::{ClientId, ControllerEvent},
+    packets::universal::{
+        Intent,
+        handshaking::c2s::Handshake,
+        status::{c2s::StatusRequest, s2c::StatusResponse},
+    },
+};
+
+/// How often the proxy pings the upstream server.
+pub const UPSTREAM_PING_INTERVAL: Duration = Duration::from_secs(10);
+/// How long a status ping may take before it counts as lost.
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+/// Keep-alives waiting for an echo; older ones are given up on.
+const MAX_OUTSTANDING: usize = 4;
+/// Id of the status phase's ping, the same in both directions.
+const STATUS_PING_ID: i32 = 0x01;
+
+/// Latest round-trip time of each link; `None` until measured, after a lost
+/// ping to the server, or while the client is disconnected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Latency {
+    /// From the proxy to the server and back.
+    pub upstream: Option<Duration>,
+    /// From the proxy to the primary client and back.
+    pub primary: Option<Duration>,
+    pub secondary: Option<Duration>,
+}
+
+impl Latency {
+    pub fn client(&self, client_id: ClientId) -> Option<Duration> {
+        match client_id {
+            ClientId::Primary => self.primary,
+            ClientId::Secondary => self.secondary,
+        }
+    }
+}
+
+/// Times the clients' echoes of the server's keep-alives. A keep-alive is
+/// answered with the same payload, so the payload is its key.
+#[derive(Debug, Default)]
+pub(crate) struct Echoes {
+    outstanding: VecDeque<Outstanding>,
+    latency: Latency,
+}
+
+#[derive(Debug)]
+struct Outstanding {
+    payload: Vec<u8>,
+    sent: Instant,
+    primary: bool,
+    secondary: bool,
+}
+
+impl Outstanding {
+    fn is_answered(&self, client_id: ClientId) -> bool {
+        match client_id {
+            ClientId::Primary => self.primary,
+            ClientId::Secondary => self.secondary,
+        }
+    }
+
+    fn answered(&mut self, client_id: ClientId) -> &mut bool {
+        match client_id {
+            ClientId::Primary => &mut self.primary,
+            ClientId::Secondary => &mut self.secondary,
+        }
+    }
+}
+
+impl Echoes {
+    /// The server's keep-alive is on its way to the clients.
+    pub(crate) fn sent(&mut self, payload: &[u8], now: Instant) {
+        if self.outstanding.len() == MAX_OUTSTANDING {
+            self.outstanding.pop_front();
+        }
+        self.outstanding.push_back(Outstanding {
+            payload: payload.to_vec(),
+            sent: now,
+            primary: false,
+            secondary: false,
+        });
+    }
+
+    /// `client_id` echoed a keep-alive.
+    pub(crate) fn answered(&mut self, client_id: ClientId, payload: &[u8], now: Instant) {
+        let Some(i) = self
+            .outstanding
+            .iter()
+            .position(|o| o.payload == payload && !o.is_answered(client_id))
+        else {
+            return;
+        };
+        let outstanding = &mut self.outstanding[i];
+        *outstanding.answered(client_id) = true;
+        let rtt = Some(now.duration_since(outstanding.sent));
+        match client_id {
+            ClientId::Primary => self.latency.primary = rtt,
+            ClientId::Secondary => self.latency.secondary = rtt,
+        }
+        if outstanding.primary && outstanding.secondary {
+            self.outstanding.remove(i);
+        }
+    }
+
+    /// `client_id` disconnected; its last measurement no longer holds.
+    pub(crate) fn forget(&mut self, client_id: ClientId) {
+        match client_id {
+            ClientId::Primary => self.latency.primary = None,
+            ClientId::Secondary => self.latency.secondary = None,
+        }
+        for outstanding in &mut self.outstanding {
+            *outstanding.answered(client_id) = true;
+        }
+        self.outstanding.retain(|o| !(o.primary && o.secondary));
+    }
+
+    pub(crate) fn client(&self, client_id: ClientId) -> Option<Duration> {
+        self.latency.client(client_id)
+    }
+}
+
+/// The server a session is connected to, as the client addressed it.
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    pub addr: SocketAddr,
+    /// Host and port from the client's Handshake, which proxies in front of
+    /// the server may route by.
+    pub host: String,
+    pub port: u16,
+    pub protocol: i32,
+}
+
+/// Pings `upstream` every [`UPSTREAM_PING_INTERVAL`] and reports each answer,
+/// or `None` for a lost ping, to the controller. Holds no strong sender, so it never keeps the session
+/// alive; returns once the controller is gone.
+pub(crate) async fn probe_upstream(upstream: Upstream, events: WeakSender<ControllerEvent>) {
+    let mut timer = interval(UPSTREAM_PING_INTERVAL);
+    loop {
+        timer.tick().await;
+        // A lost ping is reported too, so a stale measurement doesn't linger
+        // after the server stops answering.
+        let rtt = timeout(PING_TIMEOUT, status_ping(&upstream))
+            .await
+            .ok()
+            .and_then(Result::ok);
+        let Some(events) = events.upgrade() else {
+            return;
+        };
+        if events
+            .send(ControllerEvent::UpstreamLatency(rtt))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Round-trip time of one status ping to `upstream`, not counting the
+/// connection and the status request before it.
+pub async fn status_ping(upstream: &Upstream) -> anyhow::Result<Duration> {
+    let mut stream = TcpStream::connect(upstream.addr).await?;
+    let handshake = Handshake {
+        protocol_version: VarInt(upstream.protocol),
+        server_address: upstream.host.clone(),
+        server_port: upstream.port,
+        intent: Intent::Status.into(),
+    };
+    UncompressedPacket::from_packet(&handshake)?
+        .write_async(&mut stream)
+        .await?;
+    UncompressedPacket::from_packet(&StatusRequest {})?
+        .write_async(&mut stream)
+        .await?;
+    let response = RawPacket::read_async(&mut stream)
+        .await?
+        .as_uncompressed()?;
+    anyhow::ensure!(
+        response.packet_id == StatusResponse::PACKET_ID,
+        "Неожиданный ответ на запрос статуса: {}",
+        response.packet_id
+    );
+
+    let payload = 0x5650_i64.to_be_bytes().to_vec();
+    let started = Instant::now();
+    UncompressedPacket::new(STATUS_PING_ID, payload.clone())
+        .write_async(&mut stream)
+        .await?;
+    let pong = RawPacket::read_async(&mut stream)
+        .await?
+        .as_uncompressed()?;
+    let rtt = started.elapsed();
+    anyhow::ensure!(
+        pong.packet_id == STATUS_PING_ID && pong.payload == payload,
+        "Сервер ответил на пинг не тем пакетом"
+    );
+    Ok(rtt)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn echoes_are_timed_per_client() {
+        let start = Instant::now();
+        let mut echoes = Echoes::default();
+        echoes.sent(&[1], start);
+        echoes.sent(&[2], start + Duration::from_millis(10));
+
+        echoes.answered(ClientId::Primary, &[1], start + Duration::from_millis(5));
+        echoes.answered(ClientId::Secondary, &[2], start + Duration::from_millis(50));
+        // Neither a second echo nor one of an unknown keep-alive changes it.
+        echoes.answered(ClientId::Primary, &[1], start + Duration::from_millis(90));
+        echoes.answered(ClientId::Primary, &[3], start + Duration::from_millis(90));
+        assert_eq!(
+            echoes.client(ClientId::Primary),
+            Some(Duration::from_millis(5))
+        );
+        assert_eq!(
+            echoes.client(ClientId::Secondary),
+            Some(Duration::from_millis(40))
+        );
+
+        echoes.forget(ClientId::Secondary);
+        assert_eq!(echoes.client(ClientId::Secondary), None);
+        echoes.answered(ClientId::Primary, &[2], start + Duration::from_millis(30));
+        assert!(echoes.outstanding.is_empty());
+    }
+
+    #[test]
+    fn unanswered_keep_alives_are_given_up() {
+        let start = Instant::now();
+        let mut echoes = Echoes::default();
+        for i in 0..=MAX_OUTSTANDING as u8 {
+            echoes.sent(&[i], start);
+        }
+        echoes.answered(ClientId::Primary, &[0], start);
+        assert_eq!(echoes.client(ClientId::Primary), None);
+        assert_eq!(echoes.outstanding.len(), MAX_OUTSTANDING);
+    }
+
+    #[tokio::test]
+    async fn pings_the_server_over_a_status_connection() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let handshake = RawPacket::read_async(&mut stream).await.unwrap();
+            let handshake: Handshake = handshake
+                .as_uncompressed()
+                .unwrap()
+                .deserialize_payload()
+                .unwrap();
+            RawPacket::read_async(&mut stream).await.unwrap();
+            UncompressedPacket::from_packet(&StatusResponse {
+                response: "{}".to_string(),
+            })
+            .unwrap()
+            .write_async(&mut stream)
+            .await
+            .unwrap();
+            RawPacket::read_async(&mut stream)
+                .await
+                .unwrap()
+                .write_async(&mut stream)
+                .await
+                .unwrap();
+            handshake
+        });
+
+        let upstream = Upstream {
+            addr,
+            host: "mc.example.com".to_string(),
+            port: 25565,
+            protocol: 774,
+        };
+        let rtt = status_ping(&upstream).await.unwrap();
+        assert!(rtt < PING_TIMEOUT);
+
+        let handshake = server.await.unwrap();
+        assert_eq!(handshake.server_address, "mc.example.com");
+        assert_eq!(handshake.protocol_version.0, 774);
+        assert_eq!(handshake.intent.0, Intent::Status as i32);
+    }
+
+    #[tokio::test]
+    async fn a_lost_ping_is_reported() {
+        // Nothing listens on the port once the listener is gone.
+        let addr = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let upstream = Upstream {
+            addr,
+            host: "mc.example.com".to_string(),
+            port: 25565,
+            protocol: 774,
+        };
+        let (events, mut rx) = tokio::sync::mpsc::channel(1);
+        tokio::spawn(probe_upstream(upstream, events.downgrade()));
+
+        let event = timeout(PING_TIMEOUT, rx.recv()).await.unwrap();
+        assert!(matches!(
+            event,
+            Some(ControllerEvent::UpstreamLatency(None))
+        ));
+    }
+}
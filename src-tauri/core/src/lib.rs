@@ -0,0 +1,26 @@
+//! Tauri-free VoxelProxy engine: the two-client proxy, per-version protocol
+//! handlers and session orchestration. Frontends such as the desktop app drive
+//! it through [`session`] and observe it through an [`EventSink`].
+
+pub mod auth;
+pub mod capture;
+pub mod chat_command;
+pub mod controller;
+#[cfg(target_os = "windows")]
+pub mod hotspot_redirect;
+pub mod inspector;
+pub mod latency;
+pub mod logger;
+pub mod mcpr;
+pub mod metrics;
+pub mod packets;
+pub mod protocols;
+pub mod proxy;
+pub mod replay;
+pub mod resolver;
+pub mod session;
+pub mod sink;
+
+pub use controller::ClientId;
+pub use protocols::Version;
+pub use sink::{EventSink, LogLevel, Sink};
@@ -1,16 +1,13 @@
-use tauri::AppHandle;
-use tauri_specta::Event;
-
-use crate::events::{LogLevel, ProxyLogEvent};
+use crate::sink::{EventSink, LogLevel};
 
 #[derive(Clone)]
 pub struct Logger<'a> {
-    app: &'a AppHandle,
+    sink: &'a dyn EventSink,
 }
 
 impl<'a> Logger<'a> {
-    pub fn new(app: &'a AppHandle) -> Self {
-        Self { app }
+    pub fn new(sink: &'a dyn EventSink) -> Self {
+        Self { sink }
     }
 
     pub fn info(&self, msg: impl Into<String>) {
@@ -30,6 +27,6 @@ impl<'a> Logger<'a> {
     }
 
     fn send(&self, level: LogLevel, message: String) {
-        ProxyLogEvent { level, message }.emit(self.app).ok();
+        self.sink.log(level, message);
     }
 }
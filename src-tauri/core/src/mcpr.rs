@@ -0,0 +1,207 @@
+//! Export of a [`capture`](crate::capture) as a ReplayMod replay (`.mcpr`), so
+//! a recorded session can be watched in ReplayMod on any client.
+//!
+//! The archive is a zip with two entries:
+//!
+//! - `recording.tmcpr` — the server's packets from `LoginSuccess` on, each as
+//!   the milliseconds since the first one and the frame length (both `i32`,
+//!   big-endian), then the packet id as a VarInt and the payload;
+//! - `metaData.json` — the server name, the Minecraft version, the protocol,
+//!   the start date and the duration.
+//!
+//! The server never sends the player their own entity, so the recording
+//! player is not visible in the replay; the camera starts where they stood.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Seek, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use mc_protocol::{ser::Serialize, varint::VarInt};
+use serde_json::json;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    capture::{self, Direction, Record},
+    protocols::Version,
+    replay::Replay,
+};
+
+/// File extension of a ReplayMod replay.
+pub const EXTENSION: &str = "mcpr";
+/// Version of the replay format the archive follows.
+const FILE_FORMAT_VERSION: i32 = 14;
+
+/// Converts the recording at `recording` into a replay at `output`.
+pub fn export(recording: &Path, output: &Path, server_name: &str) -> anyhow::Result<()> {
+    let file = File::open(recording)
+        .with_context(|| format!("Не удалось открыть запись {}", recording.display()))?;
+    let records = capture::read(BufReader::new(file))
+        .with_context(|| format!("Ошибка в записи {}", recording.display()))?;
+    let out =
+        File::create(output).with_context(|| format!("Не удалось создать {}", output.display()))?;
+    write(records, server_name, BufWriter::new(out))
+}
+
+/// Writes the server's side of `records` to `out` as a `.mcpr` archive.
+pub fn write<W: Write + Seek>(
+    records: Vec<Record>,
+    server_name: &str,
+    out: W,
+) -> anyhow::Result<()> {
+    // Same requirements as for playing a recording back: the server's
+    // packets from LoginSuccess on, on a known protocol.
+    let replay = Replay::new(records)?;
+    let protocol = replay.protocol();
+    let records = replay.into_records();
+    let start = records[0].time;
+    let end = records.last().map_or(start, |record| record.time);
+
+    let mut zip = ZipWriter::new(out);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("recording.tmcpr", options)?;
+    zip.write_all(&tmcpr(&records, start)?)?;
+    zip.start_file("metaData.json", options)?;
+    zip.write_all(
+        metadata(server_name, protocol, start, end)
+            .to_string()
+            .as_bytes(),
+    )?;
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+fn tmcpr(records: &[Record], start: SystemTime) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut frame = Vec::new();
+    for record in records {
+        debug_assert_eq!(record.direction, Direction::Clientbound);
+        frame.clear();
+        VarInt(record.packet.packet_id).serialize(&mut frame)?;
+        frame.extend_from_slice(&record.packet.payload);
+        out.extend_from_slice(&millis_between(start, record.time).to_be_bytes());
+        out.extend_from_slice(&(frame.len() as i32).to_be_bytes());
+        out.extend_from_slice(&frame);
+    }
+    Ok(out)
+}
+
+fn metadata(
+    server_name: &str,
+    protocol: i32,
+    start: SystemTime,
+    end: SystemTime,
+) -> serde_json::Value {
+    let mcversion = Version::from_protocol(protocol)
+        .and_then(|version| {
+            version
+                .table()
+                .releases()
+                .find(|&(_, p)| p == protocol)
+                .map(|(name, _)| name.to_string())
+        })
+        .unwrap_or_default();
+    let date = start
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    json!({
+        "singleplayer": false,
+        "serverName": server_name,
+        "duration": millis_between(start, end),
+        "date": date,
+        "mcversion": mcversion,
+        "fileFormat": "MCPR",
+        "fileFormatVersion": FILE_FORMAT_VERSION,
+        "protocol": protocol,
+        "generator": format!("VoxelProxy {}", env!("CARGO_PKG_VERSION")),
+        "selfId": -1,
+        "players": [],
+    })
+}
+
+/// Milliseconds from `start` to `time`, clamped to the format's `i32`.
+fn millis_between(start: SystemTime, time: SystemTime) -> i32 {
+    let millis = time.duration_since(start).unwrap_or_default().as_millis();
+    millis.min(i32::MAX as u128) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Cursor, Read},
+        time::Duration,
+    };
+
+    use mc_protocol::packet::UncompressedPacket;
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::{ClientId, packets::universal::login::s2c::LoginSuccess, protocols::State};
+
+    fn record(millis: u64, direction: Direction, state: State, packet_id: i32) -> Record {
+        Record {
+            time: UNIX_EPOCH + Duration::from_millis(1_700_000_000_000 + millis),
+            direction,
+            client: (direction == Direction::Serverbound).then_some(ClientId::Primary),
+            state,
+            protocol: 774,
+            packet: UncompressedPacket::new(packet_id, vec![7; 3]),
+        }
+    }
+
+    fn entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn archive_holds_the_server_stream_and_metadata() {
+        let records = vec![
+            record(
+                0,
+                Direction::Clientbound,
+                State::Login,
+                LoginSuccess::PACKET_ID,
+            ),
+            record(5, Direction::Serverbound, State::Login, 3),
+            record(1_500, Direction::Clientbound, State::Game, 300),
+        ];
+        let mut out = Cursor::new(Vec::new());
+        write(records, "mc.example.com", &mut out).unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(out.into_inner())).unwrap();
+        let tmcpr = entry(&mut archive, "recording.tmcpr");
+        assert_eq!(
+            tmcpr,
+            [
+                &[0, 0, 0, 0, 0, 0, 0, 4, 2, 7, 7, 7][..],
+                &[0, 0, 0x05, 0xDC, 0, 0, 0, 5, 0xAC, 0x02, 7, 7, 7],
+            ]
+            .concat()
+        );
+
+        let metadata: serde_json::Value =
+            serde_json::from_slice(&entry(&mut archive, "metaData.json")).unwrap();
+        assert_eq!(metadata["serverName"], "mc.example.com");
+        assert_eq!(metadata["mcversion"], "1.21.11");
+        assert_eq!(metadata["protocol"], 774);
+        assert_eq!(metadata["duration"], 1_500);
+        assert_eq!(metadata["date"], 1_700_000_000_000u64);
+        assert_eq!(metadata["fileFormat"], "MCPR");
+    }
+
+    #[test]
+    fn needs_a_recording_from_the_login() {
+        let game = record(0, Direction::Clientbound, State::Game, 300);
+        assert!(write(vec![game], "", Cursor::new(Vec::new())).is_err());
+    }
+}
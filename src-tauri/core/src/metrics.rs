@@ -0,0 +1,313 @@
+//! Per-session counters and gauges. The controller keeps them while it runs
+//! and publishes a [`SessionStats`] snapshot every [`STATS_INTERVAL`]: to the
+//! sink, for the UI, and to a [`SessionMetrics`] handle, which
+//! [`serve`] exposes over HTTP in the Prometheus text format.
+
+use std::{
+    fmt::Write as _,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+
+use crate::latency::Latency;
+
+/// How often a running session publishes its stats.
+pub const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Packets and the bytes of their frames, length prefix not included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counter {
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+impl Counter {
+    pub(crate) fn add(&mut self, bytes: usize) {
+        self.packets += 1;
+        self.bytes += bytes as u64;
+    }
+}
+
+/// Traffic of one peer of the proxy, by direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Traffic {
+    /// For a client, what it sent; for the server, what the proxy sent it.
+    pub serverbound: Counter,
+    /// For a client, what the proxy relayed to it; for the server, what it sent.
+    pub clientbound: Counter,
+}
+
+/// Packets waiting in the session's channels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueDepths {
+    /// Events for the controller from the I/O tasks.
+    pub events: usize,
+    pub primary: usize,
+    pub secondary: usize,
+    pub server: usize,
+}
+
+/// One snapshot of a running session.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    pub uptime: Duration,
+    pub primary: Traffic,
+    pub secondary: Traffic,
+    pub server: Traffic,
+    pub queues: QueueDepths,
+    /// Times control moved between the clients, on request or on failover.
+    pub handovers: u64,
+    /// Server pings not every connected client has answered yet.
+    pub pending_pings: usize,
+    pub latency: Latency,
+}
+
+/// Handle to the latest stats, shared by the sessions a frontend starts and
+/// the `/metrics` endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct SessionMetrics {
+    latest: Arc<Mutex<SessionStats>>,
+}
+
+impl SessionMetrics {
+    pub fn latest(&self) -> SessionStats {
+        self.latest.lock().unwrap().clone()
+    }
+
+    pub(crate) fn publish(&self, stats: SessionStats) {
+        *self.latest.lock().unwrap() = stats;
+    }
+}
+
+/// `stats` in the Prometheus text exposition format.
+pub fn render(stats: &SessionStats) -> String {
+    let mut out = String::new();
+    let peers = [
+        ("primary", &stats.primary),
+        ("secondary", &stats.secondary),
+        ("server", &stats.server),
+    ];
+    traffic_metric(
+        &mut out,
+        &peers,
+        "voxelproxy_packets_total",
+        "Packets handled by the session.",
+        |c| c.packets,
+    );
+    traffic_metric(
+        &mut out,
+        &peers,
+        "voxelproxy_bytes_total",
+        "Bytes of the packets handled by the session.",
+        |c| c.bytes,
+    );
+
+    metric_header(
+        &mut out,
+        "voxelproxy_queue_depth",
+        "Packets waiting in a session channel.",
+        "gauge",
+    );
+    let queues = &stats.queues;
+    for (queue, depth) in [
+        ("events", queues.events),
+        ("primary", queues.primary),
+        ("secondary", queues.secondary),
+        ("server", queues.server),
+    ] {
+        writeln!(
+            out,
+            "voxelproxy_queue_depth{{queue=\"{}\"}} {}",
+            queue, depth
+        )
+        .unwrap();
+    }
+
+    for (name, help, kind, value) in [
+        (
+            "voxelproxy_handovers_total",
+            "Times control moved between the clients.",
+            "counter",
+            stats.handovers.to_string(),
+        ),
+        (
+            "voxelproxy_pending_pings",
+            "Server pings not every client has answered yet.",
+            "gauge",
+            stats.pending_pings.to_string(),
+        ),
+        (
+            "voxelproxy_session_duration_seconds",
+            "Time since the session started.",
+            "gauge",
+            stats.uptime.as_secs_f64().to_string(),
+        ),
+    ] {
+        metric_header(&mut out, name, help, kind);
+        writeln!(out, "{} {}", name, value).unwrap();
+    }
+
+    metric_header(
+        &mut out,
+        "voxelproxy_rtt_seconds",
+        "Last measured round-trip time of a link.",
+        "gauge",
+    );
+    let latency = &stats.latency;
+    for (link, rtt) in [
+        ("upstream", latency.upstream),
+        ("primary", latency.primary),
+        ("secondary", latency.secondary),
+    ] {
+        if let Some(rtt) = rtt {
+            writeln!(
+                out,
+                "voxelproxy_rtt_seconds{{link=\"{}\"}} {}",
+                link,
+                rtt.as_secs_f64()
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+fn traffic_metric(
+    out: &mut String,
+    peers: &[(&str, &Traffic)],
+    name: &str,
+    help: &str,
+    value: impl Fn(&Counter) -> u64,
+) {
+    metric_header(out, name, help, "counter");
+    for (peer, traffic) in peers {
+        for (direction, counter) in [
+            ("serverbound", &traffic.serverbound),
+            ("clientbound", &traffic.clientbound),
+        ] {
+            writeln!(
+                out,
+                "{}{{peer=\"{}\",direction=\"{}\"}} {}",
+                name,
+                peer,
+                direction,
+                value(counter)
+            )
+            .unwrap();
+        }
+    }
+}
+
+fn metric_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+/// Answers `GET /metrics` on `listener` with the latest stats until the task
+/// is dropped.
+pub async fn serve(listener: TcpListener, metrics: SessionMetrics) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            timeout(Duration::from_secs(5), answer(stream, &metrics))
+                .await
+                .ok();
+        });
+    }
+}
+
+async fn answer(mut stream: TcpStream, metrics: &SessionMetrics) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let line = request.split(|&b| b == b'\r').next().unwrap_or_default();
+    let mut parts = line.split(|&b| b == b' ');
+    let response = match (parts.next(), parts.next()) {
+        (Some(b"GET"), Some(b"/metrics")) => {
+            let body = render(&metrics.latest());
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> SessionStats {
+        let mut stats = SessionStats {
+            uptime: Duration::from_millis(1500),
+            handovers: 2,
+            pending_pings: 1,
+            ..Default::default()
+        };
+        stats.primary.serverbound.add(10);
+        stats.primary.serverbound.add(5);
+        stats.queues.server = 3;
+        stats.latency.upstream = Some(Duration::from_millis(40));
+        stats
+    }
+
+    #[test]
+    fn renders_prometheus_text() {
+        let text = render(&stats());
+        assert!(text.contains("# TYPE voxelproxy_packets_total counter\n"));
+        assert!(
+            text.contains(
+                "voxelproxy_packets_total{peer=\"primary\",direction=\"serverbound\"} 2\n"
+            )
+        );
+        assert!(
+            text.contains(
+                "voxelproxy_bytes_total{peer=\"primary\",direction=\"serverbound\"} 15\n"
+            )
+        );
+        assert!(text.contains("voxelproxy_queue_depth{queue=\"server\"} 3\n"));
+        assert!(text.contains("voxelproxy_handovers_total 2\n"));
+        assert!(text.contains("voxelproxy_session_duration_seconds 1.5\n"));
+        assert!(text.contains("voxelproxy_rtt_seconds{link=\"upstream\"} 0.04\n"));
+        assert!(!text.contains("link=\"primary\""));
+    }
+
+    #[tokio::test]
+    async fn serves_metrics_over_http() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let metrics = SessionMetrics::default();
+        metrics.publish(stats());
+        tokio::spawn(serve(listener, metrics));
+
+        let get = |path: &'static str| async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+        let response = get("/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&render(&stats())));
+        assert!(get("/").await.starts_with("HTTP/1.1 404"));
+    }
+}
@@ -68,6 +68,27 @@ pub mod universal {
     pub mod login {
         use super::*;
 
+        pub mod c2s {
+            use super::*;
+
+            #[derive(Packet)]
+            #[packet(0x01)]
+            pub struct EncryptionResponse {
+                pub shared_secret: Vec<u8>,
+                pub verify_token: Vec<u8>,
+            }
+
+            /// 1.19–1.19.2: a flag chooses between the verify token and a
+            /// signed salt; the proxy always sends the token.
+            #[derive(Packet)]
+            #[packet(0x01)]
+            pub struct EncryptionResponseFlagged {
+                pub shared_secret: Vec<u8>,
+                pub has_verify_token: bool,
+                pub verify_token: Vec<u8>,
+            }
+        }
+
         pub mod s2c {
             use super::*;
 
@@ -77,9 +98,14 @@ pub mod universal {
                 pub reason: String,
             }
 
+            /// 1.20.5+ append a `should_authenticate` flag, read separately.
             #[derive(Packet)]
             #[packet(0x01)]
-            pub struct EncryptionRequest {}
+            pub struct EncryptionRequest {
+                pub server_id: String,
+                pub public_key: Vec<u8>,
+                pub verify_token: Vec<u8>,
+            }
 
             #[derive(Packet)]
             #[packet(0x02)]
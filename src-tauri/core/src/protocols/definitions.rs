@@ -0,0 +1,516 @@
+//! Protocol versions described by files instead of code, so a new release can
+//! be supported by dropping a table into the versions directory.
+//!
+//! A file has the same shape as [`PacketTable`]; `.toml` and `.json` are read.
+//!
+//! ```toml
+//! version = "1.21.11"
+//! protocol = 774
+//! # releases with an identical table, optional
+//! aliases = [{ version = "1.21.10", protocol = 773 }]
+//!
+//! [c2s]
+//! login_acknowledged = 3
+//! finish_configuration = 3
+//! cookie_response = 1
+//! select_known_packs = 7
+//! configuration_acknowledged = 15
+//! accept_teleportation = 0
+//! move_pos = 29
+//! move_pos_rot = 30
+//! move_rot = 31
+//! pong = 44
+//! keep_alive = 27
+//! container_close = 18
+//! container_click = 17
+//! set_carried_item = 52
+//! chat_command = 6
+//! chat_ack = 5
+//! chat = 8
+//!
+//! [s2c]
+//! finish_configuration = 3
+//! cookie_request = 0
+//! select_known_packs = 14
+//! start_configuration = 116
+//! position = 70
+//! ping = 59
+//! keep_alive = 43
+//! container_close = 17
+//! open_screen = 57
+//! container_set_content = 18
+//! container_set_slot = 20
+//! set_cursor_item = 94
+//! set_carried_item = 103
+//! system_chat = 119
+//! level_chunk = 44
+//! forget_level_chunk = 37
+//! light_update = 47
+//! # left out of the world cache, optional
+//! transient = [2, 35, 46, 51, 52, 54, 81, 99, 111, 114, 115, 123]
+//!
+//! [layout]
+//! position = "delta"      # or "legacy", "legacy_dismount", "no_confirm"
+//! ping = "ping"           # or "container_ack"
+//! chat = "nbt"            # or "json", "legacy_sender", "legacy"
+//! click = "state_id"      # or "legacy_flagged", "legacy"
+//!
+//! # serverbound ids the inactive client may be allowed to send too, optional
+//! [categories]
+//! chat = [8]
+//! commands = [6, 7, 14]
+//! movement = [29, 30, 31, 32]
+//! combat = [25, 60]
+//! interaction = [40, 63, 64]
+//! inventory = [17, 18, 52]
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+
+use super::{
+    register,
+    table::{PacketCategory, PacketTable, PositionLayout},
+};
+use crate::logger::Logger;
+
+/// Highest packet id accepted from a file. Real ids stay well below this; a
+/// larger one is almost certainly a typo.
+const MAX_PACKET_ID: i32 = 0xFF;
+
+/// Reads and validates one definition file.
+pub fn load_file(path: &Path) -> anyhow::Result<PacketTable> {
+    let text = fs::read_to_string(path).context("не удалось прочитать файл")?;
+    let table: PacketTable = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&text)?,
+        Some("json") => serde_json::from_str(&text)?,
+        _ => bail!("неизвестный формат, нужен .toml или .json"),
+    };
+    validate(&table)?;
+    Ok(table)
+}
+
+/// Checks the things serde can't: id ranges, the Configuration ids being all
+/// present or all absent, two logical packets sharing an id, and a packet
+/// sitting in two categories.
+pub fn validate(table: &PacketTable) -> anyhow::Result<()> {
+    for (version, protocol) in table.releases() {
+        if version.trim().is_empty() {
+            bail!("version не может быть пустым");
+        }
+        if protocol <= 0 {
+            bail!("protocol должен быть положительным, указано {}", protocol);
+        }
+    }
+
+    let (c2s, s2c) = (&table.c2s, &table.s2c);
+    let configuration = [
+        ("c2s.login_acknowledged", c2s.login_acknowledged),
+        ("c2s.finish_configuration", c2s.finish_configuration),
+        (
+            "c2s.configuration_acknowledged",
+            c2s.configuration_acknowledged,
+        ),
+        ("s2c.finish_configuration", s2c.finish_configuration),
+        ("s2c.start_configuration", s2c.start_configuration),
+    ];
+    let present = configuration.iter().filter(|(_, id)| id.is_some()).count();
+    if present != 0 && present != configuration.len() {
+        let missing: Vec<_> = configuration
+            .iter()
+            .filter(|(_, id)| id.is_none())
+            .map(|(name, _)| *name)
+            .collect();
+        bail!(
+            "пакеты фазы конфигурации заданы не полностью, не хватает: {}",
+            missing.join(", ")
+        );
+    }
+
+    let exchanges = [
+        (
+            "s2c.cookie_request",
+            s2c.cookie_request,
+            "c2s.cookie_response",
+            c2s.cookie_response,
+        ),
+        (
+            "s2c.select_known_packs",
+            s2c.select_known_packs,
+            "c2s.select_known_packs",
+            c2s.select_known_packs,
+        ),
+    ];
+    for (request, request_id, answer, answer_id) in exchanges {
+        if request_id.is_some() != answer_id.is_some() {
+            bail!("{} и {} задаются только вместе", request, answer);
+        }
+        if request_id.is_some() && present == 0 {
+            bail!("{} задан, но у версии нет фазы конфигурации", request);
+        }
+    }
+
+    match (table.layout.position, c2s.accept_teleportation) {
+        (PositionLayout::NoConfirm, Some(_)) => {
+            bail!("c2s.accept_teleportation не используется при layout.position = \"no_confirm\"")
+        }
+        (PositionLayout::Legacy | PositionLayout::LegacyDismount | PositionLayout::Delta, None) => {
+            bail!("c2s.accept_teleportation обязателен, если клиент подтверждает телепорт")
+        }
+        _ => {}
+    }
+
+    // Packets the proxy tells apart within one phase must not collide.
+    let c2s_configuration = [
+        ("c2s.finish_configuration", c2s.finish_configuration),
+        ("c2s.cookie_response", c2s.cookie_response),
+        ("c2s.select_known_packs", c2s.select_known_packs),
+    ];
+    let s2c_configuration = [
+        ("s2c.finish_configuration", s2c.finish_configuration),
+        ("s2c.cookie_request", s2c.cookie_request),
+        ("s2c.select_known_packs", s2c.select_known_packs),
+    ];
+    let c2s_game = [
+        (
+            "c2s.configuration_acknowledged",
+            c2s.configuration_acknowledged,
+        ),
+        ("c2s.accept_teleportation", c2s.accept_teleportation),
+        ("c2s.move_pos", Some(c2s.move_pos)),
+        ("c2s.move_pos_rot", Some(c2s.move_pos_rot)),
+        ("c2s.move_rot", Some(c2s.move_rot)),
+        ("c2s.pong", Some(c2s.pong)),
+        ("c2s.keep_alive", c2s.keep_alive),
+        ("c2s.container_close", Some(c2s.container_close)),
+        ("c2s.container_click", c2s.container_click),
+        ("c2s.set_carried_item", c2s.set_carried_item),
+        ("c2s.chat_command", c2s.chat_command),
+        ("c2s.chat_ack", c2s.chat_ack),
+        ("c2s.chat", Some(c2s.chat)),
+    ];
+    let s2c_game = [
+        ("s2c.start_configuration", s2c.start_configuration),
+        ("s2c.position", Some(s2c.position)),
+        ("s2c.ping", Some(s2c.ping)),
+        ("s2c.keep_alive", s2c.keep_alive),
+        ("s2c.container_close", Some(s2c.container_close)),
+        ("s2c.open_screen", s2c.open_screen),
+        ("s2c.container_set_content", s2c.container_set_content),
+        ("s2c.container_set_slot", s2c.container_set_slot),
+        ("s2c.set_cursor_item", s2c.set_cursor_item),
+        ("s2c.set_carried_item", s2c.set_carried_item),
+        ("s2c.system_chat", s2c.system_chat),
+        ("s2c.level_chunk", s2c.level_chunk),
+        ("s2c.forget_level_chunk", s2c.forget_level_chunk),
+        ("s2c.light_update", s2c.light_update),
+    ];
+    for (name, id) in c2s_game
+        .iter()
+        .chain(&s2c_game)
+        .chain(&c2s_configuration)
+        .chain(&s2c_configuration)
+        .chain(&configuration)
+    {
+        if let Some(id) = *id
+            && !(0..=MAX_PACKET_ID).contains(&id)
+        {
+            bail!(
+                "{} = {}: id должен быть от 0 до {}",
+                name,
+                id,
+                MAX_PACKET_ID
+            );
+        }
+    }
+    for group in [
+        &c2s_game[..],
+        &s2c_game[..],
+        &c2s_configuration[..],
+        &s2c_configuration[..],
+    ] {
+        for (i, (name, id)) in group.iter().enumerate() {
+            if id.is_none() {
+                continue;
+            }
+            if let Some((other, _)) = group[..i].iter().find(|(_, other)| other == id) {
+                bail!("{} и {} имеют одинаковый id {}", other, name, id.unwrap());
+            }
+        }
+    }
+
+    // A packet the proxy reads must still reach the world cache.
+    for &id in s2c.transient.iter() {
+        if !(0..=MAX_PACKET_ID).contains(&id) {
+            bail!(
+                "s2c.transient: id {} должен быть от 0 до {}",
+                id,
+                MAX_PACKET_ID
+            );
+        }
+        if let Some((name, _)) = s2c_game.iter().find(|(_, other)| *other == Some(id)) {
+            bail!("s2c.transient: id {} уже задан как {}", id, name);
+        }
+    }
+
+    let categories = &table.categories;
+    for (i, category) in PacketCategory::ALL.into_iter().enumerate() {
+        for &id in categories.ids(category) {
+            if !(0..=MAX_PACKET_ID).contains(&id) {
+                bail!(
+                    "categories.{}: id {} должен быть от 0 до {}",
+                    category.name(),
+                    id,
+                    MAX_PACKET_ID
+                );
+            }
+            if let Some(other) = PacketCategory::ALL[..i]
+                .iter()
+                .find(|&&other| categories.ids(other).contains(&id))
+            {
+                bail!(
+                    "id {} указан и в categories.{}, и в categories.{}",
+                    id,
+                    other.name(),
+                    category.name()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads every definition in `dir` and registers the valid ones, logging each
+/// file's outcome. A missing directory is not an error. Returns how many
+/// versions were registered.
+pub fn load_dir(dir: &Path, log: &Logger) -> usize {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            matches!(
+                p.extension().and_then(|e| e.to_str()),
+                Some("toml" | "json")
+            )
+        })
+        .collect();
+    paths.sort();
+
+    let mut loaded = 0;
+    for path in paths {
+        match load_file(&path) {
+            Ok(table) => {
+                let releases = table
+                    .releases()
+                    .map(|(version, protocol)| format!("{} ({})", version, protocol))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if register(table) {
+                    log.warn(format!(
+                        "{}: версия {} заменяет встроенную",
+                        path.display(),
+                        releases
+                    ));
+                } else {
+                    log.success(format!(
+                        "Загружена версия {} из {}",
+                        releases,
+                        path.display()
+                    ));
+                }
+                loaded += 1;
+            }
+            Err(e) => log.error(format!("{}: {:#}", path.display(), e)),
+        }
+    }
+    loaded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::{BUILTIN, table::ChatLayout};
+
+    const LEGACY: &str = r#"
+        version = "1.20.1"
+        protocol = 763
+
+        [c2s]
+        accept_teleportation = 0
+        move_pos = 20
+        move_pos_rot = 21
+        move_rot = 22
+        pong = 32
+        container_close = 12
+        chat_command = 4
+        chat = 5
+
+        [s2c]
+        position = 60
+        ping = 50
+        container_close = 17
+
+        [layout]
+        position = "legacy"
+        ping = "ping"
+    "#;
+
+    fn parse(text: &str) -> anyhow::Result<PacketTable> {
+        let table: PacketTable = toml::from_str(text)?;
+        validate(&table)?;
+        Ok(table)
+    }
+
+    #[test]
+    fn builtin_tables_are_valid() {
+        for table in BUILTIN {
+            validate(table).unwrap_or_else(|e| panic!("{}: {}", table.version, e));
+        }
+    }
+
+    #[test]
+    fn builtin_categories_hold_the_named_packets() {
+        for table in BUILTIN {
+            let (c2s, categories) = (&table.c2s, &table.categories);
+            let expected = [
+                (c2s.chat, PacketCategory::Chat),
+                (c2s.move_pos, PacketCategory::Movement),
+                (c2s.move_pos_rot, PacketCategory::Movement),
+                (c2s.move_rot, PacketCategory::Movement),
+                (c2s.container_close, PacketCategory::Inventory),
+            ]
+            .into_iter()
+            .chain(
+                [
+                    (c2s.chat_command, PacketCategory::Commands),
+                    (c2s.container_click, PacketCategory::Inventory),
+                    (c2s.set_carried_item, PacketCategory::Inventory),
+                ]
+                .into_iter()
+                .filter_map(|(id, category)| Some((id?, category))),
+            );
+            for (id, category) in expected {
+                assert_eq!(
+                    categories.of(id),
+                    Some(category),
+                    "{}: {}",
+                    table.version,
+                    id
+                );
+            }
+            for id in [Some(c2s.pong), c2s.accept_teleportation, c2s.keep_alive]
+                .into_iter()
+                .flatten()
+            {
+                assert_eq!(categories.of(id), None, "{}: {}", table.version, id);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_a_version_without_configuration() {
+        let table = parse(LEGACY).unwrap();
+        assert!(!table.has_configuration());
+        assert_eq!(table.c2s.move_pos, 20);
+    }
+
+    #[test]
+    fn parses_aliases() {
+        let text = LEGACY.replace(
+            "protocol = 763",
+            "protocol = 763\naliases = [{ version = \"1.20\", protocol = 763 }]",
+        );
+        let table = parse(&text).unwrap();
+        assert_eq!(
+            table.releases().collect::<Vec<_>>(),
+            [("1.20", 763), ("1.20.1", 763)]
+        );
+    }
+
+    #[test]
+    fn chat_keys_are_optional() {
+        assert_eq!(parse(LEGACY).unwrap().s2c.system_chat, None);
+
+        let text = LEGACY
+            .replace(
+                "container_close = 17",
+                "container_close = 17\nsystem_chat = 100",
+            )
+            .replace("ping = \"ping\"", "ping = \"ping\"\nchat = \"json\"");
+        let table = parse(&text).unwrap();
+        assert_eq!(table.s2c.system_chat, Some(100));
+        assert_eq!(table.layout.chat, ChatLayout::Json);
+    }
+
+    #[test]
+    fn categories_are_optional() {
+        assert_eq!(parse(LEGACY).unwrap().categories.of(5), None);
+
+        let text = format!("{}\n[categories]\nchat = [5]\ncommands = [4, 9]\n", LEGACY);
+        let table = parse(&text).unwrap();
+        assert_eq!(table.categories.of(5), Some(PacketCategory::Chat));
+        assert_eq!(table.categories.of(9), Some(PacketCategory::Commands));
+    }
+
+    #[test]
+    fn rejects_an_id_in_two_categories() {
+        let text = format!("{}\n[categories]\nchat = [5]\ncommands = [4, 5]\n", LEGACY);
+        let err = parse(&text).unwrap_err().to_string();
+        assert!(err.contains("categories.chat"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_partial_configuration() {
+        let text = LEGACY.replace("[c2s]", "[c2s]\nlogin_acknowledged = 3");
+        let err = parse(&text).unwrap_err().to_string();
+        assert!(err.contains("c2s.finish_configuration"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_exchange_without_configuration() {
+        let text = LEGACY
+            .replace("[c2s]", "[c2s]\ncookie_response = 1")
+            .replace("[s2c]", "[s2c]\ncookie_request = 0");
+        let err = parse(&text).unwrap_err().to_string();
+        assert!(err.contains("s2c.cookie_request"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_colliding_ids() {
+        let text = LEGACY.replace("move_rot = 22", "move_rot = 21");
+        let err = parse(&text).unwrap_err().to_string();
+        assert!(err.contains("c2s.move_pos_rot и c2s.move_rot"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_a_transient_packet_the_proxy_reads() {
+        let text = LEGACY.replace(
+            "container_close = 17",
+            "container_close = 17\ntransient = [4, 60]",
+        );
+        let err = parse(&text).unwrap_err().to_string();
+        assert!(err.contains("s2c.position"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_teleport_confirm_mismatch() {
+        let text = LEGACY.replace("position = \"legacy\"", "position = \"no_confirm\"");
+        let err = parse(&text).unwrap_err().to_string();
+        assert!(err.contains("c2s.accept_teleportation"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let text = LEGACY.replace("move_pos = 20", "move_pso = 20");
+        assert!(parse(&text).is_err());
+    }
+}
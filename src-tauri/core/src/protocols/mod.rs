@@ -0,0 +1,224 @@
+pub mod definitions;
+pub mod packets;
+mod sync;
+pub mod table;
+pub mod v1_12_2;
+pub mod v1_16_5;
+pub mod v1_17_1;
+pub mod v1_18_2;
+pub mod v1_19_2;
+pub mod v1_19_4;
+pub mod v1_20_1;
+pub mod v1_20_2;
+pub mod v1_20_4;
+pub mod v1_20_6;
+pub mod v1_21_11;
+pub mod v1_21_3;
+pub mod v1_21_4;
+pub mod v1_21_5;
+pub mod v1_21_8;
+pub mod v1_8_9;
+mod world;
+
+use std::{sync::RwLock, time::Duration};
+
+use mc_protocol::packet::{RawPacket, UncompressedPacket};
+use uuid::Uuid;
+
+use crate::controller::ClientId;
+use table::{PacketCategory, PacketTable};
+
+pub use sync::{State, Version};
+
+/// Built-in versions, oldest first.
+pub static BUILTIN: &[&PacketTable] = &[
+    &v1_8_9::TABLE,
+    &v1_12_2::TABLE,
+    &v1_16_5::TABLE,
+    &v1_17_1::TABLE,
+    &v1_18_2::TABLE,
+    &v1_19_2::TABLE,
+    &v1_19_4::TABLE,
+    &v1_20_1::TABLE,
+    &v1_20_2::TABLE,
+    &v1_20_4::TABLE,
+    &v1_20_6::TABLE,
+    &v1_21_3::TABLE,
+    &v1_21_4::TABLE,
+    &v1_21_5::TABLE,
+    &v1_21_8::TABLE,
+    &v1_21_11::TABLE,
+];
+
+/// Tables registered at runtime from definition files. Looked up before
+/// [`BUILTIN`], so a file can also correct a built-in version.
+static LOADED: RwLock<Vec<&'static PacketTable>> = RwLock::new(Vec::new());
+
+/// Makes `table` available to [`Version::from_protocol`], replacing any table
+/// loaded earlier for one of the same protocols. Returns `true` if it shadows
+/// a built-in version.
+///
+/// Tables are kept for the rest of the process; loading happens once at startup.
+pub fn register(table: PacketTable) -> bool {
+    let table: &'static PacketTable = Box::leak(Box::new(table));
+    let mut loaded = LOADED.write().unwrap();
+    loaded.retain(|t| !t.releases().any(|(_, p)| table.covers(p)));
+    loaded.push(table);
+    BUILTIN
+        .iter()
+        .any(|t| t.releases().any(|(_, p)| table.covers(p)))
+}
+
+/// Every known release as `(version, protocol, table)`, sorted by protocol
+/// number. A loaded table hides the built-in releases of its protocols.
+fn releases() -> Vec<(&'static str, i32, &'static PacketTable)> {
+    let loaded = LOADED.read().unwrap();
+    let mut releases: Vec<_> = loaded
+        .iter()
+        .flat_map(|&t| t.releases().map(move |(v, p)| (v, p, t)))
+        .collect();
+    for &builtin in BUILTIN {
+        for (version, protocol) in builtin.releases() {
+            if !loaded.iter().any(|t| t.covers(protocol)) {
+                releases.push((version, protocol, builtin));
+            }
+        }
+    }
+    releases.sort_by_key(|&(_, protocol, _)| protocol);
+    releases
+}
+
+pub trait VersionProtocol {
+    fn handle_c2s(
+        &mut self,
+        packet: &RawPacket,
+        client_id: ClientId,
+        is_active: bool,
+        both_active: bool,
+    ) -> Option<ServerBoundEvent>;
+    fn handle_s2c(&mut self, packet: &RawPacket, both_active: bool) -> Option<ClientBoundEvent>;
+    fn update_threshold(&mut self, threshold: Option<i32>);
+    /// The server's `LoginSuccess`, kept for clients that join later.
+    fn handle_login_success(&mut self, packet: RawPacket);
+    /// A client connects to the running session in the vacant `client_id`
+    /// slot. Returns what to send it, from `SetCompression` to a position
+    /// resync, or why it can't join right now.
+    fn handle_late_join(&mut self, client_id: ClientId) -> Result<Vec<RawPacket>, String>;
+    fn handle_client_disconnect(&mut self, new_active: ClientId) -> Option<ClientDisconnectEvent>;
+    /// Control moves to `new_active` while the other client stays connected.
+    fn handle_active_switch(&mut self, new_active: ClientId) -> ActiveSwitchEvent;
+    fn parse_login_start(&self, packet: &RawPacket) -> Option<String>;
+    /// LoginStart for the given player, laid out for this version.
+    fn login_start(&self, name: &str, uuid: Uuid) -> anyhow::Result<RawPacket>;
+    /// Routing category of a Game packet `client_id` sent, if its table puts
+    /// it in one.
+    fn category(&self, packet: &RawPacket, client_id: ClientId) -> Option<PacketCategory>;
+    /// `packet` decompressed, with the phase it was sent in, as read from
+    /// `from` (the server when `None`).
+    fn inspect(
+        &self,
+        packet: &RawPacket,
+        from: Option<ClientId>,
+    ) -> Option<(State, UncompressedPacket)>;
+    /// Server pings not every connected client has answered yet.
+    fn pending_pings(&self) -> usize;
+    /// How long `client_id` last took to echo a keep-alive.
+    fn client_latency(&self, client_id: ClientId) -> Option<Duration>;
+    /// The server's `LoginSuccess` decompressed, once it has arrived.
+    fn login_success(&self) -> Option<UncompressedPacket>;
+    /// A chat line from the proxy itself; `None` if the table has no way to
+    /// send one.
+    fn system_chat(&self, text: &str) -> Option<RawPacket>;
+}
+
+pub enum ServerBoundEvent {
+    SendToInactive(RawPacket),
+    SkipRelay,
+    /// Chat/command payload from the active client; still relayed upstream.
+    ProtocolMetadata {
+        data: String,
+        custom: bool,
+    },
+    /// A `#vp` command for the proxy; the packet itself is never relayed.
+    /// `ack` goes upstream in its place so the server keeps track of the chat
+    /// the active client has seen (1.19.3+).
+    ChatCommand {
+        message: String,
+        ack: Option<RawPacket>,
+    },
+    /// The active client closed a container. `mirror` closes it on the
+    /// inactive client and resends the inventory as the server last sent it;
+    /// `resync` may follow the relayed packet upstream to have the server
+    /// resend the inventory to both.
+    ContainerClosed {
+        mirror: Vec<RawPacket>,
+        resync: Option<RawPacket>,
+    },
+}
+
+pub enum ClientBoundEvent {}
+
+pub enum ClientDisconnectEvent {
+    SendToServer(Vec<RawPacket>),
+}
+
+/// What a hand-over between two connected clients leaves to send.
+pub struct ActiveSwitchEvent {
+    /// Answers only the new active client gave, held back until now.
+    pub to_server: Vec<RawPacket>,
+    /// Resyncs the new active client with the position, hotbar slot and
+    /// inventory the server knows.
+    pub to_active: Vec<RawPacket>,
+    /// A click that has the server resend the inventory to both clients, for
+    /// sessions that ask for one.
+    pub resync: Option<RawPacket>,
+}
+
+impl Version {
+    /// Construct the appropriate `Version` for the given protocol number.
+    /// Returns `None` if the protocol is not supported.
+    pub fn from_protocol(protocol: i32) -> Option<Self> {
+        releases()
+            .into_iter()
+            .find(|&(_, p, _)| p == protocol)
+            .map(|(_, protocol, table)| Version::new(table, protocol))
+    }
+
+    /// Protocol numbers accepted by [`Version::from_protocol`].
+    pub fn supported_protocols() -> Vec<i32> {
+        let mut protocols: Vec<i32> = releases().iter().map(|&(_, p, _)| p).collect();
+        protocols.dedup();
+        protocols
+    }
+
+    /// Every supported release, including those sharing a protocol number.
+    pub fn supported_versions() -> Vec<String> {
+        releases().iter().map(|(v, _, _)| v.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_resolve_to_their_table() {
+        for protocol in [771, 772] {
+            let version = Version::from_protocol(protocol).unwrap();
+            assert!(std::ptr::eq(version.table(), &v1_21_8::TABLE));
+        }
+        let versions = Version::supported_versions();
+        let position = |v: &str| versions.iter().position(|s| s == v).unwrap();
+        assert!(position("1.21.6") < position("1.21.7"));
+        assert!(position("1.21.7") < position("1.21.8"));
+    }
+
+    #[test]
+    fn protocols_are_listed_once() {
+        let protocols = Version::supported_protocols();
+        let mut unique = protocols.clone();
+        unique.dedup();
+        assert_eq!(protocols, unique);
+        assert!(protocols.contains(&767) && protocols.contains(&773));
+    }
+}
@@ -0,0 +1,147 @@
+//! Packet bodies shared by every version. Ids come from the version's
+//! [`PacketTable`](super::table::PacketTable), so none of these carry
+//! `#[packet(..)]`.
+//!
+//! Serverbound movement packets end in `on_ground: bool` (or `flags: u8` since
+//! 1.21.2); the proxy never needs that byte, so the structs stop before it.
+
+use mc_protocol::{Packet, varint::VarInt};
+
+pub mod c2s {
+    use super::*;
+
+    #[derive(Packet, Debug)]
+    pub struct AcceptTeleportation {
+        pub id: VarInt,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct MovePos {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct MovePosRot {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+        pub yaw: f32,
+        pub pitch: f32,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct MoveRot {
+        pub yaw: f32,
+        pub pitch: f32,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct Pong {
+        pub id: i32,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct SetCarriedItem {
+        pub slot: i16,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct ChatAck {
+        pub offset: VarInt,
+    }
+}
+
+pub mod s2c {
+    use super::*;
+    use uuid::Uuid;
+
+    /// `ClientboundChatPacket` before 1.16; `position` 1 is a system line.
+    #[derive(Packet, Debug)]
+    pub struct ChatLegacy {
+        pub message: String,
+        pub position: i8,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct ChatLegacySender {
+        pub message: String,
+        pub position: i8,
+        pub sender: Uuid,
+    }
+
+    /// `ClientboundSystemChatPacket` while components were still JSON.
+    #[derive(Packet, Debug)]
+    pub struct SystemChatJson {
+        pub content: String,
+        pub overlay: bool,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct Ping {
+        pub id: i32,
+    }
+
+    /// The slot is a VarInt since 1.21.2; for hotbar slots 0–8 that is the
+    /// same single byte.
+    #[derive(Packet, Debug)]
+    pub struct SetCarriedItem {
+        pub slot: i8,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct PositionNoConfirm {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+        pub yaw: f32,
+        pub pitch: f32,
+        pub relative_flags: u8,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct PositionLegacy {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+        pub yaw: f32,
+        pub pitch: f32,
+        pub relative_flags: u8,
+        pub id: VarInt,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct PositionLegacyDismount {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+        pub yaw: f32,
+        pub pitch: f32,
+        pub relative_flags: u8,
+        pub id: VarInt,
+        pub dismount_vehicle: bool,
+    }
+
+    #[derive(Packet, Debug)]
+    pub struct PositionDelta {
+        pub id: VarInt,
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+        pub delta_x: f64,
+        pub delta_y: f64,
+        pub delta_z: f64,
+        pub yaw: f32,
+        pub pitch: f32,
+        pub relative_flags: i32,
+    }
+}
+
+/// Same layout in both directions.
+#[derive(Packet, Debug, Clone)]
+pub struct ContainerAck {
+    pub container_id: i8,
+    pub uid: i16,
+    pub accepted: bool,
+}
@@ -0,0 +1,1153 @@
+//! The one state machine every version runs: phase tracking, position and
+//! hotbar mirroring, inventory resyncs, ping and Configuration-answer de-duplication, failover and
+//! hand-over replay, and the world cache clients joining late are caught up
+//! from. Everything that differs between versions is looked up in the version's [`PacketTable`].
+
+use std::{
+    io::Cursor,
+    time::{Duration, Instant},
+};
+
+use mc_protocol::{
+    packet::{RawPacket, UncompressedPacket},
+    ser::{Deserialize, Serialize},
+    varint::VarInt,
+};
+
+use super::{
+    ActiveSwitchEvent, ClientBoundEvent, ClientDisconnectEvent, ServerBoundEvent, VersionProtocol,
+    packets::{ContainerAck, c2s, s2c},
+    table::{ChatLayout, ClickLayout, PacketCategory, PacketTable, PingLayout, PositionLayout},
+    world::WorldCache,
+};
+use crate::{
+    chat_command::ChatCommand, controller::ClientId, latency::Echoes,
+    packets::universal::login::s2c::SetCompression,
+};
+use uuid::Uuid;
+
+const TELEPORT_ID: i32 = 1000;
+/// Action number of the proxy's own clicks before 1.17, echoed back in the
+/// server's `ContainerAck`. Clients count theirs up from 1.
+const RESYNC_ACTION: i16 = i16::MIN;
+/// Colour of the proxy's own chat lines.
+const CHAT_COLOR: &str = "gold";
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum State {
+    Login,
+    Configuration,
+    Game,
+}
+
+/// Phase of each direction of one client's connection.
+#[derive(Debug, Clone, Copy)]
+struct ClientState {
+    /// Phase of the packets the client receives.
+    inbound: State,
+    /// Phase of the packets the client sends.
+    outbound: State,
+    /// Mirror teleports sent to the client that it hasn't confirmed yet.
+    mirrors: u32,
+}
+
+/// Last known player position, mirrored to the inactive client.
+#[derive(Debug, Default)]
+struct Position {
+    x: f64,
+    y: f64,
+    z: f64,
+    yaw: f32,
+    pitch: f32,
+    /// Whether the active client has reported its coordinates yet.
+    known: bool,
+}
+
+/// The containers and cursor item as the server last sent them, resent to a
+/// client whose own view may have drifted.
+#[derive(Debug, Default)]
+struct Inventory {
+    /// Latest `ContainerSetContent` per container.
+    contents: Vec<(i32, RawPacket)>,
+    /// `ContainerSetSlot`s since, one per container and slot.
+    slots: Vec<((i32, i16), RawPacket)>,
+    /// The cursor item, unless a later content packet carried it.
+    cursor: Option<RawPacket>,
+}
+
+impl Inventory {
+    /// Before 1.17 the content packet leaves the cursor alone.
+    fn set_content(&mut self, container_id: i32, packet: RawPacket, carries_cursor: bool) {
+        self.contents.retain(|(id, _)| *id != container_id);
+        self.slots.retain(|((id, _), _)| *id != container_id);
+        self.contents.push((container_id, packet));
+        if carries_cursor {
+            self.cursor = None;
+        }
+    }
+
+    fn set_slot(&mut self, target: (i32, i16), packet: RawPacket) {
+        // Where the cursor item went before it got a packet of its own.
+        if target == (-1, -1) {
+            self.cursor = Some(packet);
+            return;
+        }
+        self.slots.retain(|(slot, _)| *slot != target);
+        self.slots.push((target, packet));
+    }
+
+    /// Drops what the server sent for an open container once it is gone; the
+    /// player's own inventory (0, and -2 for its slots directly) stays. The
+    /// cursor item goes back into it on every close.
+    fn forget_containers(&mut self) {
+        self.contents.retain(|(id, _)| *id <= 0);
+        self.slots.retain(|((id, _), _)| *id <= 0);
+        self.cursor = None;
+    }
+
+    fn packets(&self) -> impl Iterator<Item = RawPacket> + '_ {
+        self.contents
+            .iter()
+            .map(|(_, packet)| packet)
+            .chain(self.slots.iter().map(|(_, packet)| packet))
+            .chain(&self.cursor)
+            .cloned()
+    }
+}
+
+pub struct Version {
+    table: &'static PacketTable,
+    /// The release actually connected, which may be one of the table's aliases.
+    protocol: i32,
+    primary: ClientState,
+    secondary: ClientState,
+    position: Position,
+    pings: Vec<PingSync>,
+    exchanges: Vec<Exchange>,
+    /// Keep-alives on their way to the clients, timed for their latency.
+    echoes: Echoes,
+    threshold: Option<i32>,
+    world: WorldCache,
+    /// Hotbar slot the server last saw selected.
+    held_slot: Option<i8>,
+    /// Container the server has open for the player; 0 is the inventory.
+    container_id: i32,
+    inventory: Inventory,
+}
+
+impl Version {
+    pub fn new(table: &'static PacketTable, protocol: i32) -> Self {
+        let initial = if table.has_configuration() {
+            State::Login
+        } else {
+            State::Game
+        };
+        let state = ClientState {
+            inbound: initial,
+            outbound: initial,
+            mirrors: 0,
+        };
+        Self {
+            table,
+            protocol,
+            primary: state,
+            secondary: state,
+            position: Position::default(),
+            pings: vec![],
+            exchanges: vec![],
+            echoes: Echoes::default(),
+            threshold: None,
+            world: WorldCache::default(),
+            held_slot: None,
+            container_id: 0,
+            inventory: Inventory::default(),
+        }
+    }
+
+    pub fn table(&self) -> &'static PacketTable {
+        self.table
+    }
+
+    pub fn protocol(&self) -> i32 {
+        self.protocol
+    }
+
+    fn state(&self, client_id: ClientId) -> ClientState {
+        match client_id {
+            ClientId::Primary => self.primary,
+            ClientId::Secondary => self.secondary,
+        }
+    }
+
+    /// Both clients receive the same clientbound stream, so the server is in
+    /// whichever phase the furthest-along client has acknowledged.
+    fn server_state(&self) -> State {
+        self.primary.inbound.max_with(self.secondary.inbound)
+    }
+
+    fn client(&mut self, client_id: ClientId) -> &mut ClientState {
+        match client_id {
+            ClientId::Primary => &mut self.primary,
+            ClientId::Secondary => &mut self.secondary,
+        }
+    }
+
+    fn set_inbound(&mut self, state: State) {
+        self.primary.inbound = state;
+        self.secondary.inbound = state;
+    }
+
+    fn encode<P: Serialize>(&self, packet_id: i32, packet: &P) -> anyhow::Result<RawPacket> {
+        let mut payload = Vec::new();
+        packet.serialize(&mut payload)?;
+        Ok(UncompressedPacket::new(packet_id, payload).to_raw_packet_compressed(self.threshold)?)
+    }
+}
+
+impl VersionProtocol for Version {
+    fn handle_c2s(
+        &mut self,
+        packet: &RawPacket,
+        client_id: ClientId,
+        is_active: bool,
+        both_active: bool,
+    ) -> Option<ServerBoundEvent> {
+        match self.client(client_id).outbound {
+            State::Login => self.handle_c2s_login(packet, client_id),
+            State::Configuration => {
+                self.handle_c2s_configuration(packet, client_id, is_active, both_active)
+            }
+            State::Game => self.handle_c2s_game(packet, client_id, is_active, both_active),
+        }
+        .unwrap_or_default()
+    }
+
+    fn handle_s2c(&mut self, packet: &RawPacket, both_active: bool) -> Option<ClientBoundEvent> {
+        match self.server_state() {
+            State::Login => Ok(None),
+            State::Configuration => self.handle_s2c_configuration(packet, both_active),
+            State::Game => self.handle_s2c_game(packet, both_active),
+        }
+        .unwrap_or_default()
+    }
+
+    fn update_threshold(&mut self, threshold: Option<i32>) {
+        self.threshold = threshold;
+    }
+
+    fn handle_login_success(&mut self, packet: RawPacket) {
+        self.world.set_login_success(packet);
+    }
+
+    fn handle_late_join(&mut self, client_id: ClientId) -> Result<Vec<RawPacket>, String> {
+        if self.client(client_id.opposite()).inbound != State::Game {
+            return Err("Сервер перенастраивает соединение, попробуйте позже".to_string());
+        }
+        let mut packets = vec![];
+        if let Some(threshold) = self.threshold {
+            let compression = SetCompression {
+                threshold: VarInt(threshold),
+            };
+            packets.push(
+                UncompressedPacket::from_packet(&compression)
+                    .and_then(|packet| packet.to_raw_packet())
+                    .map_err(|e| e.to_string())?,
+            );
+        }
+        packets.extend(self.world.replay()?);
+
+        // The replay already takes the client into Game; only what it sends
+        // still starts at Login.
+        *self.client(client_id) = ClientState {
+            inbound: State::Game,
+            outbound: if self.table.has_configuration() {
+                State::Login
+            } else {
+                State::Game
+            },
+            mirrors: 0,
+        };
+        if self.position.known
+            && let Ok(Some(packet)) = self.position_packet(client_id)
+        {
+            packets.push(packet);
+        }
+        if let Ok(Some(packet)) = self.held_slot_packet(client_id) {
+            packets.push(packet);
+        }
+        Ok(packets)
+    }
+
+    fn handle_client_disconnect(&mut self, new_active: ClientId) -> Option<ClientDisconnectEvent> {
+        self.echoes.forget(new_active.opposite());
+        let mut packets = vec![];
+        let pings = std::mem::take(&mut self.pings);
+
+        for ping in pings {
+            if ping.is_sent(new_active) {
+                if !ping.relayed
+                    && let Ok(packet) = self.pong(ping.key)
+                {
+                    packets.push(packet);
+                }
+            } else {
+                self.pings.push(ping);
+            }
+        }
+
+        // Configuration answers only the new active client gave are still owed
+        // to the server.
+        self.exchanges.retain_mut(|exchange| {
+            if exchange.relayed {
+                return exchange.answer(new_active).is_pending();
+            }
+            if let Answer::Held(packet) =
+                std::mem::replace(exchange.answer(new_active), Answer::Done)
+            {
+                packets.push(packet);
+                return false;
+            }
+            true
+        });
+
+        if packets.is_empty() {
+            None
+        } else {
+            Some(ClientDisconnectEvent::SendToServer(packets))
+        }
+    }
+
+    fn handle_active_switch(&mut self, new_active: ClientId) -> ActiveSwitchEvent {
+        let mut to_server = vec![];
+
+        // Answers only the new active client gave were held back; from now on
+        // the server must get them from it.
+        let keys: Vec<PingKey> = self
+            .pings
+            .iter_mut()
+            .filter(|ping| ping.is_sent(new_active) && !ping.relayed)
+            .map(|ping| {
+                ping.relayed = true;
+                ping.key
+            })
+            .collect();
+        for key in keys {
+            if let Ok(packet) = self.pong(key) {
+                to_server.push(packet);
+            }
+        }
+        for exchange in &mut self.exchanges {
+            if !exchange.relayed && matches!(exchange.answer(new_active), Answer::Held(_)) {
+                if let Answer::Held(packet) =
+                    std::mem::replace(exchange.answer(new_active), Answer::Done)
+                {
+                    to_server.push(packet);
+                }
+                exchange.relayed = true;
+            }
+        }
+
+        // While inactive the client may have walked off on its own; put it
+        // back where the server last saw the player.
+        let mut to_active = vec![];
+        if self.position.known
+            && let Ok(Some(packet)) = self.position_packet(new_active)
+        {
+            to_active.push(packet);
+        }
+        // The same goes for the hotbar slot and whatever it clicked around in
+        // its inventory.
+        if let Ok(Some(packet)) = self.held_slot_packet(new_active) {
+            to_active.push(packet);
+        }
+        if self.client(new_active).inbound == State::Game {
+            to_active.extend(self.inventory.packets());
+        }
+
+        ActiveSwitchEvent {
+            to_server,
+            to_active,
+            resync: self.resync_click().ok().flatten(),
+        }
+    }
+
+    fn parse_login_start(&self, packet: &RawPacket) -> Option<String> {
+        // Every version's LoginStart begins with the player name. What follows
+        // (signature data on 1.19–1.19.2, the UUID since 1.19.1) isn't needed.
+        let packet = packet.as_uncompressed().ok()?;
+        Deserialize::deserialize(&mut Cursor::new(packet.payload)).ok()
+    }
+
+    fn login_start(&self, name: &str, uuid: Uuid) -> anyhow::Result<RawPacket> {
+        let protocol = self.table.protocol;
+        let mut payload = Vec::new();
+        name.serialize(&mut payload)?;
+        if self.table.has_configuration() {
+            uuid.serialize(&mut payload)?;
+        } else if protocol >= 761 {
+            Some(uuid).serialize(&mut payload)?;
+        } else if protocol >= 759 {
+            // No signature data: the proxy has no chat key to offer.
+            false.serialize(&mut payload)?;
+            if protocol >= 760 {
+                Some(uuid).serialize(&mut payload)?;
+            }
+        }
+        Ok(UncompressedPacket::new(0, payload).to_raw_packet()?)
+    }
+
+    fn category(&self, packet: &RawPacket, client_id: ClientId) -> Option<PacketCategory> {
+        if self.state(client_id).outbound != State::Game {
+            return None;
+        }
+        let packet = packet.uncompress(self.threshold).ok()?;
+        self.table.categories.of(packet.packet_id)
+    }
+
+    fn inspect(
+        &self,
+        packet: &RawPacket,
+        from: Option<ClientId>,
+    ) -> Option<(State, UncompressedPacket)> {
+        let state = match from {
+            Some(client_id) => self.state(client_id).outbound,
+            None => self.server_state(),
+        };
+        Some((state, packet.uncompress(self.threshold).ok()?))
+    }
+
+    fn pending_pings(&self) -> usize {
+        self.pings.len()
+    }
+
+    fn client_latency(&self, client_id: ClientId) -> Option<Duration> {
+        self.echoes.client(client_id)
+    }
+
+    fn login_success(&self) -> Option<UncompressedPacket> {
+        self.world.login_success()?.uncompress(self.threshold).ok()
+    }
+
+    fn system_chat(&self, text: &str) -> Option<RawPacket> {
+        let id = self.table.s2c.system_chat?;
+        let text = format!("[VoxelProxy] {}", text);
+        let json = || serde_json::json!({ "text": text, "color": CHAT_COLOR }).to_string();
+        let packet = match self.table.layout.chat {
+            ChatLayout::Legacy => self.encode(
+                id,
+                &s2c::ChatLegacy {
+                    message: json(),
+                    position: 1,
+                },
+            ),
+            ChatLayout::LegacySender => self.encode(
+                id,
+                &s2c::ChatLegacySender {
+                    message: json(),
+                    position: 1,
+                    sender: Uuid::nil(),
+                },
+            ),
+            ChatLayout::Json => self.encode(
+                id,
+                &s2c::SystemChatJson {
+                    content: json(),
+                    overlay: false,
+                },
+            ),
+            ChatLayout::Nbt => {
+                let mut payload = nbt_text(&text, CHAT_COLOR);
+                false.serialize(&mut payload).ok()?;
+                UncompressedPacket::new(id, payload)
+                    .to_raw_packet_compressed(self.threshold)
+                    .map_err(Into::into)
+            }
+        };
+        packet.ok()
+    }
+}
+
+/// `{text, color}` as a nameless network-NBT compound. NBT strings are
+/// modified UTF-8, which only differs from UTF-8 for NUL and characters
+/// outside the BMP; the proxy's messages have neither.
+fn nbt_text(text: &str, color: &str) -> Vec<u8> {
+    const COMPOUND: u8 = 0x0A;
+    const STRING: u8 = 0x08;
+    const END: u8 = 0x00;
+
+    let mut nbt = vec![COMPOUND];
+    for (key, value) in [("text", text), ("color", color)] {
+        nbt.push(STRING);
+        for s in [key, value] {
+            nbt.extend_from_slice(&(s.len() as u16).to_be_bytes());
+            nbt.extend_from_slice(s.as_bytes());
+        }
+    }
+    nbt.push(END);
+    nbt
+}
+
+impl State {
+    /// The later of two phases, so a client that has already moved on wins.
+    fn max_with(self, other: State) -> State {
+        match (self, other) {
+            (State::Game, _) | (_, State::Game) => State::Game,
+            (State::Configuration, _) | (_, State::Configuration) => State::Configuration,
+            _ => State::Login,
+        }
+    }
+}
+
+impl Version {
+    fn handle_s2c_configuration(
+        &mut self,
+        packet: &RawPacket,
+        both_active: bool,
+    ) -> anyhow::Result<Option<ClientBoundEvent>> {
+        let raw = packet;
+        let packet = raw.uncompress(self.threshold)?;
+        let (c2s, s2c) = (&self.table.c2s, &self.table.s2c);
+        let id = Some(packet.packet_id);
+
+        // A cookie is asked of each client anew; a late one can't answer the
+        // server's old request.
+        if id != s2c.cookie_request {
+            self.world.record_configuration(raw.clone());
+        }
+
+        if id == s2c.finish_configuration {
+            self.set_inbound(State::Game);
+        } else if both_active {
+            let answer_id = if id == s2c.cookie_request {
+                c2s.cookie_response
+            } else if id == s2c.select_known_packs {
+                c2s.select_known_packs
+            } else {
+                None
+            };
+            if let Some(answer_id) = answer_id {
+                self.exchanges.push(Exchange::new(answer_id));
+            }
+        }
+        Ok(None)
+    }
+
+    fn handle_s2c_game(
+        &mut self,
+        packet: &RawPacket,
+        both_active: bool,
+    ) -> anyhow::Result<Option<ClientBoundEvent>> {
+        let raw = packet;
+        let packet = raw.uncompress(self.threshold)?;
+        let ids = &self.table.s2c;
+
+        if Some(packet.packet_id) == ids.keep_alive {
+            self.echoes.sent(&packet.payload, Instant::now());
+        }
+        if Some(packet.packet_id) == ids.start_configuration {
+            self.set_inbound(State::Configuration);
+            self.exchanges.clear();
+            self.world.start_configuration();
+            self.inventory = Inventory::default();
+        } else if packet.packet_id == ids.ping {
+            // Never cached: the answer would reach the server long after it
+            // stopped waiting.
+            if !both_active {
+                return Ok(None);
+            }
+            let key = match self.table.layout.ping {
+                PingLayout::Ping => PingKey::Id(packet.deserialize_payload::<s2c::Ping>()?.id),
+                PingLayout::ContainerAck => {
+                    let ack: ContainerAck = packet.deserialize_payload()?;
+                    PingKey::Container {
+                        container_id: ack.container_id,
+                        uid: ack.uid,
+                    }
+                }
+            };
+            self.pings.push(PingSync::new(key));
+        } else {
+            self.track_inventory(raw, &packet);
+            self.cache_game(raw, &packet);
+        }
+
+        Ok(None)
+    }
+
+    /// Follows what the server itself changes: the selected hotbar slot, the
+    /// open container, its contents and the cursor item.
+    fn track_inventory(&mut self, raw: &RawPacket, packet: &UncompressedPacket) {
+        let ids = &self.table.s2c;
+        let id = Some(packet.packet_id);
+        // Container packets carry a state id since the click did.
+        let stateful = self.table.layout.click == ClickLayout::StateId;
+
+        if id == ids.set_carried_item
+            && let Ok(held) = packet.deserialize_payload::<s2c::SetCarriedItem>()
+        {
+            self.held_slot = Some(held.slot);
+        } else if id == ids.open_screen
+            && let Ok(container_id) = VarInt::deserialize(&mut Cursor::new(&packet.payload))
+        {
+            // A u8 before 1.14; ids wrap at 100, so it reads the same.
+            self.container_id = container_id.0;
+            self.inventory.forget_containers();
+        } else if packet.packet_id == ids.container_close {
+            self.container_id = 0;
+            self.inventory.forget_containers();
+        } else if id == ids.container_set_content
+            && let Some(container_id) = self.read_container_id(&mut Cursor::new(&packet.payload))
+        {
+            self.inventory
+                .set_content(container_id, raw.clone(), stateful);
+        } else if id == ids.container_set_slot {
+            let mut payload = Cursor::new(&packet.payload);
+            let target = self
+                .read_container_id(&mut payload)
+                .and_then(|container_id| {
+                    if stateful {
+                        VarInt::deserialize(&mut payload).ok()?;
+                    }
+                    Some((container_id, i16::deserialize(&mut payload).ok()?))
+                });
+            if let Some(target) = target {
+                self.inventory.set_slot(target, raw.clone());
+            }
+        } else if id == ids.set_cursor_item {
+            self.inventory.cursor = Some(raw.clone());
+        }
+    }
+
+    /// A byte until the cursor got a packet of its own in 1.21.2, a VarInt
+    /// since.
+    fn read_container_id(&self, payload: &mut Cursor<&Vec<u8>>) -> Option<i32> {
+        if self.table.s2c.set_cursor_item.is_some() {
+            VarInt::deserialize(payload).ok().map(|id| id.0)
+        } else {
+            i8::deserialize(payload).ok().map(i32::from)
+        }
+    }
+
+    /// Adds a Game packet to the world cache, keyed by chunk where it is one.
+    /// Keep-alives and transient packets are stale by the time a late client
+    /// would see them, so they're left out.
+    fn cache_game(&mut self, raw: &RawPacket, packet: &UncompressedPacket) {
+        let ids = &self.table.s2c;
+        let id = Some(packet.packet_id);
+        if id == ids.keep_alive || ids.transient.contains(&packet.packet_id) {
+            return;
+        }
+        let mut payload = Cursor::new(&packet.payload);
+        let mut ints = || -> Option<(i32, i32)> {
+            Some((
+                i32::deserialize(&mut payload).ok()?,
+                i32::deserialize(&mut payload).ok()?,
+            ))
+        };
+
+        if id == ids.level_chunk
+            && let Some(pos) = ints()
+        {
+            self.world.record_chunk(pos, raw.clone());
+        } else if id == ids.forget_level_chunk
+            && let Some((a, b)) = ints()
+        {
+            // Since 1.20.2 the position is one packed long, z in the high half.
+            let pos = if self.table.has_configuration() {
+                (b, a)
+            } else {
+                (a, b)
+            };
+            self.world.forget_chunk(pos);
+        } else if id == ids.light_update
+            && let (Ok(x), Ok(z)) = (
+                VarInt::deserialize(&mut payload),
+                VarInt::deserialize(&mut payload),
+            )
+        {
+            self.world.record_light((x.0, z.0), raw.clone());
+        } else {
+            self.world.record(raw.clone());
+        }
+    }
+
+    fn handle_c2s_login(
+        &mut self,
+        packet: &RawPacket,
+        client_id: ClientId,
+    ) -> anyhow::Result<Option<ServerBoundEvent>> {
+        let packet = packet.uncompress(self.threshold)?;
+
+        if Some(packet.packet_id) == self.table.c2s.login_acknowledged {
+            let client = self.client(client_id);
+            // A client that joined late already has the Game stream queued.
+            client.inbound = client.inbound.max_with(State::Configuration);
+            client.outbound = State::Configuration;
+        }
+
+        Ok(None)
+    }
+
+    fn handle_c2s_configuration(
+        &mut self,
+        raw: &RawPacket,
+        client_id: ClientId,
+        is_active: bool,
+        both_active: bool,
+    ) -> anyhow::Result<Option<ServerBoundEvent>> {
+        let packet = raw.uncompress(self.threshold)?;
+        let id = packet.packet_id;
+
+        if Some(id) == self.table.c2s.finish_configuration {
+            self.client(client_id).outbound = State::Game;
+            return Ok(None);
+        }
+
+        let Some(i) = self
+            .exchanges
+            .iter_mut()
+            .position(|e| e.answer_id == id && e.answer(client_id).is_pending())
+        else {
+            return Ok(None);
+        };
+        let exchange = &mut self.exchanges[i];
+        let mut skip = false;
+
+        if is_active {
+            *exchange.answer(client_id) = Answer::Done;
+            skip = exchange.relayed;
+            exchange.relayed = true;
+        } else if exchange.relayed {
+            *exchange.answer(client_id) = Answer::Done;
+        } else {
+            *exchange.answer(client_id) = Answer::Held(raw.clone());
+        }
+
+        if exchange.relayed && (!both_active || !exchange.answer(client_id.opposite()).is_pending())
+        {
+            self.exchanges.remove(i);
+        }
+
+        Ok(skip.then_some(ServerBoundEvent::SkipRelay))
+    }
+
+    fn handle_c2s_game(
+        &mut self,
+        packet: &RawPacket,
+        client_id: ClientId,
+        is_active: bool,
+        both_active: bool,
+    ) -> anyhow::Result<Option<ServerBoundEvent>> {
+        let packet = packet.uncompress(self.threshold)?;
+        let ids = &self.table.c2s;
+        let id = packet.packet_id;
+
+        if Some(id) == ids.keep_alive {
+            self.echoes
+                .answered(client_id, &packet.payload, Instant::now());
+        }
+        if Some(id) == ids.configuration_acknowledged {
+            self.client(client_id).outbound = State::Configuration;
+        } else if Some(id) == ids.accept_teleportation {
+            let teleport: c2s::AcceptTeleportation = packet.deserialize_payload()?;
+
+            // With both clients connected the id may just as well be the
+            // server's own, so only swallow confirmations the client owes us.
+            let client = self.client(client_id);
+            if teleport.id.0 == TELEPORT_ID && (client.mirrors > 0 || !both_active) {
+                client.mirrors = client.mirrors.saturating_sub(1);
+                return Ok(Some(ServerBoundEvent::SkipRelay));
+            }
+        } else if id == ids.move_pos {
+            if is_active {
+                let pos: c2s::MovePos = packet.deserialize_payload()?;
+
+                self.position.x = pos.x;
+                self.position.y = pos.y;
+                self.position.z = pos.z;
+                self.position.known = true;
+                return self.send_position_to_inactive(client_id);
+            }
+        } else if id == ids.move_pos_rot {
+            if is_active {
+                let pos_rot: c2s::MovePosRot = packet.deserialize_payload()?;
+
+                self.position.x = pos_rot.x;
+                self.position.y = pos_rot.y;
+                self.position.z = pos_rot.z;
+                self.position.known = true;
+                self.position.yaw = pos_rot.yaw;
+                self.position.pitch = pos_rot.pitch;
+                return self.send_position_to_inactive(client_id);
+            }
+        } else if id == ids.move_rot {
+            if is_active {
+                let rot: c2s::MoveRot = packet.deserialize_payload()?;
+
+                self.position.yaw = rot.yaw;
+                self.position.pitch = rot.pitch;
+                return self.send_position_to_inactive(client_id);
+            }
+        } else if id == ids.pong {
+            let key = match self.table.layout.ping {
+                PingLayout::Ping => PingKey::Id(packet.deserialize_payload::<c2s::Pong>()?.id),
+                PingLayout::ContainerAck => {
+                    let ack: ContainerAck = packet.deserialize_payload()?;
+                    PingKey::Container {
+                        container_id: ack.container_id,
+                        uid: ack.uid,
+                    }
+                }
+            };
+
+            if both_active {
+                if let Some(i) = self
+                    .pings
+                    .iter()
+                    .position(|s| s.key == key && !s.is_sent(client_id))
+                {
+                    let ping = &mut self.pings[i];
+                    // After a hand-over the server may already have this
+                    // answer from the client that was active before.
+                    let skip = is_active && ping.relayed;
+                    ping.relayed |= is_active;
+                    if ping.sent(client_id) {
+                        self.pings.remove(i);
+                    }
+                    if skip {
+                        return Ok(Some(ServerBoundEvent::SkipRelay));
+                    }
+                }
+            } else if let Some(head) = self.pings.first()
+                && head.is_sent(client_id.opposite())
+            {
+                self.pings.remove(0);
+                return Ok(Some(ServerBoundEvent::SkipRelay));
+            }
+        } else if Some(id) == ids.set_carried_item {
+            if is_active {
+                let held: c2s::SetCarriedItem = packet.deserialize_payload()?;
+                // The server kicks for a slot off the hotbar; leave that to it.
+                if let Ok(slot) = i8::try_from(held.slot)
+                    && (0..9).contains(&slot)
+                {
+                    self.held_slot = Some(slot);
+                    return Ok(self
+                        .held_slot_packet(client_id.opposite())?
+                        .map(ServerBoundEvent::SendToInactive));
+                }
+            }
+        } else if id == ids.container_close {
+            // The container id is encoded the same way in both directions.
+            if is_active {
+                let close = UncompressedPacket::new(self.table.s2c.container_close, packet.payload)
+                    .to_raw_packet_compressed(self.threshold)?;
+                // The server never sends this one, so a late client needs it
+                // from the cache to close what the replay opened.
+                self.world.record(close.clone());
+                self.container_id = 0;
+                self.inventory.forget_containers();
+                if self.client(client_id.opposite()).inbound == State::Game {
+                    // Whatever the inactive client was shown of the inventory
+                    // in the meantime is put back the way the server sent it.
+                    let mirror = std::iter::once(close)
+                        .chain(self.inventory.packets())
+                        .collect();
+                    let resync = if both_active {
+                        self.resync_click()?
+                    } else {
+                        None
+                    };
+                    return Ok(Some(ServerBoundEvent::ContainerClosed { mirror, resync }));
+                }
+            }
+        } else if id == ids.chat
+            && let Ok(message) = String::deserialize(&mut Cursor::new(&packet.payload))
+            && ChatCommand::is_addressed(&message)
+        {
+            // Swallowed whatever happens next, so a malformed tail only costs
+            // the acknowledgement.
+            let ack = if is_active {
+                self.chat_ack(&packet.payload).ok().flatten()
+            } else {
+                None
+            };
+            return Ok(Some(ServerBoundEvent::ChatCommand { message, ack }));
+        } else if (id == ids.chat || Some(id) == ids.chat_command)
+            && is_active
+            && let Ok(data) = Deserialize::deserialize(&mut Cursor::new(packet.payload))
+        {
+            return Ok(Some(ServerBoundEvent::ProtocolMetadata {
+                data,
+                custom: Some(id) == ids.chat_command,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    fn send_position_to_inactive(
+        &mut self,
+        active: ClientId,
+    ) -> anyhow::Result<Option<ServerBoundEvent>> {
+        Ok(self
+            .position_packet(active.opposite())?
+            .map(ServerBoundEvent::SendToInactive))
+    }
+
+    /// Teleports `target` to the last known position, if it is in Game.
+    fn position_packet(&mut self, target: ClientId) -> anyhow::Result<Option<RawPacket>> {
+        if self.client(target).inbound != State::Game {
+            return Ok(None);
+        }
+
+        let id = self.table.s2c.position;
+        let p = &self.position;
+        let packet = match self.table.layout.position {
+            PositionLayout::NoConfirm => self.encode(
+                id,
+                &s2c::PositionNoConfirm {
+                    x: p.x,
+                    y: p.y,
+                    z: p.z,
+                    yaw: p.yaw,
+                    pitch: p.pitch,
+                    relative_flags: 0,
+                },
+            )?,
+            PositionLayout::Legacy => self.encode(
+                id,
+                &s2c::PositionLegacy {
+                    x: p.x,
+                    y: p.y,
+                    z: p.z,
+                    yaw: p.yaw,
+                    pitch: p.pitch,
+                    relative_flags: 0,
+                    id: VarInt(TELEPORT_ID),
+                },
+            )?,
+            PositionLayout::LegacyDismount => self.encode(
+                id,
+                &s2c::PositionLegacyDismount {
+                    x: p.x,
+                    y: p.y,
+                    z: p.z,
+                    yaw: p.yaw,
+                    pitch: p.pitch,
+                    relative_flags: 0,
+                    id: VarInt(TELEPORT_ID),
+                    dismount_vehicle: false,
+                },
+            )?,
+            PositionLayout::Delta => self.encode(
+                id,
+                &s2c::PositionDelta {
+                    id: VarInt(TELEPORT_ID),
+                    x: p.x,
+                    y: p.y,
+                    z: p.z,
+                    delta_x: 0.0,
+                    delta_y: 0.0,
+                    delta_z: 0.0,
+                    yaw: p.yaw,
+                    pitch: p.pitch,
+                    relative_flags: 0,
+                },
+            )?,
+        };
+        if self.table.layout.position != PositionLayout::NoConfirm {
+            self.client(target).mirrors += 1;
+        }
+        Ok(Some(packet))
+    }
+
+    /// Selects the hotbar slot the server knows on `target`, if it is in Game.
+    fn held_slot_packet(&mut self, target: ClientId) -> anyhow::Result<Option<RawPacket>> {
+        let (Some(id), Some(slot)) = (self.table.s2c.set_carried_item, self.held_slot) else {
+            return Ok(None);
+        };
+        if self.client(target).inbound != State::Game {
+            return Ok(None);
+        }
+        Ok(Some(self.encode(id, &s2c::SetCarriedItem { slot })?))
+    }
+
+    /// A click the server can't square with its own state, so it answers by
+    /// sending the open container and the cursor item in full, to both
+    /// clients. A middle click outside the window changes nothing, even in
+    /// creative, but anti-cheats may still flag it; only sent when the
+    /// session asks for resync clicks.
+    fn resync_click(&self) -> anyhow::Result<Option<RawPacket>> {
+        const OUTSIDE: i16 = -999;
+        const MIDDLE_BUTTON: i8 = 2;
+        const CLONE: VarInt = VarInt(3);
+
+        let Some(id) = self.table.c2s.container_click else {
+            return Ok(None);
+        };
+        // A u8 before 1.21.2; ids wrap at 100, so both are the same byte.
+        let container_id = VarInt(self.container_id);
+        let mut payload = Vec::new();
+        match self.table.layout.click {
+            ClickLayout::Legacy | ClickLayout::LegacyFlagged => {
+                container_id.serialize(&mut payload)?;
+                OUTSIDE.serialize(&mut payload)?;
+                MIDDLE_BUTTON.serialize(&mut payload)?;
+                RESYNC_ACTION.serialize(&mut payload)?;
+                // A byte on 1.8, which encodes the same.
+                CLONE.serialize(&mut payload)?;
+                // Claims the click picked up a stone. It didn't, and the
+                // server resends the container to correct the client.
+                if self.table.layout.click == ClickLayout::Legacy {
+                    1i16.serialize(&mut payload)?;
+                    1i8.serialize(&mut payload)?;
+                    0i16.serialize(&mut payload)?;
+                } else {
+                    true.serialize(&mut payload)?;
+                    VarInt(1).serialize(&mut payload)?;
+                    1i8.serialize(&mut payload)?;
+                }
+                // No NBT.
+                0u8.serialize(&mut payload)?;
+            }
+            ClickLayout::StateId => {
+                container_id.serialize(&mut payload)?;
+                // State ids are never negative, and a stale one is answered
+                // with everything.
+                VarInt(-1).serialize(&mut payload)?;
+                OUTSIDE.serialize(&mut payload)?;
+                MIDDLE_BUTTON.serialize(&mut payload)?;
+                CLONE.serialize(&mut payload)?;
+                // No changed slots, and an empty cursor in every item format.
+                VarInt(0).serialize(&mut payload)?;
+                0u8.serialize(&mut payload)?;
+            }
+        }
+        Ok(Some(
+            UncompressedPacket::new(id, payload).to_raw_packet_compressed(self.threshold)?,
+        ))
+    }
+
+    /// The acknowledgements a 1.19.3+ chat message carries, as a packet of
+    /// their own. `None` when there are none or the version has no such packet.
+    fn chat_ack(&self, payload: &[u8]) -> anyhow::Result<Option<RawPacket>> {
+        let Some(id) = self.table.c2s.chat_ack else {
+            return Ok(None);
+        };
+        let mut cursor = Cursor::new(payload);
+        String::deserialize(&mut cursor)?;
+        // Timestamp and salt, then the optional 256-byte signature.
+        cursor.set_position(cursor.position() + 16);
+        if bool::deserialize(&mut cursor)? {
+            cursor.set_position(cursor.position() + 256);
+        }
+        let offset = VarInt::deserialize(&mut cursor)?;
+        if offset.0 == 0 {
+            return Ok(None);
+        }
+        Ok(Some(self.encode(id, &c2s::ChatAck { offset })?))
+    }
+
+    /// The answer the server expects for `key`.
+    fn pong(&self, key: PingKey) -> anyhow::Result<RawPacket> {
+        let id = self.table.c2s.pong;
+        match key {
+            PingKey::Id(ping) => self.encode(id, &c2s::Pong { id: ping }),
+            PingKey::Container { container_id, uid } => self.encode(
+                id,
+                &ContainerAck {
+                    container_id,
+                    uid,
+                    accepted: true,
+                },
+            ),
+        }
+    }
+}
+
+/// A Configuration request both clients answer (Cookie, known packs) while the
+/// server must get exactly one answer.
+#[derive(Debug)]
+struct Exchange {
+    /// Serverbound id of the answer.
+    answer_id: i32,
+    primary: Answer,
+    secondary: Answer,
+    /// Whether an answer has reached the server.
+    relayed: bool,
+}
+
+#[derive(Debug)]
+enum Answer {
+    Pending,
+    /// Given by the inactive client and held back; replayed if it takes over.
+    Held(RawPacket),
+    Done,
+}
+
+impl Exchange {
+    fn new(answer_id: i32) -> Self {
+        Self {
+            answer_id,
+            primary: Answer::Pending,
+            secondary: Answer::Pending,
+            relayed: false,
+        }
+    }
+
+    fn answer(&mut self, client: ClientId) -> &mut Answer {
+        match client {
+            ClientId::Primary => &mut self.primary,
+            ClientId::Secondary => &mut self.secondary,
+        }
+    }
+}
+
+impl Answer {
+    fn is_pending(&self) -> bool {
+        matches!(self, Answer::Pending)
+    }
+}
+
+/// Identifies a ping across the two layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingKey {
+    Id(i32),
+    Container { container_id: i8, uid: i16 },
+}
+
+#[derive(Debug)]
+pub struct PingSync {
+    pub key: PingKey,
+    primary_sent: bool,
+    secondary_sent: bool,
+    /// Whether an answer has reached the server.
+    relayed: bool,
+}
+
+impl PingSync {
+    pub fn new(key: PingKey) -> Self {
+        Self {
+            key,
+            primary_sent: false,
+            secondary_sent: false,
+            relayed: false,
+        }
+    }
+
+    pub fn sent(&mut self, client: ClientId) -> bool {
+        match client {
+            ClientId::Primary => self.primary_sent = true,
+            ClientId::Secondary => self.secondary_sent = true,
+        }
+        self.primary_sent && self.secondary_sent
+    }
+
+    pub fn is_sent(&self, client: ClientId) -> bool {
+        match client {
+            ClientId::Primary => self.primary_sent,
+            ClientId::Secondary => self.secondary_sent,
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Declarative description of one protocol version: which packet id each
+//! logical packet has, and which wire layout the packets whose fields changed
+//! over time use. The sync logic in [`super::sync`] only ever talks to a
+//! version through its table.
+//!
+//! Built-in tables live in `protocols/v*.rs`; the same shape can be loaded
+//! from a file at runtime, see [`super::definitions`].
+
+use std::{borrow::Cow, str::FromStr};
+
+use serde::Deserialize;
+
+use super::State;
+use crate::{capture::Direction, packets::universal::login::s2c::LoginSuccess};
+
+/// Everything version-specific the proxy needs to know.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PacketTable {
+    /// Release name shown to the user, e.g. `"1.21.4"`.
+    pub version: Cow<'static, str>,
+    /// Protocol number sent in the Handshake.
+    pub protocol: i32,
+    /// Other releases with an identical table, e.g. 1.21.6 and 1.21.7 next to
+    /// 1.21.8. Several releases may share one protocol number.
+    #[serde(default)]
+    pub aliases: Cow<'static, [Release]>,
+    pub c2s: ServerboundIds,
+    pub s2c: ClientboundIds,
+    pub layout: Layout,
+    /// Without it none of the inactive client's packets can be routed upstream.
+    #[serde(default)]
+    pub categories: Categories,
+}
+
+/// One more release a table covers.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Release {
+    pub version: Cow<'static, str>,
+    pub protocol: i32,
+}
+
+/// Serverbound packet ids. `None` means the packet doesn't exist in this version.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerboundIds {
+    /// Login: `ServerboundLoginAcknowledgedPacket` (1.20.2+).
+    pub login_acknowledged: Option<i32>,
+    /// Configuration: `ServerboundFinishConfigurationPacket` (1.20.2+).
+    pub finish_configuration: Option<i32>,
+    /// Configuration: `ServerboundCookieResponsePacket` (1.20.5+).
+    pub cookie_response: Option<i32>,
+    /// Configuration: `ServerboundSelectKnownPacks` (1.20.5+).
+    pub select_known_packs: Option<i32>,
+    /// Game: `ServerboundConfigurationAcknowledgedPacket` (1.20.2+).
+    pub configuration_acknowledged: Option<i32>,
+    /// Game: `ServerboundAcceptTeleportationPacket` (1.9+).
+    pub accept_teleportation: Option<i32>,
+    /// Game: `ServerboundMovePlayerPacket.Pos`.
+    pub move_pos: i32,
+    /// Game: `ServerboundMovePlayerPacket.PosRot`.
+    pub move_pos_rot: i32,
+    /// Game: `ServerboundMovePlayerPacket.Rot`.
+    pub move_rot: i32,
+    /// Game: answer to [`ClientboundIds::ping`] (`Pong`, or `ContainerAck` /
+    /// `ConfirmTransaction` before 1.17).
+    pub pong: i32,
+    /// Game: `ServerboundKeepAlivePacket`, timed to measure the client's
+    /// latency.
+    pub keep_alive: Option<i32>,
+    /// Game: `ServerboundContainerClosePacket`.
+    pub container_close: i32,
+    /// Game: `ServerboundContainerClickPacket` (`ClickWindow` before 1.17).
+    /// Used to have the server resend a container in full after a hand-over,
+    /// when the session asks for resync clicks.
+    pub container_click: Option<i32>,
+    /// Game: `ServerboundSetCarriedItemPacket`, the selected hotbar slot.
+    pub set_carried_item: Option<i32>,
+    /// Game: `ServerboundChatCommandPacket` (1.19+).
+    pub chat_command: Option<i32>,
+    /// Game: `ServerboundChatAckPacket` carrying a bare offset (1.19.3+).
+    pub chat_ack: Option<i32>,
+    /// Game: `ServerboundChatPacket`.
+    pub chat: i32,
+}
+
+/// Clientbound packet ids. `None` means the packet doesn't exist in this version.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientboundIds {
+    /// Configuration: `ClientboundFinishConfigurationPacket` (1.20.2+).
+    pub finish_configuration: Option<i32>,
+    /// Configuration: `ClientboundCookieRequestPacket` (1.20.5+).
+    pub cookie_request: Option<i32>,
+    /// Configuration: `ClientboundSelectKnownPacks` (1.20.5+).
+    pub select_known_packs: Option<i32>,
+    /// Game: `ClientboundStartConfigurationPacket` (1.20.2+).
+    pub start_configuration: Option<i32>,
+    /// Game: `ClientboundPlayerPositionPacket`.
+    pub position: i32,
+    /// Game: `ClientboundPingPacket`, or `ContainerAck` before 1.17.
+    pub ping: i32,
+    /// Game: `ClientboundKeepAlivePacket`.
+    pub keep_alive: Option<i32>,
+    /// Game: `ClientboundContainerClosePacket`.
+    pub container_close: i32,
+    /// Game: `ClientboundOpenScreenPacket` (`OpenWindow` before 1.14).
+    pub open_screen: Option<i32>,
+    /// Game: `ClientboundContainerSetContentPacket` (`WindowItems` before 1.17).
+    pub container_set_content: Option<i32>,
+    /// Game: `ClientboundContainerSetSlotPacket` (`SetSlot` before 1.17), which
+    /// also sets the cursor item before 1.21.2.
+    pub container_set_slot: Option<i32>,
+    /// Game: `ClientboundSetCursorItemPacket` (1.21.2+). Container ids are
+    /// VarInts from the same release on.
+    pub set_cursor_item: Option<i32>,
+    /// Game: `ClientboundSetCarriedItemPacket` (`SetHeldSlot` since 1.21.2).
+    pub set_carried_item: Option<i32>,
+    /// Game: `ClientboundSystemChatPacket`, or `ClientboundChatPacket` before
+    /// 1.19. Without it `#vp` commands still run, just without an answer.
+    pub system_chat: Option<i32>,
+    /// Game: `ClientboundLevelChunkWithLightPacket` (`ChunkData` before
+    /// 1.18). Lets the world cache drop a chunk once it is sent again.
+    pub level_chunk: Option<i32>,
+    /// Game: `ClientboundForgetLevelChunkPacket` (`UnloadChunk`, 1.9+).
+    pub forget_level_chunk: Option<i32>,
+    /// Game: `ClientboundLightUpdatePacket` (1.14+).
+    pub light_update: Option<i32>,
+    /// Game: packets that only matter the moment they arrive, which the world
+    /// cache leaves out: entity movement, `Animate`, sounds, particles and
+    /// `SetTime`.
+    #[serde(default)]
+    pub transient: Cow<'static, [i32]>,
+}
+
+/// Serverbound Game packet ids grouped by what the player does with them, so a
+/// session can relay some of the inactive client's packets too. A packet in no
+/// category only ever reaches the server from the active client.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Categories {
+    /// `ServerboundChatPacket`, which also carries commands before 1.19.
+    #[serde(default)]
+    pub chat: Cow<'static, [i32]>,
+    /// `ServerboundChatCommandPacket` (and its signed twin) and
+    /// `ServerboundCommandSuggestionPacket`.
+    #[serde(default)]
+    pub commands: Cow<'static, [i32]>,
+    /// The four `ServerboundMovePlayerPacket`s.
+    #[serde(default)]
+    pub movement: Cow<'static, [i32]>,
+    /// `ServerboundInteractPacket` and `ServerboundSwingPacket`.
+    #[serde(default)]
+    pub combat: Cow<'static, [i32]>,
+    /// `ServerboundPlayerActionPacket`, `ServerboundUseItemOnPacket` and
+    /// `ServerboundUseItemPacket`: digging, placing and using items.
+    #[serde(default)]
+    pub interaction: Cow<'static, [i32]>,
+    /// `ServerboundContainerClickPacket`, `ServerboundContainerClosePacket`
+    /// and `ServerboundSetCarriedItemPacket`.
+    #[serde(default)]
+    pub inventory: Cow<'static, [i32]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PacketCategory {
+    Chat,
+    Commands,
+    Movement,
+    Combat,
+    Interaction,
+    Inventory,
+}
+
+/// Field layouts of the packets the proxy builds or reads beyond their id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layout {
+    pub position: PositionLayout,
+    pub ping: PingLayout,
+    /// Only read when [`ClientboundIds::system_chat`] is set.
+    #[serde(default)]
+    pub chat: ChatLayout,
+    /// Only read when [`ServerboundIds::container_click`] is set, and for
+    /// whether the clientbound container packets carry a state id.
+    #[serde(default)]
+    pub click: ClickLayout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionLayout {
+    /// `x, y, z, yaw, pitch, flags: u8` with no teleport id; the client never
+    /// confirms it (1.8).
+    NoConfirm,
+    /// `x, y, z, yaw, pitch, flags: u8, teleport_id, dismount_vehicle: bool`
+    /// (1.17–1.19.3).
+    LegacyDismount,
+    /// `x, y, z, yaw, pitch, flags: u8, teleport_id` (1.9–1.16.5, 1.19.4–1.21.1).
+    Legacy,
+    /// `teleport_id, x, y, z, dx, dy, dz, yaw, pitch, flags: i32` (1.21.2+).
+    Delta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PingLayout {
+    /// `Ping { id: i32 }` answered by `Pong { id: i32 }` (1.17+).
+    Ping,
+    /// `ContainerAck { container_id: i8, uid: i16, accepted }` answered in kind
+    /// (`ConfirmTransaction` before 1.13).
+    ContainerAck,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatLayout {
+    /// `json, position: i8` (1.8–1.15).
+    Legacy,
+    /// `json, position: i8, sender: uuid` (1.16–1.18).
+    LegacySender,
+    /// `json, overlay: bool` (1.19.1–1.20.2).
+    Json,
+    /// `nbt, overlay: bool`: the component is network NBT (1.20.3+).
+    #[default]
+    Nbt,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickLayout {
+    /// `container_id: u8, slot: i16, button: i8, action: i16, mode, item` with
+    /// a short item id, `-1` for none (1.8–1.12).
+    Legacy,
+    /// The same with a `present: bool` flag and a VarInt item id (1.13–1.16).
+    LegacyFlagged,
+    /// `container_id, state_id, slot: i16, button: i8, mode, changed_slots,
+    /// carried` (1.17.1+).
+    #[default]
+    StateId,
+}
+
+impl PacketCategory {
+    pub const ALL: [PacketCategory; 6] = [
+        PacketCategory::Chat,
+        PacketCategory::Commands,
+        PacketCategory::Movement,
+        PacketCategory::Combat,
+        PacketCategory::Interaction,
+        PacketCategory::Inventory,
+    ];
+
+    /// Name used in config files, as in [`Categories`].
+    pub fn name(self) -> &'static str {
+        match self {
+            PacketCategory::Chat => "chat",
+            PacketCategory::Commands => "commands",
+            PacketCategory::Movement => "movement",
+            PacketCategory::Combat => "combat",
+            PacketCategory::Interaction => "interaction",
+            PacketCategory::Inventory => "inventory",
+        }
+    }
+}
+
+impl FromStr for PacketCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|c| c.name()).collect();
+                format!(
+                    "неизвестная категория \"{}\", доступны: {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl Categories {
+    pub fn ids(&self, category: PacketCategory) -> &[i32] {
+        match category {
+            PacketCategory::Chat => &self.chat,
+            PacketCategory::Commands => &self.commands,
+            PacketCategory::Movement => &self.movement,
+            PacketCategory::Combat => &self.combat,
+            PacketCategory::Interaction => &self.interaction,
+            PacketCategory::Inventory => &self.inventory,
+        }
+    }
+
+    /// The category a serverbound Game packet belongs to.
+    pub fn of(&self, packet_id: i32) -> Option<PacketCategory> {
+        PacketCategory::ALL
+            .into_iter()
+            .find(|&category| self.ids(category).contains(&packet_id))
+    }
+}
+
+impl PacketTable {
+    /// Every release using this table as `(version, protocol)`, aliases first.
+    pub fn releases(&self) -> impl Iterator<Item = (&str, i32)> {
+        self.aliases
+            .iter()
+            .map(|r| (r.version.as_ref(), r.protocol))
+            .chain([(self.version.as_ref(), self.protocol)])
+    }
+
+    pub fn covers(&self, protocol: i32) -> bool {
+        self.releases().any(|(_, p)| p == protocol)
+    }
+
+    /// Whether the version has the Configuration phase between Login and Game.
+    pub fn has_configuration(&self) -> bool {
+        self.c2s.login_acknowledged.is_some()
+    }
+
+    /// Name of a packet the table knows, as its key in a definition file
+    /// (`"position"`, `"move_pos"`…). Packets the proxy never looks into have
+    /// none.
+    pub fn name(&self, state: State, direction: Direction, packet_id: i32) -> Option<&'static str> {
+        let (c2s, s2c) = (&self.c2s, &self.s2c);
+        let named: &[(&'static str, Option<i32>)] = match (state, direction) {
+            (State::Login, Direction::Serverbound) => {
+                &[("login_acknowledged", c2s.login_acknowledged)]
+            }
+            (State::Login, Direction::Clientbound) => {
+                &[("login_success", Some(LoginSuccess::PACKET_ID))]
+            }
+            (State::Configuration, Direction::Serverbound) => &[
+                ("finish_configuration", c2s.finish_configuration),
+                ("cookie_response", c2s.cookie_response),
+                ("select_known_packs", c2s.select_known_packs),
+            ],
+            (State::Configuration, Direction::Clientbound) => &[
+                ("finish_configuration", s2c.finish_configuration),
+                ("cookie_request", s2c.cookie_request),
+                ("select_known_packs", s2c.select_known_packs),
+            ],
+            (State::Game, Direction::Serverbound) => &[
+                ("configuration_acknowledged", c2s.configuration_acknowledged),
+                ("accept_teleportation", c2s.accept_teleportation),
+                ("move_pos", Some(c2s.move_pos)),
+                ("move_pos_rot", Some(c2s.move_pos_rot)),
+                ("move_rot", Some(c2s.move_rot)),
+                ("pong", Some(c2s.pong)),
+                ("keep_alive", c2s.keep_alive),
+                ("container_close", Some(c2s.container_close)),
+                ("container_click", c2s.container_click),
+                ("set_carried_item", c2s.set_carried_item),
+                ("chat_command", c2s.chat_command),
+                ("chat_ack", c2s.chat_ack),
+                ("chat", Some(c2s.chat)),
+            ],
+            (State::Game, Direction::Clientbound) => &[
+                ("start_configuration", s2c.start_configuration),
+                ("position", Some(s2c.position)),
+                ("ping", Some(s2c.ping)),
+                ("keep_alive", s2c.keep_alive),
+                ("container_close", Some(s2c.container_close)),
+                ("open_screen", s2c.open_screen),
+                ("container_set_content", s2c.container_set_content),
+                ("container_set_slot", s2c.container_set_slot),
+                ("set_cursor_item", s2c.set_cursor_item),
+                ("set_carried_item", s2c.set_carried_item),
+                ("system_chat", s2c.system_chat),
+                ("level_chunk", s2c.level_chunk),
+                ("forget_level_chunk", s2c.forget_level_chunk),
+                ("light_update", s2c.light_update),
+            ],
+        };
+        named
+            .iter()
+            .find(|&&(_, id)| id == Some(packet_id))
+            .map(|&(name, _)| name)
+    }
+}
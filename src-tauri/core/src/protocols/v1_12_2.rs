@@ -0,0 +1,65 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.12.2"),
+    protocol: 340,
+    aliases: Cow::Borrowed(&[]),
+    c2s: ServerboundIds {
+        login_acknowledged: None,
+        finish_configuration: None,
+        cookie_response: None,
+        select_known_packs: None,
+        configuration_acknowledged: None,
+        accept_teleportation: Some(0),
+        move_pos: 13,
+        move_pos_rot: 14,
+        move_rot: 15,
+        pong: 5, // ConfirmTransaction
+        keep_alive: Some(11),
+        container_close: 8,
+        container_click: Some(7),
+        set_carried_item: Some(26),
+        chat_command: None,
+        chat_ack: None,
+        chat: 2,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: None,
+        cookie_request: None,
+        select_known_packs: None,
+        start_configuration: None,
+        position: 47,
+        ping: 17, // ConfirmTransaction
+        keep_alive: Some(31),
+        container_close: 18,
+        open_screen: Some(19),
+        container_set_content: Some(20),
+        container_set_slot: Some(22),
+        set_cursor_item: None,
+        set_carried_item: Some(58),
+        system_chat: Some(15), // ClientboundChatPacket
+        level_chunk: Some(32), // ChunkData
+        forget_level_chunk: Some(29),
+        light_update: None,
+        transient: Cow::Borrowed(&[6, 25, 34, 38, 39, 40, 54, 62, 71, 73, 76]),
+    },
+    layout: Layout {
+        position: PositionLayout::Legacy,
+        ping: PingLayout::ContainerAck,
+        chat: ChatLayout::Legacy,
+        click: ClickLayout::Legacy,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[2]),
+        commands: Cow::Borrowed(&[1]),
+        movement: Cow::Borrowed(&[12, 13, 14, 15]),
+        combat: Cow::Borrowed(&[10, 29]),
+        interaction: Cow::Borrowed(&[20, 31, 32]),
+        inventory: Cow::Borrowed(&[7, 8, 26]),
+    },
+};
@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, Release, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.16.5"),
+    protocol: 754,
+    aliases: Cow::Borrowed(&[Release {
+        version: Cow::Borrowed("1.16.4"),
+        protocol: 754,
+    }]),
+    c2s: ServerboundIds {
+        login_acknowledged: None,
+        finish_configuration: None,
+        cookie_response: None,
+        select_known_packs: None,
+        configuration_acknowledged: None,
+        accept_teleportation: Some(0),
+        move_pos: 18,
+        move_pos_rot: 19,
+        move_rot: 20,
+        pong: 7, // ServerboundContainerAckPacket
+        keep_alive: Some(16),
+        container_close: 10,
+        container_click: Some(9),
+        set_carried_item: Some(37),
+        chat_command: None,
+        chat_ack: None,
+        chat: 3,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: None,
+        cookie_request: None,
+        select_known_packs: None,
+        start_configuration: None,
+        position: 52,
+        ping: 17, // ClientboundContainerAckPacket
+        keep_alive: Some(31),
+        container_close: 18,
+        open_screen: Some(45),
+        container_set_content: Some(19),
+        container_set_slot: Some(21),
+        set_cursor_item: None,
+        set_carried_item: Some(63),
+        system_chat: Some(14), // ClientboundChatPacket
+        level_chunk: Some(32), // ChunkData
+        forget_level_chunk: Some(28),
+        light_update: Some(35),
+        transient: Cow::Borrowed(&[5, 24, 34, 39, 40, 41, 58, 70, 78, 80, 81, 86]),
+    },
+    layout: Layout {
+        position: PositionLayout::Legacy,
+        ping: PingLayout::ContainerAck,
+        chat: ChatLayout::LegacySender,
+        click: ClickLayout::LegacyFlagged,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[3]),
+        commands: Cow::Borrowed(&[6]),
+        movement: Cow::Borrowed(&[18, 19, 20, 21]),
+        combat: Cow::Borrowed(&[14, 44]),
+        interaction: Cow::Borrowed(&[27, 46, 47]),
+        inventory: Cow::Borrowed(&[9, 10, 37]),
+    },
+};
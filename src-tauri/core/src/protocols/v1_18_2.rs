@@ -0,0 +1,65 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.18.2"),
+    protocol: 758,
+    aliases: Cow::Borrowed(&[]),
+    c2s: ServerboundIds {
+        login_acknowledged: None,
+        finish_configuration: None,
+        cookie_response: None,
+        select_known_packs: None,
+        configuration_acknowledged: None,
+        accept_teleportation: Some(0),
+        move_pos: 17,
+        move_pos_rot: 18,
+        move_rot: 19,
+        pong: 29, // ServerboundPongPacket
+        keep_alive: Some(15),
+        container_close: 9,
+        container_click: Some(8),
+        set_carried_item: Some(37),
+        chat_command: None,
+        chat_ack: None,
+        chat: 3,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: None,
+        cookie_request: None,
+        select_known_packs: None,
+        start_configuration: None,
+        position: 56,
+        ping: 48, // ClientboundPingPacket
+        keep_alive: Some(33),
+        container_close: 19,
+        open_screen: Some(46),
+        container_set_content: Some(20),
+        container_set_slot: Some(22),
+        set_cursor_item: None,
+        set_carried_item: Some(72),
+        system_chat: Some(15), // ClientboundChatPacket
+        level_chunk: Some(34),
+        forget_level_chunk: Some(29),
+        light_update: Some(37),
+        transient: Cow::Borrowed(&[6, 25, 36, 41, 42, 43, 62, 79, 89, 92, 93, 98]),
+    },
+    layout: Layout {
+        position: PositionLayout::LegacyDismount,
+        ping: PingLayout::Ping,
+        chat: ChatLayout::LegacySender,
+        click: ClickLayout::StateId,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[3]),
+        commands: Cow::Borrowed(&[6]),
+        movement: Cow::Borrowed(&[17, 18, 19, 20]),
+        combat: Cow::Borrowed(&[13, 44]),
+        interaction: Cow::Borrowed(&[26, 46, 47]),
+        inventory: Cow::Borrowed(&[8, 9, 37]),
+    },
+};
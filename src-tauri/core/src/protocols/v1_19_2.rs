@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, Release, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.19.2"),
+    protocol: 760,
+    aliases: Cow::Borrowed(&[Release {
+        version: Cow::Borrowed("1.19.1"),
+        protocol: 760,
+    }]),
+    c2s: ServerboundIds {
+        login_acknowledged: None,
+        finish_configuration: None,
+        cookie_response: None,
+        select_known_packs: None,
+        configuration_acknowledged: None,
+        accept_teleportation: Some(0),
+        move_pos: 20,
+        move_pos_rot: 21,
+        move_rot: 22,
+        pong: 32, // ServerboundPongPacket
+        keep_alive: Some(18),
+        container_close: 12,
+        container_click: Some(11),
+        set_carried_item: Some(40),
+        chat_command: Some(4),
+        chat_ack: None,
+        chat: 5,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: None,
+        cookie_request: None,
+        select_known_packs: None,
+        start_configuration: None,
+        position: 57,
+        ping: 47, // ClientboundPingPacket
+        keep_alive: Some(32),
+        container_close: 16,
+        open_screen: Some(45),
+        container_set_content: Some(17),
+        container_set_slot: Some(19),
+        set_cursor_item: None,
+        set_carried_item: Some(74),
+        system_chat: Some(98),
+        level_chunk: Some(33),
+        forget_level_chunk: Some(28),
+        light_update: Some(36),
+        transient: Cow::Borrowed(&[3, 23, 35, 40, 41, 42, 63, 82, 92, 95, 96, 102]),
+    },
+    layout: Layout {
+        position: PositionLayout::LegacyDismount,
+        ping: PingLayout::Ping,
+        chat: ChatLayout::Json,
+        click: ClickLayout::StateId,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[5]),
+        commands: Cow::Borrowed(&[4, 9]),
+        movement: Cow::Borrowed(&[20, 21, 22, 23]),
+        combat: Cow::Borrowed(&[16, 47]),
+        interaction: Cow::Borrowed(&[29, 49, 50]),
+        inventory: Cow::Borrowed(&[11, 12, 40]),
+    },
+};
@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, Release, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.20.1"),
+    protocol: 763,
+    aliases: Cow::Borrowed(&[Release {
+        version: Cow::Borrowed("1.20"),
+        protocol: 763,
+    }]),
+    c2s: ServerboundIds {
+        login_acknowledged: None,
+        finish_configuration: None,
+        cookie_response: None,
+        select_known_packs: None,
+        configuration_acknowledged: None,
+        accept_teleportation: Some(0),
+        move_pos: 20,
+        move_pos_rot: 21,
+        move_rot: 22,
+        pong: 32, // ServerboundPongPacket
+        keep_alive: Some(18),
+        container_close: 12,
+        container_click: Some(11),
+        set_carried_item: Some(40),
+        chat_command: Some(4),
+        chat_ack: Some(3),
+        chat: 5,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: None,
+        cookie_request: None,
+        select_known_packs: None,
+        start_configuration: None,
+        position: 60,
+        ping: 50, // ClientboundPingPacket
+        keep_alive: Some(35),
+        container_close: 17,
+        open_screen: Some(48),
+        container_set_content: Some(18),
+        container_set_slot: Some(20),
+        set_cursor_item: None,
+        set_carried_item: Some(77),
+        system_chat: Some(100),
+        level_chunk: Some(36),
+        forget_level_chunk: Some(30),
+        light_update: Some(39),
+        transient: Cow::Borrowed(&[4, 38, 43, 44, 45, 66, 84, 94, 97, 98, 104]),
+    },
+    layout: Layout {
+        position: PositionLayout::Legacy,
+        ping: PingLayout::Ping,
+        chat: ChatLayout::Json,
+        click: ClickLayout::StateId,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[5]),
+        commands: Cow::Borrowed(&[4, 9]),
+        movement: Cow::Borrowed(&[20, 21, 22, 23]),
+        combat: Cow::Borrowed(&[16, 47]),
+        interaction: Cow::Borrowed(&[29, 49, 50]),
+        inventory: Cow::Borrowed(&[11, 12, 40]),
+    },
+};
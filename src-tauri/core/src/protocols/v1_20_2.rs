@@ -0,0 +1,65 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.20.2"),
+    protocol: 764,
+    aliases: Cow::Borrowed(&[]),
+    c2s: ServerboundIds {
+        login_acknowledged: Some(3),
+        finish_configuration: Some(2),
+        cookie_response: None,
+        select_known_packs: None,
+        configuration_acknowledged: Some(11),
+        accept_teleportation: Some(0),
+        move_pos: 22,
+        move_pos_rot: 23,
+        move_rot: 24,
+        pong: 35, // ServerboundPongPacket
+        keep_alive: Some(20),
+        container_close: 14,
+        container_click: Some(13),
+        set_carried_item: Some(43),
+        chat_command: Some(4),
+        chat_ack: Some(3),
+        chat: 5,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: Some(2),
+        cookie_request: None,
+        select_known_packs: None,
+        start_configuration: Some(101),
+        position: 62,
+        ping: 51, // ClientboundPingPacket
+        keep_alive: Some(36),
+        container_close: 18,
+        open_screen: Some(49),
+        container_set_content: Some(19),
+        container_set_slot: Some(21),
+        set_cursor_item: None,
+        set_carried_item: Some(79),
+        system_chat: Some(103),
+        level_chunk: Some(37),
+        forget_level_chunk: Some(31),
+        light_update: Some(40),
+        transient: Cow::Borrowed(&[3, 39, 44, 45, 46, 68, 86, 96, 99, 100, 107]),
+    },
+    layout: Layout {
+        position: PositionLayout::Legacy,
+        ping: PingLayout::Ping,
+        chat: ChatLayout::Json,
+        click: ClickLayout::StateId,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[5]),
+        commands: Cow::Borrowed(&[4, 10]),
+        movement: Cow::Borrowed(&[22, 23, 24, 25]),
+        combat: Cow::Borrowed(&[18, 50]),
+        interaction: Cow::Borrowed(&[32, 52, 53]),
+        inventory: Cow::Borrowed(&[13, 14, 43]),
+    },
+};
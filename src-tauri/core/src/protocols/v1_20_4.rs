@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, Release, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.20.4"),
+    protocol: 765,
+    aliases: Cow::Borrowed(&[Release {
+        version: Cow::Borrowed("1.20.3"),
+        protocol: 765,
+    }]),
+    c2s: ServerboundIds {
+        login_acknowledged: Some(3),
+        finish_configuration: Some(2),
+        cookie_response: None,
+        select_known_packs: None,
+        configuration_acknowledged: Some(11),
+        accept_teleportation: Some(0),
+        move_pos: 23,
+        move_pos_rot: 24,
+        move_rot: 25,
+        pong: 36, // ServerboundPongPacket
+        keep_alive: Some(21),
+        container_close: 14,
+        container_click: Some(13),
+        set_carried_item: Some(44),
+        chat_command: Some(4),
+        chat_ack: Some(3),
+        chat: 5,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: Some(2),
+        cookie_request: None,
+        select_known_packs: None,
+        start_configuration: Some(103),
+        position: 62,
+        ping: 51, // ClientboundPingPacket
+        keep_alive: Some(36),
+        container_close: 18,
+        open_screen: Some(49),
+        container_set_content: Some(19),
+        container_set_slot: Some(21),
+        set_cursor_item: None,
+        set_carried_item: Some(81),
+        system_chat: Some(105),
+        level_chunk: Some(37),
+        forget_level_chunk: Some(31),
+        light_update: Some(40),
+        transient: Cow::Borrowed(&[3, 39, 44, 45, 46, 70, 88, 98, 101, 102, 109]),
+    },
+    layout: Layout {
+        position: PositionLayout::Legacy,
+        ping: PingLayout::Ping,
+        chat: ChatLayout::Nbt,
+        click: ClickLayout::StateId,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[5]),
+        commands: Cow::Borrowed(&[4, 10]),
+        movement: Cow::Borrowed(&[23, 24, 25, 26]),
+        combat: Cow::Borrowed(&[19, 51]),
+        interaction: Cow::Borrowed(&[33, 53, 54]),
+        inventory: Cow::Borrowed(&[13, 14, 44]),
+    },
+};
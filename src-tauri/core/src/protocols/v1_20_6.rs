@@ -0,0 +1,78 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, Release, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.20.6"),
+    protocol: 766,
+    aliases: Cow::Borrowed(&[
+        Release {
+            version: Cow::Borrowed("1.20.5"),
+            protocol: 766,
+        },
+        Release {
+            version: Cow::Borrowed("1.21"),
+            protocol: 767,
+        },
+        Release {
+            version: Cow::Borrowed("1.21.1"),
+            protocol: 767,
+        },
+    ]),
+    c2s: ServerboundIds {
+        login_acknowledged: Some(3),
+        finish_configuration: Some(3),
+        cookie_response: Some(1),
+        select_known_packs: Some(7),
+        configuration_acknowledged: Some(12),
+        accept_teleportation: Some(0),
+        move_pos: 26,
+        move_pos_rot: 27,
+        move_rot: 28,
+        pong: 39, // ServerboundPongPacket
+        keep_alive: Some(24),
+        container_close: 15,
+        container_click: Some(14),
+        set_carried_item: Some(47),
+        chat_command: Some(4),
+        chat_ack: Some(3),
+        chat: 6,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: Some(3),
+        cookie_request: Some(0),
+        select_known_packs: Some(14),
+        start_configuration: Some(105),
+        position: 64,
+        ping: 53, // ClientboundPingPacket
+        keep_alive: Some(38),
+        container_close: 18,
+        open_screen: Some(51),
+        container_set_content: Some(19),
+        container_set_slot: Some(21),
+        set_cursor_item: None,
+        set_carried_item: Some(83),
+        system_chat: Some(108),
+        level_chunk: Some(39),
+        forget_level_chunk: Some(33),
+        light_update: Some(42),
+        transient: Cow::Borrowed(&[3, 41, 46, 47, 48, 72, 90, 100, 103, 104, 112]),
+    },
+    layout: Layout {
+        position: PositionLayout::Legacy,
+        ping: PingLayout::Ping,
+        chat: ChatLayout::Nbt,
+        click: ClickLayout::StateId,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[6]),
+        commands: Cow::Borrowed(&[4, 5, 11]),
+        movement: Cow::Borrowed(&[26, 27, 28, 29]),
+        combat: Cow::Borrowed(&[22, 54]),
+        interaction: Cow::Borrowed(&[36, 56, 57]),
+        inventory: Cow::Borrowed(&[14, 15, 47]),
+    },
+};
@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, Release, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.21.11"),
+    protocol: 774,
+    aliases: Cow::Borrowed(&[
+        Release {
+            version: Cow::Borrowed("1.21.9"),
+            protocol: 773,
+        },
+        Release {
+            version: Cow::Borrowed("1.21.10"),
+            protocol: 773,
+        },
+    ]),
+    c2s: ServerboundIds {
+        login_acknowledged: Some(3),
+        finish_configuration: Some(3),
+        cookie_response: Some(1),
+        select_known_packs: Some(7),
+        configuration_acknowledged: Some(15),
+        accept_teleportation: Some(0),
+        move_pos: 29,
+        move_pos_rot: 30,
+        move_rot: 31,
+        pong: 44, // ServerboundPongPacket
+        keep_alive: Some(27),
+        container_close: 18,
+        container_click: Some(17),
+        set_carried_item: Some(52),
+        chat_command: Some(6),
+        chat_ack: Some(5),
+        chat: 8,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: Some(3),
+        cookie_request: Some(0),
+        select_known_packs: Some(14),
+        start_configuration: Some(116),
+        position: 70,
+        ping: 59, // ClientboundPingPacket
+        keep_alive: Some(43),
+        container_close: 17,
+        open_screen: Some(57),
+        container_set_content: Some(18),
+        container_set_slot: Some(20),
+        set_cursor_item: Some(94),
+        set_carried_item: Some(103),
+        system_chat: Some(119),
+        level_chunk: Some(44),
+        forget_level_chunk: Some(37),
+        light_update: Some(47),
+        transient: Cow::Borrowed(&[2, 35, 46, 51, 52, 54, 81, 99, 111, 114, 115, 123]),
+    },
+    layout: Layout {
+        position: PositionLayout::Delta,
+        ping: PingLayout::Ping,
+        chat: ChatLayout::Nbt,
+        click: ClickLayout::StateId,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[8]),
+        commands: Cow::Borrowed(&[6, 7, 14]),
+        movement: Cow::Borrowed(&[29, 30, 31, 32]),
+        combat: Cow::Borrowed(&[25, 60]),
+        interaction: Cow::Borrowed(&[40, 63, 64]),
+        inventory: Cow::Borrowed(&[17, 18, 52]),
+    },
+};
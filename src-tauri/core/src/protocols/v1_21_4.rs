@@ -0,0 +1,65 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.21.4"),
+    protocol: 769,
+    aliases: Cow::Borrowed(&[]),
+    c2s: ServerboundIds {
+        login_acknowledged: Some(3),
+        finish_configuration: Some(3),
+        cookie_response: Some(1),
+        select_known_packs: Some(7),
+        configuration_acknowledged: Some(14),
+        accept_teleportation: Some(0),
+        move_pos: 28,
+        move_pos_rot: 29,
+        move_rot: 30,
+        pong: 43, // ServerboundPongPacket
+        keep_alive: Some(26),
+        container_close: 17,
+        container_click: Some(16),
+        set_carried_item: Some(51),
+        chat_command: Some(5),
+        chat_ack: Some(4),
+        chat: 7,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: Some(3),
+        cookie_request: Some(0),
+        select_known_packs: Some(14),
+        start_configuration: Some(112),
+        position: 66,
+        ping: 55, // ClientboundPingPacket
+        keep_alive: Some(39),
+        container_close: 18,
+        open_screen: Some(53),
+        container_set_content: Some(19),
+        container_set_slot: Some(21),
+        set_cursor_item: Some(90),
+        set_carried_item: Some(99),
+        system_chat: Some(115),
+        level_chunk: Some(40),
+        forget_level_chunk: Some(34),
+        light_update: Some(43),
+        transient: Cow::Borrowed(&[3, 32, 42, 47, 48, 50, 77, 95, 107, 110, 111, 119]),
+    },
+    layout: Layout {
+        position: PositionLayout::Delta,
+        ping: PingLayout::Ping,
+        chat: ChatLayout::Nbt,
+        click: ClickLayout::StateId,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[7]),
+        commands: Cow::Borrowed(&[5, 6, 13]),
+        movement: Cow::Borrowed(&[28, 29, 30, 31]),
+        combat: Cow::Borrowed(&[24, 58]),
+        interaction: Cow::Borrowed(&[39, 60, 61]),
+        inventory: Cow::Borrowed(&[16, 17, 51]),
+    },
+};
@@ -0,0 +1,65 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.21.5"),
+    protocol: 770,
+    aliases: Cow::Borrowed(&[]),
+    c2s: ServerboundIds {
+        login_acknowledged: Some(3),
+        finish_configuration: Some(3),
+        cookie_response: Some(1),
+        select_known_packs: Some(7),
+        configuration_acknowledged: Some(14),
+        accept_teleportation: Some(0),
+        move_pos: 28,
+        move_pos_rot: 29,
+        move_rot: 30,
+        pong: 43, // ServerboundPongPacket
+        keep_alive: Some(26),
+        container_close: 17,
+        container_click: Some(16),
+        set_carried_item: Some(51),
+        chat_command: Some(5),
+        chat_ack: Some(4),
+        chat: 7,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: Some(3),
+        cookie_request: Some(0),
+        select_known_packs: Some(14),
+        start_configuration: Some(111),
+        position: 65,
+        ping: 54, // ClientboundPingPacket
+        keep_alive: Some(38),
+        container_close: 17,
+        open_screen: Some(52),
+        container_set_content: Some(18),
+        container_set_slot: Some(20),
+        set_cursor_item: Some(89),
+        set_carried_item: Some(98),
+        system_chat: Some(114),
+        level_chunk: Some(39),
+        forget_level_chunk: Some(33),
+        light_update: Some(42),
+        transient: Cow::Borrowed(&[2, 31, 41, 46, 47, 49, 76, 94, 106, 109, 110, 118]),
+    },
+    layout: Layout {
+        position: PositionLayout::Delta,
+        ping: PingLayout::Ping,
+        chat: ChatLayout::Nbt,
+        click: ClickLayout::StateId,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[7]),
+        commands: Cow::Borrowed(&[5, 6, 13]),
+        movement: Cow::Borrowed(&[28, 29, 30, 31]),
+        combat: Cow::Borrowed(&[24, 59]),
+        interaction: Cow::Borrowed(&[39, 62, 63]),
+        inventory: Cow::Borrowed(&[16, 17, 51]),
+    },
+};
@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+
+use super::table::{
+    Categories, ChatLayout, ClickLayout, ClientboundIds, Layout, PacketTable, PingLayout,
+    PositionLayout, Release, ServerboundIds,
+};
+
+pub static TABLE: PacketTable = PacketTable {
+    version: Cow::Borrowed("1.8.9"),
+    protocol: 47,
+    aliases: Cow::Borrowed(&[
+        Release {
+            version: Cow::Borrowed("1.8"),
+            protocol: 47,
+        },
+        Release {
+            version: Cow::Borrowed("1.8.1"),
+            protocol: 47,
+        },
+        Release {
+            version: Cow::Borrowed("1.8.2"),
+            protocol: 47,
+        },
+        Release {
+            version: Cow::Borrowed("1.8.3"),
+            protocol: 47,
+        },
+        Release {
+            version: Cow::Borrowed("1.8.4"),
+            protocol: 47,
+        },
+        Release {
+            version: Cow::Borrowed("1.8.5"),
+            protocol: 47,
+        },
+        Release {
+            version: Cow::Borrowed("1.8.6"),
+            protocol: 47,
+        },
+        Release {
+            version: Cow::Borrowed("1.8.7"),
+            protocol: 47,
+        },
+        Release {
+            version: Cow::Borrowed("1.8.8"),
+            protocol: 47,
+        },
+    ]),
+    c2s: ServerboundIds {
+        login_acknowledged: None,
+        finish_configuration: None,
+        cookie_response: None,
+        select_known_packs: None,
+        configuration_acknowledged: None,
+        accept_teleportation: None,
+        move_pos: 4,
+        move_pos_rot: 6,
+        move_rot: 5,
+        pong: 15, // ConfirmTransaction
+        keep_alive: Some(0),
+        container_close: 13,
+        container_click: Some(14),
+        set_carried_item: Some(9),
+        chat_command: None,
+        chat_ack: None,
+        chat: 1,
+    },
+    s2c: ClientboundIds {
+        finish_configuration: None,
+        cookie_request: None,
+        select_known_packs: None,
+        start_configuration: None,
+        position: 8,
+        ping: 50, // ConfirmTransaction
+        keep_alive: Some(0),
+        container_close: 46,
+        open_screen: Some(45),
+        container_set_content: Some(48),
+        container_set_slot: Some(47),
+        set_cursor_item: None,
+        set_carried_item: Some(9),
+        system_chat: Some(2),  // ClientboundChatPacket
+        level_chunk: Some(33), // ChunkData
+        forget_level_chunk: None,
+        light_update: None,
+        transient: Cow::Borrowed(&[3, 11, 18, 21, 22, 23, 24, 25, 41, 42]),
+    },
+    layout: Layout {
+        position: PositionLayout::NoConfirm,
+        ping: PingLayout::ContainerAck,
+        chat: ChatLayout::Legacy,
+        click: ClickLayout::Legacy,
+    },
+    categories: Categories {
+        chat: Cow::Borrowed(&[1]),
+        commands: Cow::Borrowed(&[20]),
+        movement: Cow::Borrowed(&[3, 4, 5, 6]),
+        combat: Cow::Borrowed(&[2, 10]),
+        interaction: Cow::Borrowed(&[7, 8]),
+        inventory: Cow::Borrowed(&[14, 13, 9]),
+    },
+};
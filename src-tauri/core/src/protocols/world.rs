@@ -0,0 +1,294 @@
+//! What a client joining a running session has missed: the server's
+//! `LoginSuccess`, its latest Configuration round and the Game stream since,
+//! replayed in order so the client ends up where the others are.
+//!
+//! The Game stream is kept whole rather than rebuilt from parsed state, so the
+//! proxy doesn't have to understand entities, inventories or scoreboards to
+//! restore them. Only chunks, by far the bulk of it, are tracked by position:
+//! a chunk sent again replaces the old copy, an unloaded one is dropped. When
+//! the cache grows too big, the oldest of the other packets go first.
+
+use std::collections::{HashMap, HashSet};
+
+use mc_protocol::packet::RawPacket;
+
+/// Beyond this the oldest packets other than chunks are dropped. Only if the
+/// chunks alone are more is the cache dropped, and late joins refused until
+/// the server starts a new Configuration round.
+const MAX_BYTES: usize = 64 * 1024 * 1024;
+/// Where dropping old packets stops, so it doesn't start over on the next one.
+const TRIM_TO: usize = MAX_BYTES / 4 * 3;
+
+type ChunkPos = (i32, i32);
+
+#[derive(Debug, Default)]
+pub(super) struct WorldCache {
+    login_success: Option<RawPacket>,
+    configuration: Vec<RawPacket>,
+    /// Game packets in arrival order; `None` marks one superseded since.
+    game: Vec<Option<RawPacket>>,
+    live: usize,
+    chunks: HashMap<ChunkPos, usize>,
+    /// Light updates a chunk received on top of its data.
+    lights: HashMap<ChunkPos, Vec<usize>>,
+    bytes: usize,
+    overflowed: bool,
+}
+
+impl WorldCache {
+    pub fn set_login_success(&mut self, packet: RawPacket) {
+        self.login_success = Some(packet);
+    }
+
+    pub fn login_success(&self) -> Option<&RawPacket> {
+        self.login_success.as_ref()
+    }
+
+    /// The server began a Configuration round, which resends everything.
+    pub fn start_configuration(&mut self) {
+        let login_success = self.login_success.take();
+        *self = Self {
+            login_success,
+            ..Self::default()
+        };
+    }
+
+    pub fn record_configuration(&mut self, packet: RawPacket) {
+        if !self.overflowed {
+            self.bytes += packet.data.len();
+            self.configuration.push(packet);
+            self.trim();
+        }
+    }
+
+    pub fn record(&mut self, packet: RawPacket) {
+        self.push(packet);
+        self.trim();
+    }
+
+    pub fn record_chunk(&mut self, pos: ChunkPos, packet: RawPacket) {
+        if let Some(old) = self.chunks.remove(&pos) {
+            self.remove(old);
+        }
+        if let Some(i) = self.push(packet) {
+            self.chunks.insert(pos, i);
+        }
+        self.trim();
+        self.compact_if_sparse();
+    }
+
+    pub fn record_light(&mut self, pos: ChunkPos, packet: RawPacket) {
+        if let Some(i) = self.push(packet) {
+            self.lights.entry(pos).or_default().push(i);
+        }
+        self.trim();
+    }
+
+    pub fn forget_chunk(&mut self, pos: ChunkPos) {
+        if let Some(i) = self.chunks.remove(&pos) {
+            self.remove(i);
+        }
+        for i in self.lights.remove(&pos).unwrap_or_default() {
+            self.remove(i);
+        }
+        self.compact_if_sparse();
+    }
+
+    /// Everything to send a new client after its `SetCompression`, or why it
+    /// can't join.
+    pub fn replay(&self) -> Result<Vec<RawPacket>, String> {
+        if self.overflowed {
+            return Err("Кэш мира переполнен".to_string());
+        }
+        let Some(login_success) = &self.login_success else {
+            return Err("Сессия ещё не вошла на сервер".to_string());
+        };
+        if self.live == 0 {
+            return Err("Мир ещё не загружен, попробуйте позже".to_string());
+        }
+        Ok(std::iter::once(login_success)
+            .chain(&self.configuration)
+            .chain(self.game.iter().flatten())
+            .cloned()
+            .collect())
+    }
+
+    fn push(&mut self, packet: RawPacket) -> Option<usize> {
+        if self.overflowed {
+            return None;
+        }
+        self.bytes += packet.data.len();
+        self.game.push(Some(packet));
+        self.live += 1;
+        Some(self.game.len() - 1)
+    }
+
+    fn remove(&mut self, i: usize) {
+        if let Some(packet) = self.game[i].take() {
+            self.bytes -= packet.data.len();
+            self.live -= 1;
+        }
+    }
+
+    /// Past [`MAX_BYTES`], drops the oldest packets that aren't chunks or
+    /// their light until the cache is down to [`TRIM_TO`]. If those alone
+    /// don't fit, the whole cache goes.
+    fn trim(&mut self) {
+        if self.overflowed || self.bytes <= MAX_BYTES {
+            return;
+        }
+        let world: HashSet<usize> = self
+            .chunks
+            .values()
+            .chain(self.lights.values().flatten())
+            .copied()
+            .collect();
+        for i in 0..self.game.len() {
+            if self.bytes <= TRIM_TO {
+                break;
+            }
+            if !world.contains(&i) {
+                self.remove(i);
+            }
+        }
+        if self.bytes > MAX_BYTES {
+            let login_success = self.login_success.take();
+            *self = Self {
+                login_success,
+                overflowed: true,
+                ..Self::default()
+            };
+        } else {
+            self.compact_if_sparse();
+        }
+    }
+
+    /// Drops the superseded slots once they outnumber the live ones, and
+    /// renumbers the indices pointing past them.
+    fn compact_if_sparse(&mut self) {
+        if self.live * 2 >= self.game.len() {
+            return;
+        }
+        let mut moved = vec![usize::MAX; self.game.len()];
+        let mut next = 0;
+        for (old, slot) in self.game.iter().enumerate() {
+            if slot.is_some() {
+                moved[old] = next;
+                next += 1;
+            }
+        }
+        self.game.retain(Option::is_some);
+        for i in self.chunks.values_mut() {
+            *i = moved[*i];
+        }
+        for indices in self.lights.values_mut() {
+            indices.retain_mut(|i| {
+                *i = moved[*i];
+                *i != usize::MAX
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(byte: u8) -> RawPacket {
+        RawPacket::new(vec![byte])
+    }
+
+    fn bytes(cache: &WorldCache) -> Vec<u8> {
+        cache
+            .replay()
+            .unwrap()
+            .iter()
+            .map(|packet| packet.data[0])
+            .collect()
+    }
+
+    fn cache() -> WorldCache {
+        let mut cache = WorldCache::default();
+        cache.set_login_success(packet(0));
+        cache
+    }
+
+    #[test]
+    fn refuses_before_the_world_arrives() {
+        let mut cache = WorldCache::default();
+        assert!(cache.replay().is_err());
+        cache.set_login_success(packet(0));
+        assert!(cache.replay().is_err());
+        cache.record(packet(1));
+        assert_eq!(bytes(&cache), [0, 1]);
+    }
+
+    #[test]
+    fn a_resent_chunk_moves_to_the_end() {
+        let mut cache = cache();
+        cache.record_configuration(packet(1));
+        cache.record_chunk((0, 0), packet(2));
+        cache.record(packet(3));
+        cache.record_chunk((0, 0), packet(4));
+        assert_eq!(bytes(&cache), [0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn forgetting_a_chunk_drops_its_light() {
+        let mut cache = cache();
+        cache.record_light((1, 2), packet(1));
+        cache.record_chunk((1, 2), packet(2));
+        cache.record(packet(3));
+        cache.record_light((1, 2), packet(4));
+        cache.forget_chunk((1, 2));
+        assert_eq!(bytes(&cache), [0, 3]);
+
+        // Indices stay right after the slots are compacted away.
+        cache.record_chunk((5, 5), packet(5));
+        cache.record_chunk((6, 6), packet(6));
+        cache.forget_chunk((5, 5));
+        assert_eq!(bytes(&cache), [0, 3, 6]);
+    }
+
+    #[test]
+    fn a_configuration_round_starts_over() {
+        let mut cache = cache();
+        cache.record_configuration(packet(1));
+        cache.record(packet(2));
+        cache.start_configuration();
+        cache.record_configuration(packet(3));
+        cache.record(packet(4));
+        assert_eq!(bytes(&cache), [0, 3, 4]);
+    }
+
+    #[test]
+    fn trimming_drops_the_oldest_packets_but_not_chunks() {
+        let big = |tag: u8| {
+            let mut data = vec![0; MAX_BYTES / 4];
+            data[0] = tag;
+            RawPacket::new(data)
+        };
+        let mut cache = cache();
+        cache.record_chunk((0, 0), big(1));
+        for tag in 2..=4 {
+            cache.record(big(tag));
+        }
+        cache.record(packet(5));
+        assert_eq!(bytes(&cache), [0, 1, 4, 5]);
+
+        // Indices stay right after the slots are compacted away.
+        cache.record_chunk((0, 0), packet(6));
+        assert_eq!(bytes(&cache), [0, 4, 5, 6]);
+    }
+
+    #[test]
+    fn overflow_refuses_until_the_next_round() {
+        let mut cache = cache();
+        cache.record_chunk((0, 0), RawPacket::new(vec![0; MAX_BYTES + 1]));
+        cache.record(packet(1));
+        assert!(cache.replay().is_err());
+        cache.start_configuration();
+        cache.record(packet(2));
+        assert_eq!(bytes(&cache), [0, 2]);
+    }
+}
@@ -1,11 +1,15 @@
-use std::net::SocketAddr;
+use std::{io::Cursor, net::SocketAddr};
 
 use anyhow::anyhow;
-use mc_protocol::packet::{RawPacket, UncompressedPacket};
+use mc_protocol::{
+    encryption::Cfb8Stream,
+    packet::{RawPacket, UncompressedPacket},
+    ser::Deserialize,
+};
+use openssl::rsa::{Padding, Rsa};
 use serde_json::json;
-use tauri::AppHandle;
 use tokio::{
-    io::AsyncWriteExt,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::{TcpListener, TcpStream},
     sync::mpsc::{self, Sender},
 };
@@ -13,19 +17,27 @@ use tokio::{
 use crate::{packets::universal::status::s2c::StatusResponse, resolver::resolve_host_port};
 
 use crate::{
+    auth,
     controller::{ClientId, Controller, run_client, run_server},
+    latency::{Upstream, probe_upstream},
+    logger::Logger,
     packets::universal::{
         Intent,
         handshaking::c2s::Handshake,
-        login::s2c::{EncryptionRequest, LoginDisconnect, LoginSuccess, SetCompression},
+        login::{
+            c2s::{EncryptionResponse, EncryptionResponseFlagged},
+            s2c::{EncryptionRequest, LoginDisconnect, LoginSuccess, SetCompression},
+        },
     },
     protocols::{Version, VersionProtocol},
+    session::SessionOptions,
+    sink::Sink,
 };
 
 pub const DEFAULT_PORT: u16 = 25565;
 pub const BIND_PORT: u16 = 25565;
 pub const HANDSHAKE_CHANNEL_CAPACITY: usize = 32;
-const IO_CHANNEL_CAPACITY: usize = 100;
+pub(crate) const IO_CHANNEL_CAPACITY: usize = 100;
 
 /// Information extracted from a client's Minecraft Handshake packet.
 /// Used in automatic mode to determine the real remote server without manual input.
@@ -36,6 +48,11 @@ pub struct AutoClientInfo {
     pub server_port: u16,
 }
 
+/// Address the proxy listens on unless the caller asks for another one.
+pub fn default_bind_addr() -> SocketAddr {
+    SocketAddr::from(([0, 0, 0, 0], BIND_PORT))
+}
+
 #[inline]
 pub async fn read_uncompressed(stream: &mut TcpStream) -> anyhow::Result<UncompressedPacket> {
     Ok(RawPacket::read_async(stream).await?.as_uncompressed()?)
@@ -208,75 +225,110 @@ async fn process_status(
 ///   `primary` and `secondary` streams.
 /// - The Minecraft `Handshake` + `LoginStart` have been **sent** to `remote`.
 /// - `version` has been constructed from the shared protocol number.
+///
+/// If the server asks for encryption, the proxy answers it with the account
+/// from `options` and encrypts `remote` from then on. The clients' own
+/// connections stay unencrypted.
 pub async fn run_proxy_session(
     mut primary: TcpStream,
     mut secondary: TcpStream,
     mut remote: TcpStream,
     mut version: Version,
-    app: AppHandle,
+    upstream: Option<Upstream>,
+    sink: Sink,
+    options: SessionOptions,
 ) -> anyhow::Result<()> {
-    let mut threshold = None;
-
-    loop {
-        let packet = RawPacket::read_async(&mut remote)
-            .await?
-            .uncompress(threshold)?;
-
-        match packet.packet_id {
-            LoginDisconnect::PACKET_ID => {
-                send_login_error(
-                    &mut primary,
-                    &mut secondary,
-                    packet.deserialize_payload::<LoginDisconnect>()?.reason,
-                )
-                .await;
-                return Err(anyhow!("Disconnected by server"));
-            }
-            EncryptionRequest::PACKET_ID => {
-                send_login_error(
-                    &mut primary,
-                    &mut secondary,
-                    "Лицензионный сервер не поддерживается\nИспользуйте ViaProxy".to_string(),
+    let (request, threshold) =
+        match relay_login(&mut remote, &mut primary, &mut secondary, None).await? {
+            LoginStep::Success(threshold, login_success) => {
+                version.update_threshold(threshold);
+                version.handle_login_success(login_success);
+                return run_controller(
+                    primary,
+                    secondary,
+                    remote.into_split(),
+                    version,
+                    upstream,
+                    sink,
+                    &options,
                 )
                 .await;
-                return Err(anyhow!("Licensed server"));
             }
-            LoginSuccess::PACKET_ID => {
-                let packet = packet.to_raw_packet_compressed(threshold)?;
-                packet.write_async(&mut primary).await?;
-                packet.write_async(&mut secondary).await?;
-                break;
-            }
-            SetCompression::PACKET_ID => {
-                let compression: SetCompression = packet.deserialize_payload()?;
-                threshold = Some(compression.threshold.0);
-
-                let packet = packet.to_raw_packet()?;
-                packet.write_async(&mut primary).await?;
-                packet.write_async(&mut secondary).await?;
-            }
-            _ => unreachable!(),
+            LoginStep::Encryption(request, threshold) => (request, threshold),
+        };
+
+    let log = Logger::new(&*sink);
+    let protocol = version.table().protocol;
+    let secret = match answer_encryption(&mut remote, &request, protocol, threshold, &options).await
+    {
+        Ok(secret) => secret,
+        Err(e) => {
+            send_login_error(&mut primary, &mut secondary, format!("{:#}", e)).await;
+            return Err(e);
         }
+    };
+    if let Some(auth) = &options.auth {
+        log.info(format!("Вход на сервер от имени {}", auth.account.name));
     }
 
+    let mut remote = Cfb8Stream::new_from_tcp(remote, &secret)?;
+    let LoginStep::Success(threshold, login_success) =
+        relay_login(&mut remote, &mut primary, &mut secondary, threshold).await?
+    else {
+        send_login_error(
+            &mut primary,
+            &mut secondary,
+            "Сервер повторно запросил шифрование".to_string(),
+        )
+        .await;
+        return Err(anyhow!("Second encryption request"));
+    };
+    version.update_threshold(threshold);
+    version.handle_login_success(login_success);
+    run_controller(
+        primary,
+        secondary,
+        remote.split(),
+        version,
+        upstream,
+        sink,
+        &options,
+    )
+    .await
+}
+
+/// Wires the clients and the server to a [`Controller`] and runs it until the
+/// session ends. With `upstream`, the server's latency is measured alongside.
+async fn run_controller<R, W>(
+    primary: TcpStream,
+    secondary: TcpStream,
+    (remote_read, remote_write): (R, W),
+    version: Version,
+    upstream: Option<Upstream>,
+    sink: Sink,
+    options: &SessionOptions,
+) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
     let (primary_read, primary_write) = primary.into_split();
     let (secondary_read, secondary_write) = secondary.into_split();
-    let (remote_read, remote_write) = remote.into_split();
 
     let (event_tx, event_rx) = mpsc::channel(IO_CHANNEL_CAPACITY);
     let (primary_tx, primary_rx) = mpsc::channel(IO_CHANNEL_CAPACITY);
     let (secondary_tx, secondary_rx) = mpsc::channel(IO_CHANNEL_CAPACITY);
     let (remote_tx, remote_rx) = mpsc::channel(IO_CHANNEL_CAPACITY);
+    options.control.attach(&event_tx);
 
-    version.update_threshold(threshold);
     let controller = Controller::new(
-        ClientId::Primary,
         primary_tx,
         secondary_tx,
         remote_tx,
         event_rx,
         version,
-        app.clone(),
+        sink,
+        options,
     );
 
     let primary_task = tokio::spawn(run_client(
@@ -285,6 +337,7 @@ pub async fn run_proxy_session(
         ClientId::Primary,
         event_tx.clone(),
         primary_rx,
+        vec![],
     ));
     let secondary_task = tokio::spawn(run_client(
         secondary_read,
@@ -292,7 +345,10 @@ pub async fn run_proxy_session(
         ClientId::Secondary,
         event_tx.clone(),
         secondary_rx,
+        vec![],
     ));
+    let probe_task =
+        upstream.map(|upstream| tokio::spawn(probe_upstream(upstream, event_tx.downgrade())));
     let server_task = tokio::spawn(run_server(remote_read, remote_write, event_tx, remote_rx));
 
     controller.run().await;
@@ -304,9 +360,139 @@ pub async fn run_proxy_session(
     primary_task.abort();
     secondary_task.abort();
     server_task.abort();
+    if let Some(probe_task) = probe_task {
+        probe_task.abort();
+    }
     Ok(())
 }
 
+/// Where [`relay_login`] stopped.
+pub enum LoginStep {
+    /// `LoginSuccess` reached both clients; carries the compression threshold
+    /// and the packet itself, which clients joining later get too.
+    Success(Option<i32>, RawPacket),
+    /// The server sent `EncryptionRequest`. Once it is answered, the login
+    /// continues on the encrypted stream from the threshold carried here.
+    Encryption(UncompressedPacket, Option<i32>),
+}
+
+/// Relays the server's login phase to both clients, starting from compression
+/// `threshold`, until `LoginSuccess` or an encryption request.
+///
+/// Anything else the proxy can't take part in (a disconnect or an unknown
+/// login packet) is reported to both clients and ends the session.
+pub async fn relay_login<S, C>(
+    remote: &mut S,
+    primary: &mut C,
+    secondary: &mut C,
+    mut threshold: Option<i32>,
+) -> anyhow::Result<LoginStep>
+where
+    S: AsyncReadExt + Unpin,
+    C: AsyncWriteExt + Unpin,
+{
+    loop {
+        let packet = RawPacket::read_async(remote).await?.uncompress(threshold)?;
+
+        match packet.packet_id {
+            LoginDisconnect::PACKET_ID => {
+                send_login_error(
+                    primary,
+                    secondary,
+                    packet.deserialize_payload::<LoginDisconnect>()?.reason,
+                )
+                .await;
+                return Err(anyhow!("Disconnected by server"));
+            }
+            EncryptionRequest::PACKET_ID => {
+                return Ok(LoginStep::Encryption(packet, threshold));
+            }
+            LoginSuccess::PACKET_ID => {
+                let packet = packet.to_raw_packet_compressed(threshold)?;
+                packet.write_async(primary).await?;
+                packet.write_async(secondary).await?;
+                return Ok(LoginStep::Success(threshold, packet));
+            }
+            SetCompression::PACKET_ID => {
+                let compression: SetCompression = packet.deserialize_payload()?;
+
+                // Framed with the threshold in force before this packet; a negative
+                // threshold turns compression back off.
+                let packet = packet.to_raw_packet_compressed(threshold)?;
+                packet.write_async(primary).await?;
+                packet.write_async(secondary).await?;
+
+                threshold = (compression.threshold.0 >= 0).then_some(compression.threshold.0);
+            }
+            id => {
+                send_login_error(
+                    primary,
+                    secondary,
+                    format!("Неподдерживаемый пакет входа: 0x{:02X}", id),
+                )
+                .await;
+                return Err(anyhow!("Unexpected login packet 0x{:02X}", id));
+            }
+        }
+    }
+}
+
+/// Answers `request` the way the vanilla client does: picks a shared secret,
+/// reports the join to the session server when the server authenticates, and
+/// sends both the secret and the verify token back under the server's key.
+/// Returns the secret, which keys the cipher in both directions.
+async fn answer_encryption(
+    remote: &mut TcpStream,
+    request: &UncompressedPacket,
+    protocol: i32,
+    threshold: Option<i32>,
+    options: &SessionOptions,
+) -> anyhow::Result<[u8; 16]> {
+    let mut payload = Cursor::new(&request.payload);
+    let request = EncryptionRequest::deserialize(&mut payload)?;
+    // Before 1.20.5 every encrypting server also authenticates.
+    let should_authenticate = protocol < 766 || bool::deserialize(&mut payload)?;
+
+    let mut secret = [0u8; 16];
+    openssl::rand::rand_bytes(&mut secret)?;
+
+    if should_authenticate {
+        let Some(auth) = &options.auth else {
+            anyhow::bail!("Лицензионный сервер: войдите в аккаунт Minecraft в VoxelProxy");
+        };
+        let hash = auth::server_hash(&request.server_id, &secret, &request.public_key);
+        auth::join_server(auth, &hash).await?;
+    }
+
+    let key = Rsa::public_key_from_der(&request.public_key)?;
+    let encrypt = |data: &[u8]| -> anyhow::Result<Vec<u8>> {
+        let mut out = vec![0u8; key.size() as usize];
+        let len = key.public_encrypt(data, &mut out, Padding::PKCS1)?;
+        out.truncate(len);
+        Ok(out)
+    };
+    let shared_secret = encrypt(&secret)?;
+    let verify_token = encrypt(&request.verify_token)?;
+
+    let response = if (759..=760).contains(&protocol) {
+        UncompressedPacket::from_packet(&EncryptionResponseFlagged {
+            shared_secret,
+            has_verify_token: true,
+            verify_token,
+        })?
+    } else {
+        UncompressedPacket::from_packet(&EncryptionResponse {
+            shared_secret,
+            verify_token,
+        })?
+    };
+    response
+        .to_raw_packet_compressed(threshold)?
+        .write_async(remote)
+        .await?;
+    Ok(secret)
+}
+
 /// Sends a `LoginDisconnect` packet to a single client.
 pub async fn send_login_disconnect<W: AsyncWriteExt + Unpin>(stream: &mut W, message: String) {
     let disconnect = UncompressedPacket::from_packet(&LoginDisconnect {
@@ -328,6 +514,7 @@ pub async fn send_login_error<W: AsyncWriteExt + Unpin>(
     })
     .unwrap();
 
-    disconnect.write_async(primary).await.unwrap();
-    disconnect.write_async(secondary).await.unwrap();
+    // The session is ending either way; a client that is already gone just misses the reason.
+    let _ = disconnect.write_async(primary).await;
+    let _ = disconnect.write_async(secondary).await;
 }
@@ -0,0 +1,273 @@
+//! Upstream server that plays a [`capture`](crate::capture) back: a proxy
+//! pointed at it gets the recorded clientbound stream, so a user's session can
+//! be run through the controller and the version handlers again without the
+//! server it was recorded on.
+//!
+//! The server's packets go out in order with the recorded gaps between them,
+//! scaled by a speed factor. Whatever the proxy sends is read and dropped,
+//! except for the phase changes: a recorded Configuration or Game packet waits
+//! until the proxy has acknowledged that phase, as a real server would.
+
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, bail};
+use mc_protocol::packet::{RawPacket, UncompressedPacket};
+use serde_json::json;
+use tokio::{
+    net::{TcpListener, TcpStream, tcp::OwnedReadHalf},
+    sync::watch,
+    time::sleep,
+};
+
+use crate::{
+    capture::{self, Direction, Record},
+    logger::Logger,
+    packets::universal::{
+        Intent, handshaking::c2s::Handshake, login::s2c::LoginSuccess, status::s2c::StatusResponse,
+    },
+    protocols::{State, Version, table::PacketTable},
+    proxy::{read_uncompressed, send_login_disconnect},
+    sink::Sink,
+};
+
+/// A recording's clientbound stream, ready to be played to any number of
+/// connections.
+pub struct Replay {
+    protocol: i32,
+    /// The server's packets in the order they arrived, `LoginSuccess` first.
+    records: Vec<Record>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Не удалось открыть запись {}", path.display()))?;
+        let records = capture::read(BufReader::new(file))
+            .with_context(|| format!("Ошибка в записи {}", path.display()))?;
+        Self::new(records)
+    }
+
+    pub fn new(records: Vec<Record>) -> anyhow::Result<Self> {
+        let records: Vec<_> = records
+            .into_iter()
+            .filter(|record| record.direction == Direction::Clientbound)
+            .collect();
+        let Some(first) = records.first() else {
+            bail!("В записи нет пакетов сервера");
+        };
+        if first.state != State::Login || first.packet.packet_id != LoginSuccess::PACKET_ID {
+            bail!("Запись должна начинаться с LoginSuccess");
+        }
+        let protocol = first.protocol;
+        if Version::from_protocol(protocol).is_none() {
+            bail!("Протокол записи {} не поддерживается", protocol);
+        }
+        Ok(Self { protocol, records })
+    }
+
+    /// Protocol number the recording was made with; clients must match it.
+    pub fn protocol(&self) -> i32 {
+        self.protocol
+    }
+
+    /// The server's packets, `LoginSuccess` first.
+    pub fn into_records(self) -> Vec<Record> {
+        self.records
+    }
+
+    /// Plays the recording to every connection `listener` accepts, `speed`
+    /// times faster than recorded, or without pauses at all for a speed of 0.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener, speed: f64, sink: Sink) {
+        while let Ok((stream, _)) = listener.accept().await {
+            let replay = self.clone();
+            let sink = sink.clone();
+            tokio::spawn(async move {
+                let log = Logger::new(&*sink);
+                if let Err(e) = replay.play(stream, speed, &log).await {
+                    log.warn(format!("Воспроизведение прервано: {:#}", e));
+                }
+            });
+        }
+    }
+
+    async fn play(
+        &self,
+        mut stream: TcpStream,
+        speed: f64,
+        log: &Logger<'_>,
+    ) -> anyhow::Result<()> {
+        let handshake: Handshake = read_uncompressed(&mut stream)
+            .await?
+            .deserialize_payload()?;
+        match Intent::try_from(handshake.intent.0) {
+            Ok(Intent::Status) => return self.answer_status(stream).await,
+            Ok(Intent::Login) => {}
+            Err(_) => return Ok(()),
+        }
+
+        // LoginStart: the recorded LoginSuccess answers any name.
+        RawPacket::read_async(&mut stream).await?;
+        let protocol = handshake.protocol_version.0;
+        if protocol != self.protocol {
+            let message = format!(
+                "Запись сделана на протоколе {}, а клиент подключился с {}",
+                self.protocol, protocol
+            );
+            send_login_disconnect(&mut stream, message.clone()).await;
+            bail!(message);
+        }
+        let table = Version::from_protocol(protocol)
+            .context("Протокол записи больше не поддерживается")?
+            .table();
+
+        log.info(format!(
+            "Воспроизведение записи: {} пакетов",
+            self.records.len()
+        ));
+        let (read, mut write) = stream.into_split();
+        let initial = if table.has_configuration() {
+            State::Login
+        } else {
+            State::Game
+        };
+        let (phase_tx, mut phase) = watch::channel(initial);
+        let proxy = tokio::spawn(follow_phase(read, table, phase_tx));
+
+        let mut previous = None;
+        for record in &self.records {
+            if let Some(previous) = previous {
+                sleep(delay(previous, record.time, speed)).await;
+            }
+            previous = Some(record.time);
+            if record.state != State::Login
+                && phase
+                    .wait_for(|&state| state == record.state)
+                    .await
+                    .is_err()
+            {
+                bail!("Прокси отключился");
+            }
+            record.packet.write_async(&mut write).await?;
+        }
+        log.success("Запись воспроизведена");
+
+        // Stay connected until the proxy hangs up, so the clients can look
+        // around the world the recording left them in.
+        proxy.await.ok();
+        Ok(())
+    }
+
+    async fn answer_status(&self, mut stream: TcpStream) -> anyhow::Result<()> {
+        RawPacket::read_async(&mut stream).await?;
+        UncompressedPacket::from_packet(&StatusResponse {
+            response: json!({
+                "version": {
+                    "name": "VoxelProxy",
+                    "protocol": self.protocol
+                },
+                "players": {
+                    "max": 2,
+                    "online": 0,
+                    "sample": []
+                },
+                "description": {
+                    "text": "Воспроизведение записи"
+                }
+            })
+            .to_string(),
+        })?
+        .write_async(&mut stream)
+        .await?;
+
+        // PING
+        RawPacket::read_async(&mut stream)
+            .await?
+            .write_async(&mut stream)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Reads what the proxy sends until it hangs up, publishing the phase it
+/// has acknowledged.
+async fn follow_phase(mut read: OwnedReadHalf, table: &PacketTable, phase: watch::Sender<State>) {
+    let c2s = &table.c2s;
+    while let Ok(raw) = RawPacket::read_async(&mut read).await {
+        let Ok(packet) = raw.as_uncompressed() else {
+            return;
+        };
+        let id = Some(packet.packet_id);
+        let next = match *phase.borrow() {
+            State::Login if id == c2s.login_acknowledged => State::Configuration,
+            State::Configuration if id == c2s.finish_configuration => State::Game,
+            State::Game if id == c2s.configuration_acknowledged => State::Configuration,
+            _ => continue,
+        };
+        phase.send_replace(next);
+    }
+}
+
+/// Pause between two records played `speed` times faster than recorded.
+fn delay(previous: SystemTime, time: SystemTime, speed: f64) -> Duration {
+    if speed <= 0.0 {
+        return Duration::ZERO;
+    }
+    time.duration_since(previous)
+        .unwrap_or_default()
+        .div_f64(speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(direction: Direction, state: State, packet_id: i32) -> Record {
+        Record {
+            time: SystemTime::UNIX_EPOCH,
+            direction,
+            client: None,
+            state,
+            protocol: 774,
+            packet: UncompressedPacket::new(packet_id, vec![]),
+        }
+    }
+
+    #[test]
+    fn keeps_the_server_side_only() {
+        let replay = Replay::new(vec![
+            record(
+                Direction::Clientbound,
+                State::Login,
+                LoginSuccess::PACKET_ID,
+            ),
+            record(Direction::Serverbound, State::Login, 3),
+            record(Direction::Clientbound, State::Configuration, 3),
+        ])
+        .unwrap();
+        assert_eq!(replay.protocol(), 774);
+        assert_eq!(replay.records.len(), 2);
+    }
+
+    #[test]
+    fn needs_the_login_success() {
+        assert!(Replay::new(vec![]).is_err());
+        let game = record(Direction::Clientbound, State::Game, 0x2B);
+        assert!(Replay::new(vec![game]).is_err());
+    }
+
+    #[test]
+    fn delay_scales_with_speed() {
+        let start = SystemTime::UNIX_EPOCH;
+        let later = start + Duration::from_millis(300);
+        assert_eq!(delay(start, later, 1.0), Duration::from_millis(300));
+        assert_eq!(delay(start, later, 3.0), Duration::from_millis(100));
+        assert_eq!(delay(start, later, 0.0), Duration::ZERO);
+        assert_eq!(delay(later, start, 1.0), Duration::ZERO);
+    }
+}
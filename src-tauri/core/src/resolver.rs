@@ -2,12 +2,12 @@ use std::net::{IpAddr, SocketAddr};
 use trust_dns_resolver::{TokioAsyncResolver, config::*};
 
 fn parse_host_port(input: &str, default_port: u16) -> (String, u16) {
-    if let Some((host, port_str)) = input.rsplit_once(':') {
-        if let Ok(port) = port_str.parse::<u16>() {
-            if !host.contains(']') && !host.contains(':') {
-                return (host.to_string(), port);
-            }
-        }
+    if let Some((host, port_str)) = input.rsplit_once(':')
+        && let Ok(port) = port_str.parse::<u16>()
+        && !host.contains(']')
+        && !host.contains(':')
+    {
+        return (host.to_string(), port);
     }
     (input.to_string(), default_port)
 }
@@ -28,16 +28,16 @@ pub async fn resolve_host_port(
 
     // SRV lookup
     let srv_name = format!("_{}._{}.{}", service, protocol, host);
-    if let Ok(srv_lookup) = resolver.srv_lookup(&srv_name).await {
-        if let Some(record) = srv_lookup.iter().next() {
-            let target_ip = resolver
-                .lookup_ip(record.target().to_utf8())
-                .await
-                .ok()?
-                .iter()
-                .next()?;
-            return Some(SocketAddr::new(target_ip, record.port()));
-        }
+    if let Ok(srv_lookup) = resolver.srv_lookup(&srv_name).await
+        && let Some(record) = srv_lookup.iter().next()
+    {
+        let target_ip = resolver
+            .lookup_ip(record.target().to_utf8())
+            .await
+            .ok()?
+            .iter()
+            .next()?;
+        return Some(SocketAddr::new(target_ip, record.port()));
     }
 
     // Fallback: standard A/AAAA lookup
@@ -1,14 +1,12 @@
 #[cfg(target_os = "windows")]
 use std::net::{IpAddr, ToSocketAddrs};
 use std::sync::Arc;
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
 
 use mc_protocol::{
     packet::{RawPacket, UncompressedPacket},
     varint::VarInt,
 };
-#[cfg(target_os = "windows")]
-use tauri::AppHandle;
-use tauri_specta::Event;
 use tokio::sync::Mutex;
 use tokio::{
     net::{
@@ -17,18 +15,26 @@ use tokio::{
     },
     sync::mpsc,
     task::JoinSet,
+    time::timeout,
 };
 
 use crate::{
-    config,
-    events::{ClientStatusEvent, NickNameEvent, ServerAddrEvent, WhichClient},
+    auth::UpstreamAuth,
+    controller::{ClientId, RoutingPolicy, SessionControl},
+    inspector::PacketInspector,
+    latency::Upstream,
     logger::Logger,
+    metrics::SessionMetrics,
     packets::universal::{Intent, handshaking::c2s::Handshake},
     protocols::{Version, VersionProtocol},
-    proxy::{AutoClientInfo, BIND_PORT, DEFAULT_PORT, HANDSHAKE_CHANNEL_CAPACITY},
+    proxy::{AutoClientInfo, DEFAULT_PORT, HANDSHAKE_CHANNEL_CAPACITY},
     resolver::resolve_host_port,
+    sink::Sink,
 };
 
+/// How long a client joining a running session may take to send its LoginStart.
+const LOGIN_START_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Returns true if host resolves to the local machine (loopback).
 #[cfg(target_os = "windows")]
 fn is_loopback_host(host: &str) -> bool {
@@ -82,32 +88,70 @@ async fn wait_for_disconnect(stream: &mut TcpStream) {
 /// and so never emits `SessionEndedEvent`. Re-emitting on a normal disconnect is
 /// idempotent (the controller already marked them offline).
 struct ClientStatusOfflineGuard {
-    app: AppHandle,
+    sink: Sink,
 }
 
 impl Drop for ClientStatusOfflineGuard {
     fn drop(&mut self) {
-        for which in [WhichClient::Primary, WhichClient::Secondary] {
-            ClientStatusEvent {
-                which,
-                online: false,
-            }
-            .emit(&self.app)
-            .ok();
+        for which in [ClientId::Primary, ClientId::Secondary] {
+            self.sink.client_status(which, false);
         }
     }
 }
 
-pub async fn run_manual_mode(server_addr: String, app: AppHandle) -> anyhow::Result<()> {
-    let _status_guard = ClientStatusOfflineGuard { app: app.clone() };
-    let log = Logger::new(&app);
+/// Settings a frontend passes through to every session it starts.
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    /// Account for online-mode servers. When set, the server sees this
+    /// account instead of the primary client's nickname.
+    pub auth: Option<UpstreamAuth>,
+    /// Lets the frontend switch or kick clients in the running session.
+    pub control: SessionControl,
+    /// What the inactive client may send upstream too.
+    pub routing: RoutingPolicy,
+    /// After a hand-over or a closed container, also send the server a click
+    /// it rejects by resending the whole inventory to both clients. Catches
+    /// what the active client moved around that the proxy can't see, but
+    /// anti-cheats may flag the click.
+    pub resync_clicks: bool,
+    /// Directory to record each session's packets to, see [`capture`](crate::capture).
+    pub capture_dir: Option<PathBuf>,
+    /// Reports the packets the controller handles while it is started.
+    pub inspector: PacketInspector,
+    /// Receives every running session's stats, e.g. for a `/metrics` endpoint.
+    pub metrics: SessionMetrics,
+}
+
+impl SessionOptions {
+    /// The LoginStart sent upstream: the primary client's own, or one for the
+    /// proxy's account when it logs in itself.
+    fn upstream_login_start(
+        &self,
+        version: &Version,
+        client_login_start: RawPacket,
+    ) -> anyhow::Result<RawPacket> {
+        match &self.auth {
+            Some(auth) => version.login_start(&auth.account.name, auth.account.uuid),
+            None => Ok(client_login_start),
+        }
+    }
+}
+
+pub async fn run_manual_mode(
+    server_addr: String,
+    bind_addr: SocketAddr,
+    sink: Sink,
+    options: SessionOptions,
+) -> anyhow::Result<()> {
+    let _status_guard = ClientStatusOfflineGuard { sink: sink.clone() };
+    let log = Logger::new(&*sink);
     let (remote_addr, remote_dns) =
         match resolve_host_port(&server_addr, DEFAULT_PORT, "minecraft", "tcp").await {
             Some(addr) => (addr, server_addr),
             None => anyhow::bail!("Не удалось разрешить адрес: \"{}\"", server_addr),
         };
 
-    let listener = match TcpListener::bind(format!("0.0.0.0:{}", BIND_PORT)).await {
+    let listener = match TcpListener::bind(bind_addr).await {
         Ok(t) => t,
         Err(e) => anyhow::bail!("Ошибка при создании сокета: {}", e),
     };
@@ -137,12 +181,7 @@ pub async fn run_manual_mode(server_addr: String, app: AppHandle) -> anyhow::Res
             None => anyhow::bail!("Диспетчер подключений завершился"),
         };
         let primary_login_start = RawPacket::read_async(&mut primary_stream).await?;
-        ClientStatusEvent {
-            which: WhichClient::Primary,
-            online: true,
-        }
-        .emit(&app)
-        .ok();
+        sink.client_status(ClientId::Primary, true);
 
         enum Sec {
             Got((TcpStream, i32)),
@@ -168,24 +207,14 @@ pub async fn run_manual_mode(server_addr: String, app: AppHandle) -> anyhow::Res
             }
             Sec::PrimaryGone => {
                 log.warn("Основной клиент отключился, не дождавшись второго");
-                ClientStatusEvent {
-                    which: WhichClient::Primary,
-                    online: false,
-                }
-                .emit(&app)
-                .ok();
+                sink.client_status(ClientId::Primary, false);
                 continue;
             }
             Sec::Closed => anyhow::bail!("Диспетчер подключений завершился"),
         }
     };
     let _ = RawPacket::read_async(&mut secondary_stream).await?;
-    ClientStatusEvent {
-        which: WhichClient::Secondary,
-        online: true,
-    }
-    .emit(&app)
-    .ok();
+    sink.client_status(ClientId::Secondary, true);
 
     if primary_protocol != secondary_protocol {
         crate::proxy::send_login_error(
@@ -234,38 +263,79 @@ pub async fn run_manual_mode(server_addr: String, app: AppHandle) -> anyhow::Res
         .write_async(&mut remote_stream)
         .await?;
 
-    primary_login_start.write_async(&mut remote_stream).await?;
+    let login_start = options.upstream_login_start(&version, primary_login_start)?;
+    login_start.write_async(&mut remote_stream).await?;
 
     let nickname = version
-        .parse_login_start(&primary_login_start)
+        .parse_login_start(&login_start)
         .unwrap_or("...".to_string());
-    NickNameEvent(nickname.clone()).emit(&app).ok();
-    ServerAddrEvent(handshake.server_address.clone())
-        .emit(&app)
-        .ok();
-    tokio::spawn(config::send_join(
+    sink.nickname(nickname.clone());
+    sink.server_addr(handshake.server_address.clone());
+    sink.join(
         handshake.server_address.clone(),
         nickname,
         handshake.protocol_version.0,
-    ));
+    );
 
-    crate::proxy::run_proxy_session(
-        primary_stream,
-        secondary_stream,
-        remote_stream,
-        version,
-        app,
-    )
-    .await
+    let upstream = Upstream {
+        addr: remote_addr,
+        host: handshake.server_address,
+        port: handshake.server_port,
+        protocol: primary_protocol,
+    };
+
+    // Clients connecting from now on rejoin the running session.
+    let control = options.control.clone();
+    tokio::select! {
+        result = crate::proxy::run_proxy_session(
+            primary_stream,
+            secondary_stream,
+            remote_stream,
+            version,
+            Some(upstream),
+            sink,
+            options,
+        ) => result,
+        _ = hand_over_late_clients(&mut rx, &control) => {
+            anyhow::bail!("Диспетчер подключений завершился")
+        }
+    }
+}
+
+/// Passes every client from `rx` to the running session, which logs it in or
+/// turns it away. Each client's LoginStart is read in a task of its own, so a
+/// connection that never sends one holds up nobody else. Returns when the
+/// dispatcher stops.
+async fn hand_over_late_clients(
+    rx: &mut mpsc::Receiver<(TcpStream, i32)>,
+    control: &SessionControl,
+) {
+    while let Some((mut stream, protocol)) = rx.recv().await {
+        let control = control.clone();
+        tokio::spawn(async move {
+            let login_start = timeout(LOGIN_START_TIMEOUT, RawPacket::read_async(&mut stream));
+            if !matches!(login_start.await, Ok(Ok(_))) {
+                return;
+            }
+            if let Err(mut stream) = control.join(stream, protocol) {
+                crate::proxy::send_login_disconnect(
+                    &mut stream,
+                    "Сессия ещё не началась, подождите".to_string(),
+                )
+                .await;
+            }
+        });
+    }
 }
 
 async fn run_auto_session(
     mut primary: AutoClientInfo,
     mut secondary: AutoClientInfo,
-    app: AppHandle,
+    sink: Sink,
+    options: SessionOptions,
 ) -> anyhow::Result<()> {
-    let _status_guard = ClientStatusOfflineGuard { app: app.clone() };
-    let log = Logger::new(&app);
+    let _status_guard = ClientStatusOfflineGuard { sink: sink.clone() };
+    let log = Logger::new(&*sink);
     // The secondary's LoginStart was already drained at pairing time in
     // `run_automatic_mode` (so its socket could be watched for disconnect while
     // it waited); only the primary's remains to be read here.
@@ -338,30 +408,48 @@ async fn run_auto_session(
         .write_async(&mut remote_stream)
         .await?;
 
-    primary_login_start.write_async(&mut remote_stream).await?;
+    let login_start = options.upstream_login_start(&version, primary_login_start)?;
+    login_start.write_async(&mut remote_stream).await?;
     let nickname = version
-        .parse_login_start(&primary_login_start)
+        .parse_login_start(&login_start)
         .unwrap_or("...".to_string());
-    NickNameEvent(nickname.clone()).emit(&app).ok();
-    ServerAddrEvent(handshake.server_address.clone())
-        .emit(&app)
-        .ok();
-    tokio::spawn(config::send_join(
+    sink.nickname(nickname.clone());
+    sink.server_addr(handshake.server_address.clone());
+    sink.join(
         handshake.server_address.clone(),
         nickname,
         handshake.protocol_version.0,
-    ));
+    );
 
+    let upstream = Upstream {
+        addr: remote_addr,
+        host: handshake.server_address,
+        port: handshake.server_port,
+        protocol: primary.protocol_version,
+    };
     crate::proxy::run_proxy_session(
         primary.stream,
         secondary.stream,
         remote_stream,
         version,
-        app,
+        Some(upstream),
+        sink,
+        options,
     )
     .await
 }
 
+/// Hands an auto-mode client to the running session that lost one.
+async fn rejoin(mut client: AutoClientInfo, control: SessionControl) {
+    if RawPacket::read_async(&mut client.stream).await.is_err() {
+        return;
+    }
+    if let Err(mut stream) = control.join(client.stream, client.protocol_version) {
+        crate::proxy::send_login_disconnect(&mut stream, "Сессия уже завершилась".to_string())
+            .await;
+    }
+}
+
 pub async fn run_panic_mode(client: AutoClientInfo) -> anyhow::Result<()> {
     let remote_addr = match resolve_host_port(
         &client.server_host,
@@ -412,10 +500,12 @@ pub async fn run_automatic_mode(
     use_windivert: bool,
     port_min: u16,
     port_max: u16,
-    app: AppHandle,
+    bind_addr: SocketAddr,
+    sink: Sink,
     panic_mode: Arc<Mutex<bool>>,
+    options: SessionOptions,
 ) -> anyhow::Result<()> {
-    let log = Logger::new(&app);
+    let log = Logger::new(&*sink);
     use crate::hotspot_redirect;
     use std::sync::Arc;
 
@@ -426,11 +516,15 @@ pub async fn run_automatic_mode(
             anyhow::bail!("Автоматический режим требует прав администратора.");
         }
 
-        let (nat_table, redirect) =
-            match hotspot_redirect::start_redirect(BIND_PORT, port_min, port_max, app.clone()) {
-                Ok(t) => t,
-                Err(e) => anyhow::bail!("WinDivert недоступен: {}", e),
-            };
+        let (nat_table, redirect) = match hotspot_redirect::start_redirect(
+            bind_addr.port(),
+            port_min,
+            port_max,
+            sink.clone(),
+        ) {
+            Ok(t) => t,
+            Err(e) => anyhow::bail!("WinDivert недоступен: {}", e),
+        };
         hotspot_redirect::start_nat_cleanup(Arc::clone(&nat_table));
         _redirect_handle = Some(redirect);
         log.success("WinDivert перехват активен");
@@ -439,7 +533,7 @@ pub async fn run_automatic_mode(
         log.info("WinDivert отключён — подключайтесь напрямую");
     }
 
-    let listener = match TcpListener::bind(format!("0.0.0.0:{}", BIND_PORT)).await {
+    let listener = match TcpListener::bind(bind_addr).await {
         Ok(l) => l,
         Err(e) => anyhow::bail!("Ошибка при создании сокета: {}", e),
     };
@@ -470,12 +564,7 @@ pub async fn run_automatic_mode(
         let mut client = match step {
             Step::SecondaryGone => {
                 log.warn("Второй клиент отключился, не дождавшись основного");
-                ClientStatusEvent {
-                    which: WhichClient::Secondary,
-                    online: false,
-                }
-                .emit(&app)
-                .ok();
+                sink.client_status(ClientId::Secondary, false);
                 pending = None;
                 continue;
             }
@@ -487,6 +576,11 @@ pub async fn run_automatic_mode(
             tokio::spawn(run_panic_mode(client));
             continue;
         }
+        if pending.is_none() && options.control.accepts_client() {
+            // A client coming back to the running session, not a new pair.
+            tokio::spawn(rejoin(client, options.control.clone()));
+            continue;
+        }
         if pending.is_none() && is_loopback_host(&client.server_host) {
             log.warn("Клиент подключился к 127.0.0.1 раньше второго клиента — отклонён");
             tokio::spawn(async move {
@@ -506,25 +600,20 @@ pub async fn run_automatic_mode(
         }
         match pending.take() {
             Some(secondary) => {
-                ClientStatusEvent {
-                    which: WhichClient::Primary,
-                    online: true,
-                }
-                .emit(&app)
-                .ok();
-                session_set.spawn(run_auto_session(client, secondary, app.clone()));
+                sink.client_status(ClientId::Primary, true);
+                session_set.spawn(run_auto_session(
+                    client,
+                    secondary,
+                    sink.clone(),
+                    options.clone(),
+                ));
             }
             None => {
                 // Drain the secondary's LoginStart now (it is discarded anyway, see
                 // run_auto_session) so wait_for_disconnect watches a quiet socket
                 // instead of one with the buffered LoginStart still readable.
                 let _ = RawPacket::read_async(&mut client.stream).await;
-                ClientStatusEvent {
-                    which: WhichClient::Secondary,
-                    online: true,
-                }
-                .emit(&app)
-                .ok();
+                sink.client_status(ClientId::Secondary, true);
                 pending = Some(client);
             }
         }
@@ -538,10 +627,12 @@ pub async fn run_automatic_mode(
     _use_windivert: bool,
     _port_min: u16,
     _port_max: u16,
-    app: AppHandle,
+    bind_addr: SocketAddr,
+    sink: Sink,
     panic_mode: Arc<Mutex<bool>>,
+    options: SessionOptions,
 ) -> anyhow::Result<()> {
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", BIND_PORT)).await?;
+    let listener = TcpListener::bind(bind_addr).await?;
 
     let (tx, mut rx) = mpsc::channel(HANDSHAKE_CHANNEL_CAPACITY);
     let mut session_set: JoinSet<anyhow::Result<()>> = JoinSet::new();
@@ -568,12 +659,7 @@ pub async fn run_automatic_mode(
         };
         let mut client = match step {
             Step::SecondaryGone => {
-                ClientStatusEvent {
-                    which: WhichClient::Secondary,
-                    online: false,
-                }
-                .emit(&app)
-                .ok();
+                sink.client_status(ClientId::Secondary, false);
                 pending = None;
                 continue;
             }
@@ -585,27 +671,27 @@ pub async fn run_automatic_mode(
             tokio::spawn(run_panic_mode(client));
             continue;
         }
+        if pending.is_none() && options.control.accepts_client() {
+            // A client coming back to the running session, not a new pair.
+            tokio::spawn(rejoin(client, options.control.clone()));
+            continue;
+        }
         match pending.take() {
             Some(secondary) => {
-                ClientStatusEvent {
-                    which: WhichClient::Primary,
-                    online: true,
-                }
-                .emit(&app)
-                .ok();
-                session_set.spawn(run_auto_session(client, secondary, app.clone()));
+                sink.client_status(ClientId::Primary, true);
+                session_set.spawn(run_auto_session(
+                    client,
+                    secondary,
+                    sink.clone(),
+                    options.clone(),
+                ));
             }
             None => {
                 // Drain the secondary's LoginStart now (it is discarded anyway, see
                 // run_auto_session) so wait_for_disconnect watches a quiet socket
                 // instead of one with the buffered LoginStart still readable.
                 let _ = RawPacket::read_async(&mut client.stream).await;
-                ClientStatusEvent {
-                    which: WhichClient::Secondary,
-                    online: true,
-                }
-                .emit(&app)
-                .ok();
+                sink.client_status(ClientId::Secondary, true);
                 pending = Some(client);
             }
         }
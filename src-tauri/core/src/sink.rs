@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use crate::{
+    controller::ClientId, inspector::PacketEvent, latency::Latency, metrics::SessionStats,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Info,
+    Success,
+    Warn,
+    Error,
+}
+
+/// Receiver for everything the engine reports while a session runs.
+///
+/// The engine never talks to a UI directly: the Tauri app, the CLI or a test
+/// implement this trait and decide what to do with each event. Methods are
+/// called from Tokio tasks (and, for WinDivert, from plain threads), so they
+/// must not block.
+pub trait EventSink: Send + Sync + 'static {
+    fn log(&self, level: LogLevel, message: String);
+
+    /// A client connected (`online: true`) or went away (`online: false`).
+    fn client_status(&self, which: ClientId, online: bool);
+
+    /// Control moved to `which`: at session start, on failover or on request.
+    fn active_client(&self, _which: ClientId) {}
+
+    /// Nickname parsed from the primary client's `LoginStart`.
+    fn nickname(&self, nickname: String);
+
+    /// Address of the upstream server as sent in the handshake.
+    fn server_addr(&self, addr: String);
+
+    /// The session has been handed to the upstream server.
+    fn join(&self, _server_addr: String, _nickname: String, _protocol_version: i32) {}
+
+    /// Payload of a serverbound chat/command packet sent by the active client.
+    fn protocol_metadata(&self, _data: String, _custom: bool) {}
+
+    /// A packet the [inspector](crate::inspector) let through.
+    fn packet(&self, _event: PacketEvent) {}
+
+    /// Periodic snapshot of the running session, see [`metrics`](crate::metrics).
+    fn session_stats(&self, _stats: SessionStats) {}
+
+    /// A link's round-trip time changed, see [`latency`](crate::latency).
+    /// Checked as often as the stats are published.
+    fn latency(&self, _latency: Latency) {}
+}
+
+pub type Sink = Arc<dyn EventSink>;
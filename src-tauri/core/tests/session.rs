@@ -0,0 +1,1205 @@
+//! End-to-end scenarios: a fake upstream server and two scripted clients
+//! drive a real `run_proxy_session` over loopback, once per supported version.
+
+mod support;
+
+use std::{
+    fs,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use mc_protocol::packet::UncompressedPacket;
+use support::{
+    ACCOUNT_UUID, MARKER, MIRROR_TELEPORT_ID, NICKNAME, OPAQUE_C2S_ID, OPAQUE_S2C_ID, Proto,
+    RecordingSink, Session, SessionServer, SinkEvent, control, join, next_stats, start,
+    start_against, start_configuration, start_online, start_with, wait_for,
+};
+use tokio::net::TcpListener;
+use voxelproxy_core::{
+    ClientId, LogLevel, Version,
+    capture::{self, CaptureWriter, Direction, Record},
+    controller::{ControlMessage, RoutingPolicy},
+    inspector::{InspectorFilter, Outcome, PacketInspector},
+    metrics::SessionMetrics,
+    packets::universal::login::s2c::{LoginDisconnect, LoginSuccess, SetCompression},
+    protocols::{State, VersionProtocol, table::PacketCategory},
+    replay::Replay,
+    session::SessionOptions,
+};
+
+/// The active client's movement reaches the server and is mirrored to the
+/// inactive client as a teleport; the inactive client's movement goes nowhere.
+async fn position_mirroring(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        ..
+    } = start(&proto, &[]).await;
+
+    primary.send(&proto.pos(10.5, 64.0, -3.25)).await;
+    server.expect(proto.pos_id()).await;
+
+    let mirrored = secondary.expect(proto.position_id()).await;
+    assert_eq!(
+        proto.read_position(&mirrored),
+        (10.5, 64.0, -3.25, proto.mirror_teleport_id())
+    );
+    primary.expect_silence(&mut server).await;
+    secondary.expect_marker().await;
+
+    secondary.send(&proto.pos(0.0, 0.0, 0.0)).await;
+    assert_eq!(
+        sink.handled(Some(ClientId::Secondary), proto.pos_id())
+            .await,
+        Outcome::Dropped
+    );
+    primary.send(&proto.pos(11.0, 64.0, -3.25)).await;
+    let relayed = server.expect(proto.pos_id()).await;
+    assert_eq!(relayed.payload, proto.pos(11.0, 64.0, -3.25).payload);
+}
+
+/// A ping answered by both clients reaches the server once, including when the
+/// late answer comes from a client that has just taken over.
+async fn ping_deduplication(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        task,
+        ..
+    } = start(&proto, &[]).await;
+
+    server.send(&proto.ping(7)).await;
+    primary.expect(proto.ping_id()).await;
+    secondary.expect(proto.ping_id()).await;
+
+    secondary.send(&proto.pong(7)).await;
+    sink.handled(Some(ClientId::Secondary), proto.pong_id())
+        .await;
+    primary.send(&proto.pong(7)).await;
+    assert_eq!(proto.read_pong(&server.expect(proto.pong_id()).await), 7);
+
+    // Answered by the active client only, then the active client leaves:
+    // the new active client's answer must not be relayed a second time.
+    server.send(&proto.ping(8)).await;
+    primary.expect(proto.ping_id()).await;
+    secondary.expect(proto.ping_id()).await;
+
+    primary.send(&proto.pong(8)).await;
+    assert_eq!(proto.read_pong(&server.expect(proto.pong_id()).await), 8);
+    drop(primary);
+    wait_for(&sink, SinkEvent::ClientStatus(ClientId::Primary, false)).await;
+
+    secondary.send(&proto.pong(8)).await;
+    secondary.send(&proto.pos(1.0, 2.0, 3.0)).await;
+    server.expect(proto.pos_id()).await;
+    server.expect_silence(&mut secondary).await;
+
+    drop(secondary);
+    drop(server);
+    task.await.unwrap().unwrap();
+}
+
+/// When the active client drops, the other one takes over: pings it already
+/// answered are replayed to the server, its packets are relayed, and (where the
+/// version confirms teleports) its confirmation of the proxy's own mirror
+/// teleport is swallowed.
+async fn active_client_failover(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        task,
+        ..
+    } = start(&proto, &[]).await;
+
+    server.send(&proto.ping(42)).await;
+    primary.expect(proto.ping_id()).await;
+    secondary.expect(proto.ping_id()).await;
+
+    secondary.send(&proto.pong(42)).await;
+    sink.handled(Some(ClientId::Secondary), proto.pong_id())
+        .await;
+    drop(primary);
+
+    assert_eq!(proto.read_pong(&server.expect(proto.pong_id()).await), 42);
+    wait_for(&sink, SinkEvent::ClientStatus(ClientId::Primary, false)).await;
+
+    if proto.confirms_teleport() {
+        secondary
+            .send(&proto.accept_teleport(MIRROR_TELEPORT_ID))
+            .await;
+        secondary.send(&proto.accept_teleport(5)).await;
+        let accepted = server.expect(proto.accept_teleport_id()).await;
+        assert_eq!(accepted.payload, proto.accept_teleport(5).payload);
+    }
+
+    secondary.send(&proto.pos(4.0, 5.0, 6.0)).await;
+    server.expect(proto.pos_id()).await;
+
+    drop(secondary);
+    wait_for(&sink, SinkEvent::ClientStatus(ClientId::Secondary, false)).await;
+    task.await.unwrap().unwrap();
+    drop(server);
+}
+
+/// Control handed over on request with both clients connected: the server gets
+/// each ping answered exactly once and nothing else, the new active client is
+/// put back where the server last saw the player, and its confirmations of the
+/// proxy's teleports never reach the server.
+async fn active_client_switch(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        control: session,
+        ..
+    } = start(&proto, &[]).await;
+    wait_for(&sink, SinkEvent::ActiveClient(ClientId::Primary)).await;
+
+    primary.send(&proto.pos(10.5, 64.0, -3.25)).await;
+    assert_eq!(
+        sink.handled(Some(ClientId::Primary), proto.pos_id()).await,
+        Outcome::Mirrored
+    );
+    server.expect(proto.pos_id()).await;
+    secondary.expect(proto.position_id()).await;
+
+    // Ping 11 only the inactive client has answered, ping 12 only the active one.
+    for id in [11, 12] {
+        server.send(&proto.ping(id)).await;
+        primary.expect(proto.ping_id()).await;
+        secondary.expect(proto.ping_id()).await;
+    }
+    secondary.send(&proto.pong(11)).await;
+    sink.handled(Some(ClientId::Secondary), proto.pong_id())
+        .await;
+    primary.send(&proto.pong(12)).await;
+    assert_eq!(proto.read_pong(&server.expect(proto.pong_id()).await), 12);
+
+    control(&session, ControlMessage::SwitchActive(ClientId::Secondary)).await;
+    wait_for(&sink, SinkEvent::ActiveClient(ClientId::Secondary)).await;
+    assert_eq!(proto.read_pong(&server.expect(proto.pong_id()).await), 11);
+    let resync = secondary.expect(proto.position_id()).await;
+    assert_eq!(
+        proto.read_position(&resync),
+        (10.5, 64.0, -3.25, proto.mirror_teleport_id())
+    );
+
+    secondary.send(&proto.pong(12)).await;
+    primary.send(&proto.pong(11)).await;
+    if proto.confirms_teleport() {
+        // One for the mirrored move, one for the resync.
+        for _ in 0..2 {
+            secondary
+                .send(&proto.accept_teleport(MIRROR_TELEPORT_ID))
+                .await;
+        }
+        secondary.send(&proto.accept_teleport(5)).await;
+        let accepted = server.expect(proto.accept_teleport_id()).await;
+        assert_eq!(accepted.payload, proto.accept_teleport(5).payload);
+    }
+    secondary.send(&proto.pos(4.0, 5.0, 6.0)).await;
+    server.expect(proto.pos_id()).await;
+    server.expect_silence(&mut secondary).await;
+    let mirrored = primary.expect(proto.position_id()).await;
+    assert_eq!(
+        proto.read_position(&mirrored),
+        (4.0, 5.0, 6.0, proto.mirror_teleport_id())
+    );
+
+    primary.send(&proto.pos(0.0, 0.0, 0.0)).await;
+    assert_eq!(
+        sink.handled(Some(ClientId::Primary), proto.pos_id()).await,
+        Outcome::Dropped
+    );
+    server.expect_silence(&mut secondary).await;
+}
+
+/// The inactive client follows the active one's hotbar and container closes.
+/// A close or a hand-over puts the inventory and cursor item back the way the
+/// server last sent them, without asking the server for anything. The inactive
+/// client's own selection goes nowhere, and the new active client gets the
+/// slot the server last saw picked.
+async fn inventory_mirroring(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        control: session,
+        ..
+    } = start(&proto, &[]).await;
+
+    primary.send(&proto.select_slot(4)).await;
+    server
+        .expect(proto.table.c2s.set_carried_item.unwrap())
+        .await;
+    assert_eq!(
+        secondary.expect(proto.held_slot_id()).await.payload,
+        proto.held_slot(4).payload
+    );
+
+    let select = proto.table.c2s.set_carried_item.unwrap();
+    secondary.send(&proto.select_slot(7)).await;
+    assert_eq!(
+        sink.handled(Some(ClientId::Secondary), select).await,
+        Outcome::Dropped
+    );
+    server.expect_silence(&mut primary).await;
+    primary.expect_silence(&mut server).await;
+    secondary.expect_marker().await;
+
+    let inventory = [proto.set_content(0, 1), proto.set_slot(0, 36, 2)];
+    let chest = [
+        proto.open_screen(5, 1),
+        proto.set_content(5, 3),
+        proto.set_cursor(4),
+    ];
+    for packet in inventory.iter().chain(&chest) {
+        server.send(packet).await;
+        for client in [&mut primary, &mut secondary] {
+            client.expect(packet.packet_id).await;
+        }
+    }
+    primary.send(&proto.close_container(5)).await;
+    server.expect(proto.table.c2s.container_close).await;
+    let mirrored = secondary.expect(proto.table.s2c.container_close).await;
+    assert_eq!(mirrored.payload, [5]);
+    // The chest and the cursor item are gone with the close.
+    for packet in &inventory {
+        assert_eq!(
+            secondary.expect(packet.packet_id).await.payload,
+            packet.payload
+        );
+    }
+
+    let chest = [
+        proto.open_screen(6, 1),
+        proto.held_slot(2),
+        proto.set_slot(0, 36, 5),
+        proto.set_cursor(6),
+    ];
+    for packet in &chest {
+        server.send(packet).await;
+        for client in [&mut primary, &mut secondary] {
+            client.expect(packet.packet_id).await;
+        }
+    }
+
+    control(&session, ControlMessage::SwitchActive(ClientId::Secondary)).await;
+    wait_for(&sink, SinkEvent::ActiveClient(ClientId::Secondary)).await;
+    assert_eq!(
+        secondary.expect(proto.held_slot_id()).await.payload,
+        proto.held_slot(2).payload
+    );
+    for packet in [
+        proto.set_content(0, 1),
+        proto.set_slot(0, 36, 5),
+        proto.set_cursor(6),
+    ] {
+        assert_eq!(
+            secondary.expect(packet.packet_id).await.payload,
+            packet.payload
+        );
+    }
+
+    secondary.send(&proto.select_slot(3)).await;
+    server
+        .expect(proto.table.c2s.set_carried_item.unwrap())
+        .await;
+    assert_eq!(
+        primary.expect(proto.held_slot_id()).await.payload,
+        proto.held_slot(3).payload
+    );
+    server.expect_silence(&mut secondary).await;
+}
+
+/// Asked for, resync clicks go upstream after a close by the active client and
+/// after a hand-over, naming the container the server has open so it resends
+/// that one to both clients.
+async fn resync_clicks(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        control: session,
+        ..
+    } = start_with(
+        &proto,
+        &[],
+        SessionOptions {
+            resync_clicks: true,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    server.send(&proto.open_screen(5, 1)).await;
+    for client in [&mut primary, &mut secondary] {
+        client.expect(proto.table.s2c.open_screen.unwrap()).await;
+    }
+    primary.send(&proto.close_container(5)).await;
+    server.expect(proto.table.c2s.container_close).await;
+    let click = server.expect(proto.container_click_id()).await;
+    assert_eq!(proto.read_click_container(&click), 0);
+    secondary.expect(proto.table.s2c.container_close).await;
+
+    server.send(&proto.open_screen(6, 1)).await;
+    for client in [&mut primary, &mut secondary] {
+        client.expect(proto.table.s2c.open_screen.unwrap()).await;
+    }
+    control(&session, ControlMessage::SwitchActive(ClientId::Secondary)).await;
+    wait_for(&sink, SinkEvent::ActiveClient(ClientId::Secondary)).await;
+    let click = server.expect(proto.container_click_id()).await;
+    assert_eq!(proto.read_click_container(&click), 6);
+}
+
+/// Kicking a client closes only its connection: kicking the active one hands
+/// control over, and the last client left can't be kicked.
+async fn kick_client(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        control: session,
+        ..
+    } = start(&proto, &[]).await;
+
+    control(&session, ControlMessage::Kick(ClientId::Primary)).await;
+    primary.expect_closed().await;
+    wait_for(&sink, SinkEvent::ClientStatus(ClientId::Primary, false)).await;
+    wait_for(&sink, SinkEvent::ActiveClient(ClientId::Secondary)).await;
+
+    secondary.send(&proto.pos(4.0, 5.0, 6.0)).await;
+    server.expect(proto.pos_id()).await;
+
+    control(&session, ControlMessage::Kick(ClientId::Secondary)).await;
+    server.send(&proto.ping(3)).await;
+    secondary.expect(proto.ping_id()).await;
+    secondary.send(&proto.pong(3)).await;
+    assert_eq!(proto.read_pong(&server.expect(proto.pong_id()).await), 3);
+}
+
+/// A client that left can come back: the proxy logs it in from its cache and
+/// replays the world without the chunks the server replaced or unloaded since,
+/// or the movement, sounds and other transient packets, then puts it where the
+/// player stands. It returns as the inactive client, and nobody can join while
+/// both are connected.
+async fn late_join(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        secondary,
+        sink,
+        control: session,
+        listener,
+        ..
+    } = start(&proto, &[256]).await;
+
+    let opaque = |tag: u8| UncompressedPacket::new(OPAQUE_S2C_ID, vec![tag]);
+    let mut sent = vec![opaque(1), proto.chunk(0, 0, 2), proto.chunk(1, 1, 3)];
+    sent.push(proto.chunk(0, 0, 4));
+    sent.extend(proto.forget_chunk(1, 1));
+    sent.push(proto.ping(5));
+    for packet in &sent {
+        server.send(packet).await;
+        primary.expect(packet.packet_id).await;
+    }
+    primary.send(&proto.pong(5)).await;
+    server.expect(proto.pong_id()).await;
+    primary.send(&proto.pos(1.0, 2.0, 3.0)).await;
+    server.expect(proto.pos_id()).await;
+
+    drop(secondary);
+    wait_for(&sink, SinkEvent::ClientStatus(ClientId::Secondary, false)).await;
+    for &id in proto.table.s2c.transient.iter().cycle().take(1000) {
+        server.send(&UncompressedPacket::new(id, vec![0; 64])).await;
+        primary.expect(id).await;
+    }
+    server.send(&opaque(6)).await;
+    primary.expect(OPAQUE_S2C_ID).await;
+
+    let mut late = join(&listener, &session, &proto).await;
+    let compression = late.expect(SetCompression::PACKET_ID).await;
+    late.threshold = Some(
+        compression
+            .deserialize_payload::<SetCompression>()
+            .unwrap()
+            .threshold
+            .0,
+    );
+    late.expect(LoginSuccess::PACKET_ID).await;
+    let (c2s, s2c) = (&proto.table.c2s, &proto.table.s2c);
+    if let (Some(ack), Some(c2s_finish), Some(s2c_finish)) = (
+        c2s.login_acknowledged,
+        c2s.finish_configuration,
+        s2c.finish_configuration,
+    ) {
+        late.send(&UncompressedPacket::new(ack, vec![])).await;
+        late.expect(s2c_finish).await;
+        late.send(&UncompressedPacket::new(c2s_finish, vec![]))
+            .await;
+    }
+
+    // The first copy of chunk (0, 0) and the unloaded (1, 1) are gone; 1.8
+    // has no unload packet, so there (1, 1) stays.
+    let mut replayed = vec![(OPAQUE_S2C_ID, 1)];
+    if s2c.forget_level_chunk.is_none() {
+        replayed.push((s2c.level_chunk.unwrap(), 3));
+    }
+    replayed.extend([(s2c.level_chunk.unwrap(), 4), (OPAQUE_S2C_ID, 6)]);
+    for (id, tag) in replayed {
+        assert_eq!(late.expect(id).await.payload.last(), Some(&tag));
+    }
+    let position = late.expect(proto.position_id()).await;
+    assert_eq!(proto.read_position(&position).0, 1.0);
+    wait_for(&sink, SinkEvent::ClientStatus(ClientId::Secondary, true)).await;
+
+    // Back in the session, as the inactive client.
+    late.send(&proto.pos(9.0, 9.0, 9.0)).await;
+    assert_eq!(
+        sink.handled(Some(ClientId::Secondary), proto.pos_id())
+            .await,
+        Outcome::Dropped
+    );
+    server.expect_silence(&mut primary).await;
+    server.send(&proto.ping(7)).await;
+    for client in [&mut primary, &mut late] {
+        client.expect(proto.ping_id()).await;
+    }
+    late.send(&proto.pong(7)).await;
+    sink.handled(Some(ClientId::Secondary), proto.pong_id())
+        .await;
+    primary.send(&proto.pong(7)).await;
+    assert_eq!(proto.read_pong(&server.expect(proto.pong_id()).await), 7);
+    server.expect_silence(&mut primary).await;
+
+    let mut third = join(&listener, &session, &proto).await;
+    third.expect(LoginDisconnect::PACKET_ID).await;
+    third.expect_closed().await;
+
+    control(&session, ControlMessage::SwitchActive(ClientId::Secondary)).await;
+    late.send(&proto.pos(4.0, 5.0, 6.0)).await;
+    server.expect(proto.pos_id()).await;
+}
+
+/// `#vp` commands typed in chat never reach the server: the proxy runs them
+/// and answers in the sender's chat. The acknowledgements a swallowed message
+/// carried still go upstream; ordinary chat is relayed as before.
+async fn chat_commands(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        ..
+    } = start(&proto, &[]).await;
+
+    primary.send(&proto.chat("#vp status", 3)).await;
+    if let Some(ack) = proto.table.c2s.chat_ack {
+        assert_eq!(proto.read_chat_ack(&server.expect(ack).await), 3);
+    }
+    let reply = primary.expect(proto.system_chat_id()).await;
+    assert!(
+        proto
+            .read_system_chat(&reply)
+            .contains("Управление: основной")
+    );
+
+    // The inactive client takes over by itself.
+    secondary.send(&proto.chat("#vp switch", 0)).await;
+    wait_for(&sink, SinkEvent::ActiveClient(ClientId::Secondary)).await;
+    let reply = secondary.expect(proto.system_chat_id()).await;
+    assert!(
+        proto
+            .read_system_chat(&reply)
+            .contains("Управление передано")
+    );
+
+    secondary.send(&proto.chat("#vp fly", 0)).await;
+    let reply = secondary.expect(proto.system_chat_id()).await;
+    assert!(
+        proto
+            .read_system_chat(&reply)
+            .contains("Неизвестная команда")
+    );
+    server.expect_silence(&mut secondary).await;
+
+    let hello = proto.chat("hello", 0);
+    secondary.send(&hello).await;
+    assert_eq!(server.expect(proto.chat_id()).await.payload, hello.payload);
+
+    // Without a name `kick` means the inactive client, here the sender.
+    primary.send(&proto.chat("#vp kick", 0)).await;
+    let reply = primary.expect(proto.system_chat_id()).await;
+    assert!(proto.read_system_chat(&reply).contains("Отключён основной"));
+    primary.expect_closed().await;
+    wait_for(&sink, SinkEvent::ClientStatus(ClientId::Primary, false)).await;
+
+    secondary.send(&proto.chat("#vp kick", 0)).await;
+    let reply = secondary.expect(proto.system_chat_id()).await;
+    assert!(proto.read_system_chat(&reply).contains("единственного"));
+    server.expect_silence(&mut secondary).await;
+}
+
+/// Under a routing policy the inactive client's packets in the chosen
+/// categories reach the server, the rest of them still don't, and `#vp`
+/// commands stay with the proxy.
+async fn category_routing(proto: Proto) {
+    let routing = RoutingPolicy {
+        inactive: vec![PacketCategory::Chat, PacketCategory::Commands],
+    };
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        ..
+    } = start_with(
+        &proto,
+        &[],
+        SessionOptions {
+            routing,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let hello = proto.chat("hello", 0);
+    secondary.send(&hello).await;
+    assert_eq!(server.expect(proto.chat_id()).await.payload, hello.payload);
+
+    secondary.send(&proto.pos(0.0, 0.0, 0.0)).await;
+    secondary.send(&proto.chat("#vp status", 0)).await;
+    secondary.expect(proto.system_chat_id()).await;
+    server.expect_silence(&mut primary).await;
+
+    primary.send(&proto.pos(1.0, 2.0, 3.0)).await;
+    assert_eq!(
+        server.expect(proto.pos_id()).await.payload,
+        proto.pos(1.0, 2.0, 3.0).payload
+    );
+}
+
+/// Cookie and known-packs answers reach the server once. When the active
+/// client leaves mid-Configuration, an answer only the other client gave is
+/// replayed, and one the server already has is not sent again.
+async fn configuration_exchanges(proto: Proto) {
+    let (c2s, s2c) = (&proto.table.c2s, &proto.table.s2c);
+    let (Some(packs_request), Some(packs), Some(cookie_request), Some(cookie)) = (
+        s2c.select_known_packs,
+        c2s.select_known_packs,
+        s2c.cookie_request,
+        c2s.cookie_response,
+    ) else {
+        return;
+    };
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        task,
+        ..
+    } = start_configuration(&proto, &[]).await;
+
+    server
+        .send(&UncompressedPacket::new(packs_request, vec![0]))
+        .await;
+    server
+        .send(&UncompressedPacket::new(cookie_request, vec![1, b'k']))
+        .await;
+    for client in [&mut primary, &mut secondary] {
+        client.expect(packs_request).await;
+        client.expect(cookie_request).await;
+    }
+
+    // The active client answers the known packs, the inactive one the cookie.
+    primary.send(&UncompressedPacket::new(packs, vec![0])).await;
+    server.expect(packs).await;
+    let answer = UncompressedPacket::new(cookie, vec![1, b'k', 0]);
+    secondary.send(&answer).await;
+    sink.handled(Some(ClientId::Secondary), cookie).await;
+
+    drop(primary);
+    assert_eq!(server.expect(cookie).await.payload, answer.payload);
+    wait_for(&sink, SinkEvent::ClientStatus(ClientId::Primary, false)).await;
+
+    secondary
+        .send(&UncompressedPacket::new(packs, vec![0]))
+        .await;
+    server.expect_silence(&mut secondary).await;
+
+    let (c2s_finish, s2c_finish) = (
+        c2s.finish_configuration.unwrap(),
+        s2c.finish_configuration.unwrap(),
+    );
+    server
+        .send(&UncompressedPacket::new(s2c_finish, vec![]))
+        .await;
+    secondary.expect(s2c_finish).await;
+    secondary
+        .send(&UncompressedPacket::new(c2s_finish, vec![]))
+        .await;
+    server.expect(c2s_finish).await;
+
+    secondary.send(&proto.pos(4.0, 5.0, 6.0)).await;
+    server.expect(proto.pos_id()).await;
+
+    drop(secondary);
+    wait_for(&sink, SinkEvent::ClientStatus(ClientId::Secondary, false)).await;
+    task.await.unwrap().unwrap();
+    drop(server);
+}
+
+/// Packets survive every compression setup the server can negotiate during
+/// login: none, one threshold, a changed threshold, and compression disabled
+/// again by a negative one.
+async fn compression(proto: Proto, thresholds: &[i32]) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        ..
+    } = start(&proto, thresholds).await;
+
+    for size in [8, 4096] {
+        let big = UncompressedPacket::new(OPAQUE_S2C_ID, vec![0xAB; size]);
+        server.send(&big).await;
+        for client in [&mut primary, &mut secondary] {
+            assert_eq!(client.expect(OPAQUE_S2C_ID).await.payload, big.payload);
+        }
+
+        let big = UncompressedPacket::new(OPAQUE_C2S_ID, vec![0xCD; size]);
+        primary.send(&big).await;
+        assert_eq!(server.expect(OPAQUE_C2S_ID).await.payload, big.payload);
+    }
+
+    primary.send(&proto.pos(1.0, 2.0, 3.0)).await;
+    server.expect(proto.pos_id()).await;
+    let mirrored = secondary.expect(proto.position_id()).await;
+    assert_eq!(
+        proto.read_position(&mirrored),
+        (1.0, 2.0, 3.0, proto.mirror_teleport_id())
+    );
+}
+
+/// A session asked to record writes one file with every packet it read,
+/// decompressed and tagged with its sender and phase, starting from the
+/// server's `LoginSuccess`.
+async fn packet_capture(proto: Proto) {
+    let dir = std::env::temp_dir().join(format!(
+        "voxelproxy-capture-{}-{}",
+        std::process::id(),
+        proto.protocol()
+    ));
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        task,
+        ..
+    } = start_with(
+        &proto,
+        &[256],
+        SessionOptions {
+            capture_dir: Some(dir.clone()),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let big = UncompressedPacket::new(OPAQUE_S2C_ID, vec![0xAB; 4096]);
+    server.send(&big).await;
+    primary.expect(OPAQUE_S2C_ID).await;
+    secondary.expect(OPAQUE_S2C_ID).await;
+    primary.send(&proto.pos(1.0, 2.0, 3.0)).await;
+    server.expect(proto.pos_id()).await;
+    secondary.expect(proto.position_id()).await;
+    secondary.send(&proto.pos(4.0, 5.0, 6.0)).await;
+    sink.handled(Some(ClientId::Secondary), proto.pos_id())
+        .await;
+
+    drop(server);
+    primary.expect_closed().await;
+    secondary.expect_closed().await;
+    task.await.unwrap().unwrap();
+
+    let files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1);
+    let records = capture::read(fs::File::open(&files[0]).unwrap()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let login_success = &records[0];
+    assert_eq!(login_success.direction, Direction::Clientbound);
+    assert_eq!(login_success.state, State::Login);
+    assert_eq!(login_success.packet.packet_id, LoginSuccess::PACKET_ID);
+    assert!(records.iter().all(|r| r.protocol == proto.protocol()));
+    if proto.table.has_configuration() {
+        assert!(records.iter().any(|r| r.state == State::Configuration));
+    }
+
+    let game: Vec<_> = records
+        .iter()
+        .filter(|r| r.state == State::Game)
+        .map(|r| (r.direction, r.client, r.packet.packet_id, &r.packet.payload))
+        .collect();
+    assert_eq!(
+        game,
+        [
+            // `start_with` making sure the server heard nothing else.
+            (
+                Direction::Serverbound,
+                Some(ClientId::Primary),
+                OPAQUE_C2S_ID,
+                &MARKER.to_vec()
+            ),
+            (Direction::Clientbound, None, OPAQUE_S2C_ID, &big.payload),
+            (
+                Direction::Serverbound,
+                Some(ClientId::Primary),
+                proto.pos_id(),
+                &proto.pos(1.0, 2.0, 3.0).payload
+            ),
+            (
+                Direction::Serverbound,
+                Some(ClientId::Secondary),
+                proto.pos_id(),
+                &proto.pos(4.0, 5.0, 6.0).payload
+            ),
+        ]
+    );
+}
+
+/// A started inspector reports the clients' Game packets its filter lets
+/// through, named after the table, with what the controller did to each. A
+/// new filter replaces the one the running session had.
+async fn packet_inspector(proto: Proto) {
+    let inspector = PacketInspector::default();
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        ..
+    } = start_with(
+        &proto,
+        &[],
+        SessionOptions {
+            inspector: inspector.clone(),
+            ..Default::default()
+        },
+    )
+    .await;
+    inspector.start(InspectorFilter {
+        direction: Some(Direction::Serverbound),
+        states: vec![State::Game],
+        ..Default::default()
+    });
+    let started = sink.events().len();
+
+    server
+        .send(&UncompressedPacket::new(OPAQUE_S2C_ID, vec![1]))
+        .await;
+    primary.expect(OPAQUE_S2C_ID).await;
+    secondary.expect(OPAQUE_S2C_ID).await;
+    secondary.send(&proto.pos(4.0, 5.0, 6.0)).await;
+    sink.handled(Some(ClientId::Secondary), proto.pos_id())
+        .await;
+    primary.send(&proto.chat("#vp status", 0)).await;
+    primary.expect(proto.system_chat_id()).await;
+    primary.send(&proto.pos(1.0, 2.0, 3.0)).await;
+    server.expect(proto.pos_id()).await;
+
+    let seen: Vec<_> = sink
+        .events()
+        .into_iter()
+        .skip(started)
+        .filter_map(|event| match event {
+            SinkEvent::Packet(event) => Some((event.client, event.name, event.outcome)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        seen,
+        [
+            (
+                Some(ClientId::Secondary),
+                Some("move_pos"),
+                Outcome::Dropped
+            ),
+            (Some(ClientId::Primary), Some("chat"), Outcome::Skipped),
+            (Some(ClientId::Primary), Some("move_pos"), Outcome::Mirrored),
+        ]
+    );
+}
+
+/// The session publishes its traffic, hand-overs and unanswered pings to the
+/// sink and to the metrics handle.
+async fn session_stats(proto: Proto) {
+    let metrics = SessionMetrics::default();
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        control: session,
+        ..
+    } = start_with(
+        &proto,
+        &[],
+        SessionOptions {
+            metrics: metrics.clone(),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    server.send(&proto.ping(7)).await;
+    primary.expect(proto.ping_id()).await;
+    secondary.expect(proto.ping_id()).await;
+    primary.send(&proto.pos(1.0, 2.0, 3.0)).await;
+    let pos = server.expect(proto.pos_id()).await;
+
+    let stats = next_stats(&sink).await;
+    assert_eq!(stats.pending_pings, 1);
+    assert_eq!(stats.handovers, 0);
+    assert!(stats.server.clientbound.packets >= 1);
+    assert_eq!(stats.primary.clientbound, stats.server.clientbound);
+    assert_eq!(stats.secondary.clientbound, stats.server.clientbound);
+    assert!(stats.primary.serverbound.packets >= 1);
+    assert!(stats.server.serverbound.bytes >= pos.payload.len() as u64);
+    assert!(stats.uptime > Duration::ZERO);
+
+    control(&session, ControlMessage::SwitchActive(ClientId::Secondary)).await;
+    wait_for(&sink, SinkEvent::ActiveClient(ClientId::Secondary)).await;
+    assert_eq!(next_stats(&sink).await.handovers, 1);
+    assert_eq!(metrics.latest().handovers, 1);
+}
+
+/// Each client's latency is how long it takes to echo the server's keep-alive,
+/// and a change of it reaches the sink.
+async fn client_latency(proto: Proto) {
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        sink,
+        ..
+    } = start(&proto, &[]).await;
+
+    let keep_alive = proto.keep_alive(Direction::Clientbound, 42);
+    server.send(&keep_alive).await;
+    primary.expect(keep_alive.packet_id).await;
+    secondary.expect(keep_alive.packet_id).await;
+    let answer = proto.keep_alive(Direction::Serverbound, 42);
+    primary.send(&answer).await;
+    server.expect(answer.packet_id).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    secondary.send(&answer).await;
+    sink.handled(Some(ClientId::Secondary), answer.packet_id)
+        .await;
+
+    let latency = next_stats(&sink).await.latency;
+    let (primary_rtt, secondary_rtt) = (latency.primary.unwrap(), latency.secondary.unwrap());
+    assert!(secondary_rtt >= Duration::from_millis(200));
+    assert!(primary_rtt < secondary_rtt);
+    assert_eq!(latency.upstream, None);
+    assert!(sink.events().contains(&SinkEvent::Latency(latency)));
+}
+
+/// A recording loaded into the replay server reaches both clients through the
+/// proxy, each phase only after the clients have acknowledged it.
+async fn replay(proto: Proto) {
+    let record = |state, packet| Record {
+        time: SystemTime::now(),
+        direction: Direction::Clientbound,
+        client: None,
+        state,
+        protocol: proto.protocol(),
+        packet,
+    };
+    let (c2s, s2c) = (&proto.table.c2s, &proto.table.s2c);
+    let mut records = vec![record(State::Login, support::packet(&LoginSuccess {}))];
+    if let Some(finish) = s2c.finish_configuration {
+        let opaque = UncompressedPacket::new(OPAQUE_S2C_ID, vec![1]);
+        records.push(record(State::Configuration, opaque));
+        records.push(record(
+            State::Configuration,
+            UncompressedPacket::new(finish, vec![]),
+        ));
+    }
+    let big = UncompressedPacket::new(OPAQUE_S2C_ID, vec![0xAB; 4096]);
+    records.push(record(State::Game, big.clone()));
+    records.push(record(State::Game, proto.ping(5)));
+
+    let path = std::env::temp_dir().join(format!(
+        "voxelproxy-replay-{}-{}.pcapng",
+        std::process::id(),
+        proto.protocol()
+    ));
+    let mut writer = CaptureWriter::new(fs::File::create(&path).unwrap()).unwrap();
+    for record in &records {
+        writer.write(record).unwrap();
+    }
+    drop(writer);
+    let replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(replay.protocol(), proto.protocol());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream = listener.local_addr().unwrap();
+    let sink = Arc::new(RecordingSink::default());
+    tokio::spawn(Arc::new(replay).serve(listener, 0.0, sink.clone()));
+    let (mut primary, mut secondary, session_sink, task) = start_against(&proto, upstream).await;
+
+    if let (Some(c2s_finish), Some(s2c_finish)) =
+        (c2s.finish_configuration, s2c.finish_configuration)
+    {
+        for client in [&mut primary, &mut secondary] {
+            assert_eq!(client.expect(OPAQUE_S2C_ID).await.payload, [1]);
+            client.expect(s2c_finish).await;
+        }
+        for (client, id) in [
+            (&mut secondary, ClientId::Secondary),
+            (&mut primary, ClientId::Primary),
+        ] {
+            client
+                .send(&UncompressedPacket::new(c2s_finish, vec![]))
+                .await;
+            session_sink.handled(Some(id), c2s_finish).await;
+        }
+    }
+    for client in [&mut primary, &mut secondary] {
+        assert_eq!(client.expect(OPAQUE_S2C_ID).await.payload, big.payload);
+        client.expect(proto.ping_id()).await;
+    }
+    if let Some(c2s_finish) = c2s.finish_configuration {
+        // Game waited until the clients had finished configuring.
+        let packets: Vec<_> = session_sink
+            .events()
+            .into_iter()
+            .filter_map(|event| match event {
+                SinkEvent::Packet(event) => Some(event),
+                _ => None,
+            })
+            .collect();
+        let finished = packets
+            .iter()
+            .position(|p| p.client == Some(ClientId::Primary) && p.packet_id == c2s_finish);
+        let game = packets
+            .iter()
+            .position(|p| p.client.is_none() && p.state == State::Game);
+        assert!(finished.is_some() && finished < game);
+    }
+    wait_for(
+        &sink,
+        SinkEvent::Log(LogLevel::Success, "Запись воспроизведена".to_string()),
+    )
+    .await;
+
+    drop(primary);
+    drop(secondary);
+    task.await.unwrap().unwrap();
+}
+
+/// Upstream going away ends the session and closes both clients.
+async fn server_disconnect(proto: Proto) {
+    let Session {
+        server,
+        mut primary,
+        mut secondary,
+        task,
+        ..
+    } = start(&proto, &[]).await;
+
+    drop(server);
+    primary.expect_closed().await;
+    secondary.expect_closed().await;
+    task.await.unwrap().unwrap();
+}
+
+/// The nickname comes out of LoginStart whatever the version appends to it
+/// (signature data on 1.19–1.19.2, a UUID later on), including the LoginStart
+/// the proxy writes for its own account.
+fn login_start_nickname(proto: Proto) {
+    let version = Version::from_protocol(proto.protocol()).unwrap();
+    let packet = proto.login_start(NICKNAME).to_raw_packet().unwrap();
+    assert_eq!(
+        version.parse_login_start(&packet).as_deref(),
+        Some(NICKNAME)
+    );
+
+    let packet = version.login_start(NICKNAME, ACCOUNT_UUID).unwrap();
+    assert_eq!(
+        version.parse_login_start(&packet).as_deref(),
+        Some(NICKNAME)
+    );
+}
+
+/// Against an online-mode server the proxy joins through the session server
+/// and encrypts its upstream connection; the clients' side is unchanged.
+async fn online_mode(proto: Proto) {
+    let mut session_server = SessionServer::start().await;
+    let Session {
+        mut server,
+        mut primary,
+        mut secondary,
+        ..
+    } = start_online(&proto, &mut session_server).await;
+
+    primary.send(&proto.pos(1.0, 2.0, 3.0)).await;
+    assert_eq!(
+        server.expect(proto.pos_id()).await.payload,
+        proto.pos(1.0, 2.0, 3.0).payload
+    );
+    secondary.expect(proto.position_id()).await;
+
+    server.send(&proto.ping(9)).await;
+    primary.expect(proto.ping_id()).await;
+    secondary.expect(proto.ping_id()).await;
+    primary.send(&proto.pong(9)).await;
+    assert_eq!(proto.read_pong(&server.expect(proto.pong_id()).await), 9);
+}
+
+macro_rules! scenarios {
+    ($($version:ident),* $(,)?) => {
+        $(
+            mod $version {
+                use super::*;
+
+                #[test]
+                fn login_start_nickname() {
+                    super::login_start_nickname(support::$version());
+                }
+
+                #[tokio::test]
+                async fn position_mirroring() {
+                    super::position_mirroring(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn ping_deduplication() {
+                    super::ping_deduplication(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn active_client_failover() {
+                    super::active_client_failover(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn active_client_switch() {
+                    super::active_client_switch(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn inventory_mirroring() {
+                    super::inventory_mirroring(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn resync_clicks() {
+                    super::resync_clicks(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn kick_client() {
+                    super::kick_client(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn late_join() {
+                    super::late_join(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn chat_commands() {
+                    super::chat_commands(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn category_routing() {
+                    super::category_routing(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn configuration_exchanges() {
+                    super::configuration_exchanges(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn online_mode() {
+                    super::online_mode(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn packet_capture() {
+                    super::packet_capture(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn packet_inspector() {
+                    super::packet_inspector(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn replay() {
+                    super::replay(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn session_stats() {
+                    super::session_stats(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn client_latency() {
+                    super::client_latency(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn server_disconnect() {
+                    super::server_disconnect(support::$version()).await;
+                }
+
+                #[tokio::test]
+                async fn uncompressed() {
+                    super::compression(support::$version(), &[]).await;
+                }
+
+                #[tokio::test]
+                async fn compressed() {
+                    super::compression(support::$version(), &[256]).await;
+                }
+
+                #[tokio::test]
+                async fn compression_threshold_change() {
+                    super::compression(support::$version(), &[256, 64]).await;
+                }
+
+                #[tokio::test]
+                async fn compression_disabled() {
+                    super::compression(support::$version(), &[256, -1]).await;
+                }
+            }
+        )*
+    };
+}
+
+scenarios!(
+    v1_8_9, v1_12_2, v1_16_5, v1_17_1, v1_18_2, v1_19_2, v1_19_4, v1_20_1, v1_20_2, v1_20_4,
+    v1_20_6, v1_21_3, v1_21_4, v1_21_5, v1_21_8, v1_21_11
+);
@@ -0,0 +1,1095 @@
+//! In-process test kit: a stand-in upstream server and two scripted clients
+//! talking to `run_proxy_session` over loopback TCP.
+//!
+//! [`start`] plays the part of `session.rs` (accepts both clients, reads their
+//! Handshake/LoginStart, opens the upstream connection) and then walks every
+//! peer through SetCompression/LoginSuccess and, on 1.20.2+, the Configuration
+//! phase, so a scenario begins with all three peers in the Game state.
+//! [`start_online`] does the same against an online-mode server, with a
+//! [`SessionServer`] standing in for Mojang's.
+
+#![allow(dead_code)]
+
+use std::{
+    io::Cursor,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use mc_protocol::{
+    encryption::Cfb8Stream,
+    packet::{PacketId, RawPacket, UncompressedPacket},
+    ser::{Deserialize, Serialize},
+    varint::VarInt,
+};
+use openssl::rsa::{Padding, Rsa};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    task::JoinHandle,
+    time::timeout,
+};
+use uuid::Uuid;
+use voxelproxy_core::{
+    ClientId, EventSink, LogLevel, Version,
+    auth::{self, Account, UpstreamAuth},
+    capture::Direction,
+    controller::{ControlMessage, SessionControl},
+    inspector::{InspectorFilter, Outcome, PacketEvent},
+    latency::Latency,
+    metrics::{STATS_INTERVAL, SessionStats},
+    packets::universal::{
+        Intent,
+        handshaking::c2s::Handshake,
+        login::s2c::{LoginSuccess, SetCompression},
+    },
+    protocols::{
+        self, VersionProtocol,
+        table::{ChatLayout, ClickLayout, PacketTable, PingLayout, PositionLayout},
+    },
+    proxy::run_proxy_session,
+    session::SessionOptions,
+};
+
+/// How long any single read may block before the scenario is declared hung.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the sink is checked for an event a scenario waits on.
+const POLL: Duration = Duration::from_millis(10);
+
+/// Clientbound packet id none of the version handlers look at.
+pub const OPAQUE_S2C_ID: i32 = 0x7E;
+/// Serverbound packet id none of the version handlers look at.
+pub const OPAQUE_C2S_ID: i32 = 0x7E;
+/// Payload of the opaque packet [`Peer::send_marker`] sends.
+pub const MARKER: &[u8] = b"marker";
+
+pub const NICKNAME: &str = "VoxelTester";
+
+/// Account the proxy logs in with in [`start_online`].
+pub const ACCOUNT_NAME: &str = "VoxelOwner";
+pub const ACCOUNT_UUID: Uuid = Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+
+/// Teleport id the proxy stamps on the positions it mirrors to the inactive client.
+pub const MIRROR_TELEPORT_ID: i32 = 1000;
+
+/// Everything a scenario needs to speak one protocol version. Packets are
+/// encoded here by hand from the version's table rather than with the proxy's
+/// own packet structs, so a layout mistake in the proxy shows up as a failure.
+pub struct Proto {
+    pub table: &'static PacketTable,
+}
+
+pub fn packet<P: PacketId + Serialize>(p: &P) -> UncompressedPacket {
+    UncompressedPacket::from_packet(p).unwrap()
+}
+
+fn encode(packet_id: i32, fields: &[&dyn Field]) -> UncompressedPacket {
+    let mut payload = Vec::new();
+    for field in fields {
+        field.write(&mut payload);
+    }
+    UncompressedPacket::new(packet_id, payload)
+}
+
+/// Object-safe `Serialize` so [`encode`] can take a mixed field list.
+trait Field {
+    fn write(&self, buf: &mut Vec<u8>);
+}
+
+impl<T: Serialize> Field for T {
+    fn write(&self, buf: &mut Vec<u8>) {
+        self.serialize(buf).unwrap();
+    }
+}
+
+impl Proto {
+    pub fn new(table: &'static PacketTable) -> Self {
+        Self { table }
+    }
+
+    pub fn protocol(&self) -> i32 {
+        self.table.protocol
+    }
+
+    pub fn login_start(&self, name: &str) -> UncompressedPacket {
+        let name = name.to_string();
+        if self.table.has_configuration() {
+            encode(0, &[&name, &Uuid::nil()])
+        } else if self.table.protocol >= 761 {
+            encode(0, &[&name, &Option::<Uuid>::None])
+        } else if self.table.protocol >= 759 {
+            // 1.19–1.19.2: signature data, then (1.19.1+) the UUID.
+            let key = (1_700_000_000_000i64, vec![0xABu8; 162], vec![0xCDu8; 256]);
+            if self.table.protocol >= 760 {
+                encode(
+                    0,
+                    &[&name, &true, &key.0, &key.1, &key.2, &Some(Uuid::nil())],
+                )
+            } else {
+                encode(0, &[&name, &true, &key.0, &key.1, &key.2])
+            }
+        } else {
+            encode(0, &[&name])
+        }
+    }
+
+    /// Serverbound `MovePlayer.Pos`; the trailing byte is `on_ground`/`flags`.
+    pub fn pos(&self, x: f64, y: f64, z: f64) -> UncompressedPacket {
+        encode(self.pos_id(), &[&x, &y, &z, &1u8])
+    }
+
+    pub fn pos_id(&self) -> i32 {
+        self.table.c2s.move_pos
+    }
+
+    pub fn accept_teleport(&self, id: i32) -> UncompressedPacket {
+        encode(self.accept_teleport_id(), &[&VarInt(id)])
+    }
+
+    pub fn accept_teleport_id(&self) -> i32 {
+        self.table.c2s.accept_teleportation.unwrap()
+    }
+
+    /// Whether the client confirms teleports (everything after 1.8).
+    pub fn confirms_teleport(&self) -> bool {
+        self.table.c2s.accept_teleportation.is_some()
+    }
+
+    /// Teleport id [`Proto::read_position`] reports for a mirrored position.
+    pub fn mirror_teleport_id(&self) -> Option<i32> {
+        self.confirms_teleport().then_some(MIRROR_TELEPORT_ID)
+    }
+
+    /// Clientbound ping (ContainerAck before 1.17).
+    pub fn ping(&self, id: i32) -> UncompressedPacket {
+        match self.table.layout.ping {
+            PingLayout::Ping => encode(self.ping_id(), &[&id]),
+            PingLayout::ContainerAck => encode(self.ping_id(), &[&0i8, &(id as i16), &false]),
+        }
+    }
+
+    pub fn ping_id(&self) -> i32 {
+        self.table.s2c.ping
+    }
+
+    /// Serverbound answer to [`Proto::ping`].
+    pub fn pong(&self, id: i32) -> UncompressedPacket {
+        match self.table.layout.ping {
+            PingLayout::Ping => encode(self.pong_id(), &[&id]),
+            PingLayout::ContainerAck => encode(self.pong_id(), &[&0i8, &(id as i16), &true]),
+        }
+    }
+
+    pub fn pong_id(&self) -> i32 {
+        self.table.c2s.pong
+    }
+
+    pub fn read_pong(&self, packet: &UncompressedPacket) -> i32 {
+        let mut cursor = Cursor::new(&packet.payload);
+        match self.table.layout.ping {
+            PingLayout::Ping => i32::deserialize(&mut cursor).unwrap(),
+            PingLayout::ContainerAck => {
+                i8::deserialize(&mut cursor).unwrap();
+                i16::deserialize(&mut cursor).unwrap() as i32
+            }
+        }
+    }
+
+    /// Keep-alive with `id`, the same in both directions: a VarInt on 1.8,
+    /// a long since.
+    pub fn keep_alive(&self, direction: Direction, id: i64) -> UncompressedPacket {
+        let packet_id = match direction {
+            Direction::Serverbound => self.table.c2s.keep_alive,
+            Direction::Clientbound => self.table.s2c.keep_alive,
+        };
+        let packet_id = packet_id.expect("every built-in table has keep-alives");
+        if self.protocol() == 47 {
+            encode(packet_id, &[&VarInt(id as i32)])
+        } else {
+            encode(packet_id, &[&id])
+        }
+    }
+
+    pub fn position_id(&self) -> i32 {
+        self.table.s2c.position
+    }
+
+    /// Clientbound chunk data at `(x, z)`; `tag` stands in for the sections.
+    pub fn chunk(&self, x: i32, z: i32, tag: u8) -> UncompressedPacket {
+        encode(self.table.s2c.level_chunk.unwrap(), &[&x, &z, &tag])
+    }
+
+    /// Clientbound chunk unload, `None` before 1.9. Since 1.20.2 the position
+    /// is a packed long with z in the high half.
+    pub fn forget_chunk(&self, x: i32, z: i32) -> Option<UncompressedPacket> {
+        let id = self.table.s2c.forget_level_chunk?;
+        Some(if self.table.has_configuration() {
+            encode(id, &[&z, &x])
+        } else {
+            encode(id, &[&x, &z])
+        })
+    }
+
+    /// Serverbound hotbar selection.
+    pub fn select_slot(&self, slot: i16) -> UncompressedPacket {
+        encode(self.table.c2s.set_carried_item.unwrap(), &[&slot])
+    }
+
+    /// Clientbound hotbar selection; the slot is a byte, or a one-byte VarInt
+    /// since 1.21.2.
+    pub fn held_slot(&self, slot: i8) -> UncompressedPacket {
+        encode(self.held_slot_id(), &[&slot])
+    }
+
+    pub fn held_slot_id(&self) -> i32 {
+        self.table.s2c.set_carried_item.unwrap()
+    }
+
+    /// Clientbound container opening; `tag` stands in for the type and title.
+    pub fn open_screen(&self, container_id: u8, tag: u8) -> UncompressedPacket {
+        encode(self.table.s2c.open_screen.unwrap(), &[&container_id, &tag])
+    }
+
+    /// Container close; the id is laid out the same in both directions.
+    pub fn close_container(&self, container_id: u8) -> UncompressedPacket {
+        encode(self.table.c2s.container_close, &[&container_id])
+    }
+
+    /// Clientbound container contents; `tag` stands in for the state id, the
+    /// items and the cursor item.
+    pub fn set_content(&self, container_id: u8, tag: u8) -> UncompressedPacket {
+        let id = self.table.s2c.container_set_content.unwrap();
+        encode(id, &[&container_id, &tag])
+    }
+
+    /// Clientbound slot update, with a state id since 1.17; `tag` stands in
+    /// for the item.
+    pub fn set_slot(&self, container_id: i8, slot: i16, tag: u8) -> UncompressedPacket {
+        let id = self.table.s2c.container_set_slot.unwrap();
+        if self.table.layout.click == ClickLayout::StateId {
+            encode(id, &[&container_id, &VarInt(1), &slot, &tag])
+        } else {
+            encode(id, &[&container_id, &slot, &tag])
+        }
+    }
+
+    /// Clientbound cursor item: a packet of its own since 1.21.2, a slot
+    /// update for container and slot -1 before.
+    pub fn set_cursor(&self, tag: u8) -> UncompressedPacket {
+        match self.table.s2c.set_cursor_item {
+            Some(id) => encode(id, &[&tag]),
+            None => self.set_slot(-1, -1, tag),
+        }
+    }
+
+    pub fn container_click_id(&self) -> i32 {
+        self.table.c2s.container_click.unwrap()
+    }
+
+    /// Container a serverbound click names; ids stay below 128, so the u8 and
+    /// VarInt layouts are the same byte.
+    pub fn read_click_container(&self, packet: &UncompressedPacket) -> u8 {
+        packet.payload[0]
+    }
+
+    /// Serverbound chat message. Since 1.19.3 it carries the acknowledgement
+    /// offset and bitset (and a checksum since 1.21.5) after an unsigned
+    /// header; the older tails are left out, the proxy never reads them.
+    pub fn chat(&self, message: &str, offset: i32) -> UncompressedPacket {
+        let (id, message) = (self.table.c2s.chat, message.to_string());
+        if self.table.c2s.chat_ack.is_none() {
+            return encode(id, &[&message]);
+        }
+        let mut packet = encode(
+            id,
+            &[
+                &message,
+                &0i64,
+                &0i64,
+                &false,
+                &VarInt(offset),
+                &0u8,
+                &0u8,
+                &0u8,
+            ],
+        );
+        if self.protocol() >= 770 {
+            packet.payload.push(0);
+        }
+        packet
+    }
+
+    pub fn chat_id(&self) -> i32 {
+        self.table.c2s.chat
+    }
+
+    pub fn read_chat_ack(&self, packet: &UncompressedPacket) -> i32 {
+        VarInt::deserialize(&mut Cursor::new(&packet.payload))
+            .unwrap()
+            .0
+    }
+
+    pub fn system_chat_id(&self) -> i32 {
+        self.table.s2c.system_chat.unwrap()
+    }
+
+    /// Text of a chat line the proxy wrote, checking it is a system message.
+    pub fn read_system_chat(&self, packet: &UncompressedPacket) -> String {
+        let mut cursor = Cursor::new(&packet.payload);
+        let json = |cursor: &mut Cursor<&Vec<u8>>| {
+            let json: Value = serde_json::from_str(&String::deserialize(cursor).unwrap()).unwrap();
+            json["text"].as_str().unwrap().to_string()
+        };
+        let text = match self.table.layout.chat {
+            ChatLayout::Legacy | ChatLayout::LegacySender => {
+                let text = json(&mut cursor);
+                assert_eq!(
+                    i8::deserialize(&mut cursor).unwrap(),
+                    1,
+                    "not a system line"
+                );
+                text
+            }
+            ChatLayout::Json => json(&mut cursor),
+            ChatLayout::Nbt => {
+                assert_eq!(
+                    u8::deserialize(&mut cursor).unwrap(),
+                    0x0A,
+                    "not a compound"
+                );
+                let string = |cursor: &mut Cursor<&Vec<u8>>| {
+                    let mut bytes = vec![0; u16::deserialize(cursor).unwrap() as usize];
+                    std::io::Read::read_exact(cursor, &mut bytes).unwrap();
+                    String::from_utf8(bytes).unwrap()
+                };
+                let mut text = None;
+                loop {
+                    match u8::deserialize(&mut cursor).unwrap() {
+                        0x00 => break,
+                        0x08 => {
+                            let (key, value) = (string(&mut cursor), string(&mut cursor));
+                            if key == "text" {
+                                text = Some(value);
+                            }
+                        }
+                        tag => panic!("unexpected NBT tag {}", tag),
+                    }
+                }
+                text.expect("component without text")
+            }
+        };
+        if matches!(self.table.layout.chat, ChatLayout::Json | ChatLayout::Nbt) {
+            assert!(!bool::deserialize(&mut cursor).unwrap(), "overlay line");
+        }
+        text
+    }
+
+    /// Clientbound player position as `(x, y, z, teleport_id)`.
+    pub fn read_position(&self, packet: &UncompressedPacket) -> (f64, f64, f64, Option<i32>) {
+        let mut cursor = Cursor::new(&packet.payload);
+        let f64 = |c: &mut Cursor<&Vec<u8>>| f64::deserialize(c).unwrap();
+        match self.table.layout.position {
+            PositionLayout::NoConfirm => {
+                (f64(&mut cursor), f64(&mut cursor), f64(&mut cursor), None)
+            }
+            PositionLayout::Legacy | PositionLayout::LegacyDismount => {
+                let (x, y, z) = (f64(&mut cursor), f64(&mut cursor), f64(&mut cursor));
+                f32::deserialize(&mut cursor).unwrap();
+                f32::deserialize(&mut cursor).unwrap();
+                u8::deserialize(&mut cursor).unwrap();
+                (x, y, z, Some(VarInt::deserialize(&mut cursor).unwrap().0))
+            }
+            PositionLayout::Delta => {
+                let id = VarInt::deserialize(&mut cursor).unwrap().0;
+                (
+                    f64(&mut cursor),
+                    f64(&mut cursor),
+                    f64(&mut cursor),
+                    Some(id),
+                )
+            }
+        }
+    }
+}
+
+macro_rules! builtin {
+    ($($version:ident),*) => {
+        $(
+            pub fn $version() -> Proto {
+                Proto::new(&protocols::$version::TABLE)
+            }
+        )*
+    };
+}
+
+builtin!(
+    v1_8_9, v1_12_2, v1_16_5, v1_17_1, v1_18_2, v1_19_2, v1_19_4, v1_20_1, v1_20_2, v1_20_4,
+    v1_20_6, v1_21_3, v1_21_4, v1_21_5, v1_21_8, v1_21_11
+);
+
+/// Byte stream under a [`Peer`]: a socket, or one wrapped in AES/CFB8.
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// One end of a loopback connection that frames packets like a Minecraft peer,
+/// tracking the compression threshold it has been told about.
+pub struct Peer {
+    pub stream: Box<dyn Stream>,
+    pub threshold: Option<i32>,
+}
+
+impl Peer {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream: Box::new(stream),
+            threshold: None,
+        }
+    }
+
+    /// Switches the connection to AES/CFB8 keyed with `secret`.
+    fn encrypt(self, secret: &[u8; 16]) -> Self {
+        let (read, write) = tokio::io::split(self.stream);
+        Self {
+            stream: Box::new(Cfb8Stream::new(read, write, secret).unwrap()),
+            threshold: self.threshold,
+        }
+    }
+
+    pub async fn send(&mut self, packet: &UncompressedPacket) {
+        packet
+            .to_raw_packet_compressed(self.threshold)
+            .unwrap()
+            .write_async(&mut self.stream)
+            .await
+            .unwrap();
+        self.stream.flush().await.unwrap();
+    }
+
+    pub async fn send_packet<P: PacketId + Serialize>(&mut self, p: &P) {
+        self.send(&packet(p)).await;
+    }
+
+    pub async fn recv(&mut self) -> UncompressedPacket {
+        let raw = timeout(READ_TIMEOUT, RawPacket::read_async(&mut self.stream))
+            .await
+            .expect("timed out waiting for a packet")
+            .expect("connection closed while waiting for a packet");
+        raw.uncompress(self.threshold).unwrap()
+    }
+
+    /// Reads the next packet and asserts its id.
+    pub async fn expect(&mut self, packet_id: i32) -> UncompressedPacket {
+        let packet = self.recv().await;
+        assert_eq!(
+            packet.packet_id, packet_id,
+            "unexpected packet id (payload {:?})",
+            packet.payload
+        );
+        packet
+    }
+
+    /// Sends an opaque packet the proxy relays as is, to tell everything it
+    /// sent before apart from what comes after.
+    pub async fn send_marker(&mut self) {
+        // Both opaque ids are the same, whichever way the marker goes.
+        self.send(&UncompressedPacket::new(OPAQUE_C2S_ID, MARKER.to_vec()))
+            .await;
+    }
+
+    /// Asserts that the next packet is a marker.
+    pub async fn expect_marker(&mut self) {
+        let packet = self.recv().await;
+        assert!(
+            packet.packet_id == OPAQUE_S2C_ID && packet.payload == MARKER,
+            "expected the marker, got packet {:#x} (payload {:?})",
+            packet.packet_id,
+            packet.payload
+        );
+    }
+
+    /// Asserts that nothing reaches this peer ahead of a marker `via` sends
+    /// now. The controller handles each peer's packets in order, so whatever
+    /// it sends for `via`'s earlier packets arrives first; another peer's
+    /// packets must have been [handled](RecordingSink::handled) already.
+    pub async fn expect_silence(&mut self, via: &mut Peer) {
+        via.send_marker().await;
+        self.expect_marker().await;
+    }
+
+    /// Asserts that the peer has closed the connection.
+    pub async fn expect_closed(&mut self) {
+        let read = timeout(READ_TIMEOUT, RawPacket::read_async(&mut self.stream))
+            .await
+            .expect("timed out waiting for the connection to close");
+        assert!(read.is_err(), "expected a closed connection");
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SinkEvent {
+    Log(LogLevel, String),
+    ClientStatus(ClientId, bool),
+    ActiveClient(ClientId),
+    Nickname(String),
+    ServerAddr(String),
+    Join(String, String, i32),
+    ProtocolMetadata(String, bool),
+    Packet(PacketEvent),
+    Stats(SessionStats),
+    Latency(Latency),
+}
+
+/// `EventSink` that records everything for later assertions.
+#[derive(Default)]
+pub struct RecordingSink {
+    pub events: Mutex<Vec<SinkEvent>>,
+    /// Sender and id of each packet [`RecordingSink::handled`] has returned.
+    taken: Mutex<Vec<(Option<ClientId>, i32)>>,
+}
+
+impl RecordingSink {
+    pub fn events(&self) -> Vec<SinkEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Waits for the controller to handle the next packet `from` sent (the
+    /// server's when `None`) with id `packet_id`, and returns what it did
+    /// with it. Needs the session's inspector to let the packet through, as
+    /// the sessions started here do unless given one.
+    pub async fn handled(&self, from: Option<ClientId>, packet_id: i32) -> Outcome {
+        let key = (from, packet_id);
+        let nth = self
+            .taken
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|&&k| k == key)
+            .count();
+        let outcome = timeout(READ_TIMEOUT, async {
+            loop {
+                let outcome = self
+                    .events()
+                    .into_iter()
+                    .filter_map(|e| match e {
+                        SinkEvent::Packet(event) if (event.client, event.packet_id) == key => {
+                            Some(event.outcome)
+                        }
+                        _ => None,
+                    })
+                    .nth(nth);
+                if let Some(outcome) = outcome {
+                    return outcome;
+                }
+                tokio::time::sleep(POLL).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("controller never handled {:#x} from {:?}", packet_id, from));
+        self.taken.lock().unwrap().push(key);
+        outcome
+    }
+
+    fn push(&self, event: SinkEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+impl EventSink for RecordingSink {
+    fn log(&self, level: LogLevel, message: String) {
+        self.push(SinkEvent::Log(level, message));
+    }
+
+    fn client_status(&self, which: ClientId, online: bool) {
+        self.push(SinkEvent::ClientStatus(which, online));
+    }
+
+    fn active_client(&self, which: ClientId) {
+        self.push(SinkEvent::ActiveClient(which));
+    }
+
+    fn nickname(&self, nickname: String) {
+        self.push(SinkEvent::Nickname(nickname));
+    }
+
+    fn server_addr(&self, addr: String) {
+        self.push(SinkEvent::ServerAddr(addr));
+    }
+
+    fn join(&self, server_addr: String, nickname: String, protocol_version: i32) {
+        self.push(SinkEvent::Join(server_addr, nickname, protocol_version));
+    }
+
+    fn protocol_metadata(&self, data: String, custom: bool) {
+        self.push(SinkEvent::ProtocolMetadata(data, custom));
+    }
+
+    fn packet(&self, event: PacketEvent) {
+        self.push(SinkEvent::Packet(event));
+    }
+
+    fn session_stats(&self, stats: SessionStats) {
+        self.push(SinkEvent::Stats(stats));
+    }
+
+    fn latency(&self, latency: Latency) {
+        self.push(SinkEvent::Latency(latency));
+    }
+}
+
+/// A running session. Every peer is in the Game state unless it came from
+/// [`start_configuration`].
+pub struct Session {
+    pub server: Peer,
+    pub primary: Peer,
+    pub secondary: Peer,
+    pub sink: Arc<RecordingSink>,
+    pub control: SessionControl,
+    pub task: JoinHandle<anyhow::Result<()>>,
+    pub listener: TcpListener,
+}
+
+impl Session {
+    /// Ends the Configuration phase for both clients, inactive one first. Does
+    /// nothing for versions without the phase.
+    pub async fn finish_configuration(&mut self, proto: &Proto) {
+        let (Some(c2s_finish), Some(s2c_finish)) = (
+            proto.table.c2s.finish_configuration,
+            proto.table.s2c.finish_configuration,
+        ) else {
+            return;
+        };
+
+        self.server
+            .send(&UncompressedPacket::new(s2c_finish, vec![]))
+            .await;
+        for (client, id) in [
+            (&mut self.secondary, ClientId::Secondary),
+            (&mut self.primary, ClientId::Primary),
+        ] {
+            client.expect(s2c_finish).await;
+            client
+                .send(&UncompressedPacket::new(c2s_finish, vec![]))
+                .await;
+            self.sink.handled(Some(id), c2s_finish).await;
+        }
+        self.server.expect(c2s_finish).await;
+    }
+
+    /// Waits for `run_proxy_session` to return.
+    pub async fn finish(self) -> anyhow::Result<()> {
+        timeout(READ_TIMEOUT, self.task)
+            .await
+            .expect("session did not end")
+            .expect("session task panicked")
+    }
+}
+
+/// Connects one more client mid-session, the way `session.rs` does once the
+/// session runs: its Handshake and LoginStart are read, then the socket is
+/// handed to `control`.
+pub async fn join(listener: &TcpListener, control: &SessionControl, proto: &Proto) -> Peer {
+    let (client, mut proxy) = pair(listener).await;
+    let mut client = Peer::new(client);
+    client.send(&handshake(proto.protocol())).await;
+    client.send(&proto.login_start(NICKNAME)).await;
+    RawPacket::read_async(&mut proxy).await.unwrap();
+    RawPacket::read_async(&mut proxy).await.unwrap();
+    control
+        .join(proxy, proto.protocol())
+        .expect("session must be running");
+    client
+}
+
+/// Sends `message` to the session, retrying until the controller has attached.
+pub async fn control(control: &SessionControl, message: ControlMessage) {
+    timeout(READ_TIMEOUT, async {
+        while control.send(message).is_err() {
+            tokio::time::sleep(POLL).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("session never accepted {:?}", message));
+}
+
+/// Polls the sink until `event` has been recorded.
+pub async fn wait_for(sink: &RecordingSink, event: SinkEvent) {
+    timeout(READ_TIMEOUT, async {
+        while !sink.events().contains(&event) {
+            tokio::time::sleep(POLL).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("sink never recorded {:?}", event));
+}
+
+/// Waits for the first stats the session publishes after this call.
+pub async fn next_stats(sink: &RecordingSink) -> SessionStats {
+    let seen = sink.events().len();
+    timeout(STATS_INTERVAL + READ_TIMEOUT, async {
+        loop {
+            let stats = sink.events().into_iter().skip(seen).find_map(|e| match e {
+                SinkEvent::Stats(stats) => Some(stats),
+                _ => None,
+            });
+            if let Some(stats) = stats {
+                return stats;
+            }
+            tokio::time::sleep(POLL).await;
+        }
+    })
+    .await
+    .expect("session never published its stats")
+}
+
+/// `options` with the inspector reporting every packet, for
+/// [`RecordingSink::handled`], unless the scenario started its own.
+fn inspected(options: SessionOptions) -> SessionOptions {
+    if !options.inspector.is_enabled() {
+        options.inspector.start(InspectorFilter::default());
+    }
+    options
+}
+
+/// Loopback pair: `(our end, end handed to the proxy)`. Nagle is off on both
+/// ends so every exchange isn't held back by a delayed ACK.
+async fn pair(listener: &TcpListener) -> (TcpStream, TcpStream) {
+    let connect = TcpStream::connect(listener.local_addr().unwrap());
+    let (ours, accepted) = tokio::join!(connect, listener.accept());
+    let (ours, accepted) = (ours.unwrap(), accepted.unwrap().0);
+    ours.set_nodelay(true).unwrap();
+    accepted.set_nodelay(true).unwrap();
+    (ours, accepted)
+}
+
+fn handshake(protocol: i32) -> UncompressedPacket {
+    packet(&Handshake {
+        protocol_version: VarInt(protocol),
+        server_address: "localhost".to_string(),
+        server_port: 25565,
+        intent: Intent::Login.into(),
+    })
+}
+
+/// Stand-in for the session server: answers every `join` with 204 and hands
+/// the request path and JSON body to the scenario.
+pub struct SessionServer {
+    pub url: String,
+    joins: mpsc::UnboundedReceiver<(String, Value)>,
+}
+
+impl SessionServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, joins) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let mut request_line = String::new();
+                    stream.read_line(&mut request_line).await.unwrap();
+                    let mut length = 0;
+                    loop {
+                        let mut header = String::new();
+                        stream.read_line(&mut header).await.unwrap();
+                        if header == "\r\n" {
+                            break;
+                        }
+                        if let Some(value) =
+                            header.to_ascii_lowercase().strip_prefix("content-length:")
+                        {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                    let mut body = vec![0; length];
+                    stream.read_exact(&mut body).await.unwrap();
+                    stream
+                        .get_mut()
+                        .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                        .await
+                        .unwrap();
+
+                    let path = request_line.split(' ').nth(1).unwrap_or_default();
+                    let _ = tx.send((path.to_string(), serde_json::from_slice(&body).unwrap()));
+                });
+            }
+        });
+        Self { url, joins }
+    }
+
+    /// Waits for the next `join` request.
+    pub async fn expect_join(&mut self) -> (String, Value) {
+        timeout(READ_TIMEOUT, self.joins.recv())
+            .await
+            .expect("timed out waiting for a join request")
+            .unwrap()
+    }
+}
+
+/// Starts a session and sends one `SetCompression` per entry of `thresholds`
+/// (in order) before `LoginSuccess`.
+pub async fn start(proto: &Proto, thresholds: &[i32]) -> Session {
+    let mut session = start_configuration(proto, thresholds).await;
+    session.finish_configuration(proto).await;
+
+    // Only the active client's packets reach the server.
+    session.server.expect_silence(&mut session.primary).await;
+    session
+}
+
+/// Like [`start`], but the server is in online mode: the proxy logs in as
+/// [`ACCOUNT_NAME`] through `session_server`, and the server side of the
+/// session is encrypted.
+pub async fn start_online(proto: &Proto, session_server: &mut SessionServer) -> Session {
+    let mut session = launch(proto, &[], Some(session_server), SessionOptions::default()).await;
+    session.finish_configuration(proto).await;
+    session.server.expect_silence(&mut session.primary).await;
+    session
+}
+
+/// Like [`start`], but stops once both clients have acknowledged the login and
+/// entered the Configuration phase. Versions without it are already in Game.
+pub async fn start_configuration(proto: &Proto, thresholds: &[i32]) -> Session {
+    launch(proto, thresholds, None, SessionOptions::default()).await
+}
+
+/// Like [`start`], with `options` for the session (its `auth` is ignored).
+pub async fn start_with(proto: &Proto, thresholds: &[i32], options: SessionOptions) -> Session {
+    let mut session = launch(proto, thresholds, None, options).await;
+    session.finish_configuration(proto).await;
+    session.server.expect_silence(&mut session.primary).await;
+    session
+}
+
+/// Starts a session whose upstream is a real server listening on `upstream`,
+/// such as a replay, and logs both clients in through it. Returns the primary
+/// and the secondary client, the session's sink and the session task.
+pub async fn start_against(
+    proto: &Proto,
+    upstream: SocketAddr,
+) -> (
+    Peer,
+    Peer,
+    Arc<RecordingSink>,
+    JoinHandle<anyhow::Result<()>>,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let (primary, mut primary_proxy) = pair(&listener).await;
+    let (secondary, mut secondary_proxy) = pair(&listener).await;
+    let mut primary = Peer::new(primary);
+    let mut secondary = Peer::new(secondary);
+    for client in [&mut primary, &mut secondary] {
+        client.send(&handshake(proto.protocol())).await;
+        client.send(&proto.login_start(NICKNAME)).await;
+    }
+
+    let mut login_starts = vec![];
+    for stream in [&mut primary_proxy, &mut secondary_proxy] {
+        RawPacket::read_async(stream).await.unwrap();
+        login_starts.push(RawPacket::read_async(stream).await.unwrap());
+    }
+    let mut remote = TcpStream::connect(upstream).await.unwrap();
+    handshake(proto.protocol())
+        .write_async(&mut remote)
+        .await
+        .unwrap();
+    login_starts[0].write_async(&mut remote).await.unwrap();
+
+    let version = Version::from_protocol(proto.protocol()).expect("protocol must be supported");
+    let sink = Arc::new(RecordingSink::default());
+    let task = tokio::spawn(run_proxy_session(
+        primary_proxy,
+        secondary_proxy,
+        remote,
+        version,
+        None,
+        sink.clone(),
+        inspected(SessionOptions::default()),
+    ));
+    for client in [&mut primary, &mut secondary] {
+        client.expect(LoginSuccess::PACKET_ID).await;
+    }
+    if let Some(login_acknowledged) = proto.table.c2s.login_acknowledged {
+        for (client, id) in [
+            (&mut secondary, ClientId::Secondary),
+            (&mut primary, ClientId::Primary),
+        ] {
+            client
+                .send(&UncompressedPacket::new(login_acknowledged, vec![]))
+                .await;
+            sink.handled(Some(id), login_acknowledged).await;
+        }
+    }
+    (primary, secondary, sink, task)
+}
+
+/// Plays an online-mode server's side of the encryption handshake, checks the
+/// proxy's answer and its join with the session server, and returns `server`
+/// switched to the negotiated cipher.
+async fn encrypt_login(
+    proto: &Proto,
+    mut server: Peer,
+    session_server: &mut SessionServer,
+) -> Peer {
+    let rsa = Rsa::generate(1024).unwrap();
+    let public_key = rsa.public_key_to_der().unwrap();
+    let verify_token = vec![1u8, 2, 3, 4];
+    let server_id = String::new();
+    let request = if proto.protocol() >= 766 {
+        encode(1, &[&server_id, &public_key, &verify_token, &true])
+    } else {
+        encode(1, &[&server_id, &public_key, &verify_token])
+    };
+    server.send(&request).await;
+
+    let response = server.expect(1).await;
+    let mut payload = Cursor::new(response.payload);
+    let shared_secret = Vec::<u8>::deserialize(&mut payload).unwrap();
+    if (759..=760).contains(&proto.protocol()) {
+        assert!(bool::deserialize(&mut payload).unwrap());
+    }
+    let token = Vec::<u8>::deserialize(&mut payload).unwrap();
+    let decrypt = |data: &[u8]| {
+        let mut out = vec![0u8; rsa.size() as usize];
+        let len = rsa.private_decrypt(data, &mut out, Padding::PKCS1).unwrap();
+        out.truncate(len);
+        out
+    };
+    assert_eq!(decrypt(&token), verify_token);
+    let secret: [u8; 16] = decrypt(&shared_secret).try_into().unwrap();
+
+    let (path, join) = session_server.expect_join().await;
+    assert_eq!(path, "/session/minecraft/join");
+    assert_eq!(join["selectedProfile"], ACCOUNT_UUID.simple().to_string());
+    assert_eq!(
+        join["serverId"],
+        auth::server_hash(&server_id, &secret, &public_key)
+    );
+    server.encrypt(&secret)
+}
+
+async fn launch(
+    proto: &Proto,
+    thresholds: &[i32],
+    session_server: Option<&mut SessionServer>,
+    options: SessionOptions,
+) -> Session {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+    let (primary, mut primary_proxy) = pair(&listener).await;
+    let (secondary, mut secondary_proxy) = pair(&listener).await;
+    let (remote, server) = pair(&listener).await;
+    let mut primary = Peer::new(primary);
+    let mut secondary = Peer::new(secondary);
+    let mut server = Peer::new(server);
+
+    for client in [&mut primary, &mut secondary] {
+        client.send(&handshake(proto.protocol())).await;
+        client.send(&proto.login_start(NICKNAME)).await;
+    }
+
+    // What `session.rs` does before handing the sockets to `run_proxy_session`.
+    let mut primary_login_start = None;
+    for (stream, login_start) in [
+        (&mut primary_proxy, &mut primary_login_start),
+        (&mut secondary_proxy, &mut None),
+    ] {
+        RawPacket::read_async(stream).await.unwrap();
+        *login_start = Some(RawPacket::read_async(stream).await.unwrap());
+    }
+    let version = Version::from_protocol(proto.protocol()).expect("protocol must be supported");
+    let options = SessionOptions {
+        auth: session_server.as_ref().map(|session_server| UpstreamAuth {
+            account: Account {
+                name: ACCOUNT_NAME.to_string(),
+                uuid: ACCOUNT_UUID,
+                access_token: "access".to_string(),
+                refresh_token: "refresh".to_string(),
+            },
+            session_server: session_server.url.clone(),
+        }),
+        ..inspected(options)
+    };
+    let control = options.control.clone();
+    let login_start = match &options.auth {
+        Some(auth) => version
+            .login_start(&auth.account.name, auth.account.uuid)
+            .unwrap(),
+        None => primary_login_start.unwrap(),
+    };
+    let mut remote = remote;
+    handshake(proto.protocol())
+        .write_async(&mut remote)
+        .await
+        .unwrap();
+    login_start.write_async(&mut remote).await.unwrap();
+
+    server.expect(Handshake::PACKET_ID).await;
+    server.expect(0).await;
+
+    let sink = Arc::new(RecordingSink::default());
+    let task = tokio::spawn(run_proxy_session(
+        primary_proxy,
+        secondary_proxy,
+        remote,
+        version,
+        None,
+        sink.clone(),
+        options,
+    ));
+
+    if let Some(session_server) = session_server {
+        server = encrypt_login(proto, server, session_server).await;
+    }
+
+    for &threshold in thresholds {
+        server
+            .send_packet(&SetCompression {
+                threshold: VarInt(threshold),
+            })
+            .await;
+        server.threshold = (threshold >= 0).then_some(threshold);
+        for client in [&mut primary, &mut secondary] {
+            let packet = client.expect(SetCompression::PACKET_ID).await;
+            let threshold = packet
+                .deserialize_payload::<SetCompression>()
+                .unwrap()
+                .threshold
+                .0;
+            client.threshold = (threshold >= 0).then_some(threshold);
+        }
+    }
+    server.send_packet(&LoginSuccess {}).await;
+    for client in [&mut primary, &mut secondary] {
+        client.expect(LoginSuccess::PACKET_ID).await;
+    }
+
+    if let Some(login_acknowledged) = proto.table.c2s.login_acknowledged {
+        // The inactive client goes first so the controller tracks its state
+        // change before the active client's packet reaches the server.
+        secondary
+            .send(&UncompressedPacket::new(login_acknowledged, vec![]))
+            .await;
+        sink.handled(Some(ClientId::Secondary), login_acknowledged)
+            .await;
+        primary
+            .send(&UncompressedPacket::new(login_acknowledged, vec![]))
+            .await;
+        server.expect(login_acknowledged).await;
+    }
+
+    Session {
+        server,
+        primary,
+        secondary,
+        sink,
+        control,
+        task,
+        listener,
+    }
+}
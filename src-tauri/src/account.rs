@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager};
+use voxelproxy_core::{
+    auth::{self, Account, UpstreamAuth},
+    logger::Logger,
+    session::SessionOptions,
+};
+
+const ACCOUNT_FILE: &str = "account.json";
+const SESSION_SERVER_FILE: &str = "session_server.txt";
+
+fn config_file(app: &AppHandle, name: &str) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(name))
+}
+
+pub fn load(app: &AppHandle) -> Option<Account> {
+    Account::load(&config_file(app, ACCOUNT_FILE)?).ok()
+}
+
+pub fn save(app: &AppHandle, account: &Account) -> Result<(), String> {
+    let path = config_file(app, ACCOUNT_FILE).ok_or("Папка настроек недоступна")?;
+    account.save(&path).map_err(|e| format!("{:#}", e))
+}
+
+pub fn remove(app: &AppHandle) -> Result<(), String> {
+    match config_file(app, ACCOUNT_FILE) {
+        Some(path) if path.exists() => std::fs::remove_file(path).map_err(|e| e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Session server the account joins through; Mojang's unless overridden.
+pub fn session_server(app: &AppHandle) -> String {
+    config_file(app, SESSION_SERVER_FILE)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| auth::DEFAULT_SESSION_SERVER.to_string())
+}
+
+/// Overrides the session server; `None` goes back to Mojang's.
+pub fn set_session_server(app: &AppHandle, url: Option<String>) -> Result<(), String> {
+    let path = config_file(app, SESSION_SERVER_FILE).ok_or("Папка настроек недоступна")?;
+    match url.map(|url| url.trim().to_string()) {
+        Some(url) if !url.is_empty() => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(path, url).map_err(|e| e.to_string())
+        }
+        _ if path.exists() => std::fs::remove_file(path).map_err(|e| e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Options for a new session. A saved account gets a fresh access token first;
+/// if that fails the stored one is tried as is.
+pub async fn session_options(app: &AppHandle, log: &Logger<'_>) -> SessionOptions {
+    let Some(saved) = load(app) else {
+        return SessionOptions::default();
+    };
+    let account = match saved.refresh().await {
+        Ok(account) => {
+            if let Err(e) = save(app, &account) {
+                log.warn(format!("Не удалось сохранить аккаунт: {}", e));
+            }
+            account
+        }
+        Err(e) => {
+            log.warn(format!("Не удалось обновить токен: {:#}", e));
+            saved
+        }
+    };
+    SessionOptions {
+        auth: Some(UpstreamAuth {
+            account,
+            session_server: session_server(app),
+        }),
+        ..Default::default()
+    }
+}
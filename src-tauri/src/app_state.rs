@@ -2,10 +2,13 @@ use std::sync::Arc;
 
 use tokio::sync::Mutex;
 use tokio::task::AbortHandle;
+use voxelproxy_core::{controller::SessionControl, inspector::PacketInspector};
 
 pub struct AppState {
     pub session: Mutex<Option<AbortHandle>>,
     pub panic_mode: Arc<Mutex<bool>>,
+    pub control: SessionControl,
+    pub inspector: PacketInspector,
 }
 
 impl AppState {
@@ -13,6 +16,8 @@ impl AppState {
         Self {
             session: Mutex::new(None),
             panic_mode: Arc::new(Mutex::new(false)),
+            control: SessionControl::default(),
+            inspector: PacketInspector::default(),
         }
     }
 }
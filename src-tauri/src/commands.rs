@@ -1,16 +1,30 @@
-use serde::Serialize;
-use tauri::{AppHandle, State};
+use std::{net::SocketAddr, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
 use tauri_specta::Event;
+use voxelproxy_core::{
+    Sink, Version,
+    auth::DeviceLogin,
+    controller::{ControlMessage, RoutingPolicy},
+    inspector,
+    logger::Logger,
+    protocols::definitions,
+    proxy, session,
+};
 
 use crate::{
+    account,
     app_state::AppState,
     changelog::{self, ChangelogEntry},
-    config,
-    events::{SessionEndedEvent, SessionStartedEvent, UpdateProgressEvent},
-    logger::Logger,
-    prefs,
-    protocols::Version,
-    session,
+    config::{self, TelemetryAuditEntry, TelemetrySettings},
+    events::{
+        AccountEvent, AccountInfo, PacketDirection, SessionEndedEvent, SessionStartedEvent,
+        UpdateProgressEvent, WhichClient,
+    },
+    prefs::{self, PacketCategory, Profile, ProfileMode},
+    sink::AppSink,
+    telemetry::{self, Delivery, TelemetrySink},
     updater::has_update,
 };
 
@@ -20,50 +34,184 @@ pub struct UpdateInfo {
     pub link: String,
 }
 
+#[derive(Serialize, specta::Type)]
+pub struct DeviceCode {
+    pub user_code: String,
+    pub verification_uri: String,
+}
+
+/// Which packets the inspector reports; empty lists and `null` match all.
+#[derive(Deserialize, specta::Type)]
+pub struct InspectorFilter {
+    pub direction: Option<PacketDirection>,
+    pub client: Option<WhichClient>,
+    pub names: Vec<String>,
+    pub packet_ids: Vec<i32>,
+    pub max_per_second: u32,
+}
+
+impl From<InspectorFilter> for inspector::InspectorFilter {
+    fn from(filter: InspectorFilter) -> Self {
+        inspector::InspectorFilter {
+            direction: filter.direction.map(Into::into),
+            client: filter.client.map(Into::into),
+            states: Vec::new(),
+            packet_ids: filter.packet_ids,
+            names: filter.names,
+            max_per_second: filter.max_per_second,
+        }
+    }
+}
+
+fn routing_policy(routing: Vec<PacketCategory>) -> RoutingPolicy {
+    RoutingPolicy {
+        inactive: routing.into_iter().map(Into::into).collect(),
+    }
+}
+
 #[tauri::command]
 #[specta::specta]
 pub async fn start_manual_session(
     server_addr: String,
+    routing: Vec<PacketCategory>,
     app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
+    let bind = proxy::default_bind_addr();
+    spawn_manual_session(server_addr, bind, routing, app, &state).await;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn start_auto_session(
+    use_windivert: bool,
+    port_min: u16,
+    port_max: u16,
+    routing: Vec<PacketCategory>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let bind = proxy::default_bind_addr();
+    spawn_auto_session(
+        use_windivert,
+        port_min,
+        port_max,
+        bind,
+        routing,
+        app,
+        &state,
+    )
+    .await;
+    Ok(())
+}
+
+/// Starts a session with the settings of the saved profile `name` and
+/// remembers it as the one to auto-start.
+#[tauri::command]
+#[specta::specta]
+pub async fn start_profile(
+    name: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let profile = prefs::profile(&name).ok_or_else(|| format!("Профиль «{}» не найден", name))?;
+    // The same checks the start button makes, for a profile started on launch.
+    profile.validate()?;
+    if profile.mode == ProfileMode::Auto && profile.use_windivert && !is_elevated() {
+        return Err("Перехват трафика хотспота требует прав администратора".into());
+    }
+    let bind = profile
+        .bind_addr()?
+        .unwrap_or_else(proxy::default_bind_addr);
+    match profile.mode {
+        ProfileMode::Manual => {
+            spawn_manual_session(profile.server_addr, bind, profile.routing, app, &state).await
+        }
+        ProfileMode::Auto => {
+            spawn_auto_session(
+                profile.use_windivert,
+                profile.port_min,
+                profile.port_max,
+                bind,
+                profile.routing,
+                app,
+                &state,
+            )
+            .await
+        }
+    }
+    prefs::set_last_profile(&name)
+}
+
+async fn spawn_manual_session(
+    server_addr: String,
+    bind: SocketAddr,
+    routing: Vec<PacketCategory>,
+    app: AppHandle,
+    state: &State<'_, AppState>,
+) {
     tokio::spawn(config::send_start_manual(server_addr.clone()));
-    abort_existing(&state).await;
+    abort_existing(state).await;
 
     SessionStartedEvent {}.emit(&app).ok();
+    let control = state.control.clone();
+    let inspector = state.inspector.clone();
+    let routing = routing_policy(routing);
 
     let handle = tokio::spawn(async move {
-        let log = Logger::new(&app);
-        if let Err(e) = session::run_manual_mode(server_addr, app.clone()).await {
+        let sink: Sink = Arc::new(AppSink::new(app.clone()));
+        let log = Logger::new(&*sink);
+        load_protocol_definitions(&app, &log);
+        let mut options = account::session_options(&app, &log).await;
+        options.control = control;
+        options.routing = routing;
+        options.inspector = inspector;
+        if let Err(e) = session::run_manual_mode(server_addr, bind, sink.clone(), options).await {
             log.error(format!("{}", e));
         }
         SessionEndedEvent {}.emit(&app).ok();
     });
 
     *state.session.lock().await = Some(handle.abort_handle());
-    Ok(())
 }
 
-#[tauri::command]
-#[specta::specta]
-pub async fn start_auto_session(
+async fn spawn_auto_session(
     use_windivert: bool,
     port_min: u16,
     port_max: u16,
+    bind: SocketAddr,
+    routing: Vec<PacketCategory>,
     app: AppHandle,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
+    state: &State<'_, AppState>,
+) {
     tokio::spawn(config::send_start_auto(use_windivert));
-    abort_existing(&state).await;
+    abort_existing(state).await;
     let panic_mode = state.panic_mode.clone();
+    let control = state.control.clone();
+    let inspector = state.inspector.clone();
+    let routing = routing_policy(routing);
 
     SessionStartedEvent {}.emit(&app).ok();
 
     let handle = tokio::spawn(async move {
-        let log = Logger::new(&app);
-        if let Err(e) =
-            session::run_automatic_mode(use_windivert, port_min, port_max, app.clone(), panic_mode)
-                .await
+        let sink: Sink = Arc::new(AppSink::new(app.clone()));
+        let log = Logger::new(&*sink);
+        load_protocol_definitions(&app, &log);
+        let mut options = account::session_options(&app, &log).await;
+        options.control = control;
+        options.routing = routing;
+        options.inspector = inspector;
+        if let Err(e) = session::run_automatic_mode(
+            use_windivert,
+            port_min,
+            port_max,
+            bind,
+            sink.clone(),
+            panic_mode,
+            options,
+        )
+        .await
         {
             log.error(format!("{}", e));
         }
@@ -71,7 +219,14 @@ pub async fn start_auto_session(
     });
 
     *state.session.lock().await = Some(handle.abort_handle());
-    Ok(())
+}
+
+/// Picks up protocol definitions from `<app config dir>/versions` on every
+/// session start, so a file dropped in takes effect without a restart.
+pub fn load_protocol_definitions(app: &AppHandle, log: &Logger) {
+    if let Ok(dir) = app.path().app_config_dir() {
+        definitions::load_dir(&dir.join("versions"), log);
+    }
 }
 
 #[tauri::command]
@@ -82,6 +237,99 @@ pub async fn stop_session(app: AppHandle, state: State<'_, AppState>) -> Result<
     Ok(())
 }
 
+/// Hands control to `which` without disconnecting the other client.
+#[tauri::command]
+#[specta::specta]
+pub fn switch_active_client(which: WhichClient, state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .control
+        .send(ControlMessage::SwitchActive(which.into()))
+        .map_err(|e| e.to_string())
+}
+
+/// Disconnects one client; the session goes on with the other.
+#[tauri::command]
+#[specta::specta]
+pub fn kick_client(which: WhichClient, state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .control
+        .send(ControlMessage::Kick(which.into()))
+        .map_err(|e| e.to_string())
+}
+
+/// Starts reporting packets as `PacketInspectEvent`s, in the running session
+/// and the next ones, until `stop_inspector`.
+#[tauri::command]
+#[specta::specta]
+pub fn start_inspector(filter: InspectorFilter, state: State<'_, AppState>) {
+    state.inspector.start(filter.into());
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_inspector(state: State<'_, AppState>) {
+    state.inspector.stop();
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_account(app: AppHandle) -> Option<AccountInfo> {
+    account::load(&app).as_ref().map(AccountInfo::from)
+}
+
+/// Starts a Microsoft device-code login and returns the code to show. The
+/// login completes in the background and reports back with `AccountEvent`.
+#[tauri::command]
+#[specta::specta]
+pub async fn start_account_login(app: AppHandle) -> Result<DeviceCode, String> {
+    let login = DeviceLogin::start().await.map_err(|e| format!("{:#}", e))?;
+    let code = DeviceCode {
+        user_code: login.user_code.clone(),
+        verification_uri: login.verification_uri.clone(),
+    };
+
+    tokio::spawn(async move {
+        let sink = AppSink::new(app.clone());
+        let log = Logger::new(&sink);
+        match login.finish().await {
+            Ok(account) => {
+                if let Err(e) = account::save(&app, &account) {
+                    log.error(format!("Не удалось сохранить аккаунт: {}", e));
+                }
+                log.success(format!("Вход выполнен: {}", account.name));
+                AccountEvent(Some(AccountInfo::from(&account)))
+                    .emit(&app)
+                    .ok();
+            }
+            Err(e) => {
+                log.error(format!("Вход в аккаунт не удался: {:#}", e));
+                AccountEvent(None).emit(&app).ok();
+            }
+        }
+    });
+    Ok(code)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn logout_account(app: AppHandle) -> Result<(), String> {
+    account::remove(&app)?;
+    AccountEvent(None).emit(&app).ok();
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_session_server(app: AppHandle) -> String {
+    account::session_server(&app)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_session_server(url: Option<String>, app: AppHandle) -> Result<(), String> {
+    account::set_session_server(&app, url)
+}
+
 #[tauri::command]
 #[specta::specta]
 pub fn get_version() -> String {
@@ -92,9 +340,6 @@ pub fn get_version() -> String {
 #[specta::specta]
 pub fn get_supported_versions() -> Vec<String> {
     Version::supported_versions()
-        .iter()
-        .map(|s| s.to_string())
-        .collect()
 }
 
 #[tauri::command]
@@ -208,7 +453,7 @@ pub fn get_platform() -> String {
 pub fn is_elevated() -> bool {
     #[cfg(target_os = "windows")]
     {
-        crate::hotspot_redirect::is_admin()
+        voxelproxy_core::hotspot_redirect::is_admin()
     }
     #[cfg(not(target_os = "windows"))]
     {
@@ -318,13 +563,115 @@ pub fn acknowledge_manual_warning() -> Result<(), String> {
 
 #[tauri::command]
 #[specta::specta]
-pub async fn send_developer_message(message: String) -> Result<(), String> {
+pub fn list_profiles() -> Vec<Profile> {
+    prefs::profiles()
+}
+
+/// Adds `profile`, or replaces the saved one with the same name.
+#[tauri::command]
+#[specta::specta]
+pub fn save_profile(profile: Profile) -> Result<(), String> {
+    prefs::save_profile(profile)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn delete_profile(name: String) -> Result<(), String> {
+    prefs::delete_profile(&name)
+}
+
+/// All profiles as a JSON array, for `import_profiles` on another machine.
+#[tauri::command]
+#[specta::specta]
+pub fn export_profiles() -> Result<String, String> {
+    prefs::export_profiles()
+}
+
+/// Merges profiles from an exported JSON array; returns how many there were.
+#[tauri::command]
+#[specta::specta]
+pub fn import_profiles(json: String) -> Result<u32, String> {
+    prefs::import_profiles(&json)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_auto_start() -> bool {
+    prefs::auto_start()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_auto_start(value: bool) -> Result<(), String> {
+    prefs::set_auto_start(value)
+}
+
+/// The profile to start on launch, if auto-start is on.
+#[tauri::command]
+#[specta::specta]
+pub fn get_auto_start_profile() -> Option<Profile> {
+    prefs::auto_start_profile()
+}
+
+/// Waits until the message is sent, or queued because the sink is
+/// unreachable; an error if it was dropped.
+#[tauri::command]
+#[specta::specta]
+pub async fn send_developer_message(message: String) -> Result<Delivery, String> {
     let message = message.trim().to_string();
     if message.is_empty() {
         return Err("Сообщение не может быть пустым".into());
     }
-    config::send_developer_message(message).await;
-    Ok(())
+    match config::send_developer_message(message).await {
+        Delivery::Dropped => {
+            Err("Сообщение не принято: телеметрия отключена или сервер его отклонил".into())
+        }
+        delivery => Ok(delivery),
+    }
+}
+
+/// `None` until the user has answered the consent prompt.
+#[tauri::command]
+#[specta::specta]
+pub fn get_telemetry_settings() -> Option<TelemetrySettings> {
+    config::telemetry_settings()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_telemetry_settings(settings: TelemetrySettings) -> Result<(), String> {
+    config::set_telemetry_settings(settings)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_telemetry_audit() -> Result<Vec<TelemetryAuditEntry>, String> {
+    config::telemetry_audit()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn clear_telemetry_audit() -> Result<(), String> {
+    config::clear_telemetry_audit()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_telemetry_sink() -> TelemetrySink {
+    telemetry::sink()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_telemetry_sink(sink: TelemetrySink) -> Result<(), String> {
+    telemetry::set_sink(sink)
+}
+
+/// Requests waiting for the sink to come back.
+#[tauri::command]
+#[specta::specta]
+pub fn get_telemetry_queued() -> u32 {
+    telemetry::queued() as u32
 }
 
 async fn abort_existing(state: &State<'_, AppState>) {
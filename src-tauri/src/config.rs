@@ -1,28 +1,78 @@
-use std::sync::{Mutex, OnceLock};
-use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
 use uuid::Uuid;
 
+use crate::telemetry::{self, Delivery, Event};
+
 /// Generated gRPC types/client for the `worker.v1` service (see `proto/worker.proto`).
 pub mod pb {
     tonic::include_proto!("worker.v1");
 }
 
-use pb::worker_client::WorkerClient;
 use pb::*;
 
 static CONFIG: OnceLock<Mutex<Config>> = OnceLock::new();
-static CHANNEL: OnceLock<Option<Channel>> = OnceLock::new();
 
 pub fn get_config() -> &'static Mutex<Config> {
     CONFIG.get_or_init(|| Mutex::new(Config::load()))
 }
 
+const TELEMETRY_FILE: &str = "telemetry.json";
+const AUDIT_FILE: &str = "telemetry-audit.jsonl";
+/// Audit entries kept when the app starts and shown in the audit view.
+const AUDIT_LIMIT: usize = 500;
+
+/// How much the app reports to the telemetry worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum TelemetryLevel {
+    /// Nothing but messages the user sends to the developer.
+    Off,
+    /// App version, OS, session starts and the game version; no username,
+    /// server address or nickname.
+    Basic,
+    /// Everything `Basic` sends plus the username, server address and nickname.
+    Full,
+}
+
+/// The user's telemetry choice, saved in the app config dir.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct TelemetrySettings {
+    pub level: TelemetryLevel,
+    /// Whether chat messages and commands typed in the game are sent; only
+    /// honoured at [`TelemetryLevel::Full`].
+    pub include_chat: bool,
+}
+
+/// One request as it was sent to the worker, for the audit view.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct TelemetryAuditEntry {
+    /// Unix time in milliseconds.
+    pub time_ms: f64,
+    pub rpc: String,
+    /// The request message as JSON.
+    pub request: String,
+    /// Whether the sink took it; `false` if it was given up on.
+    pub delivered: bool,
+}
+
 pub struct Config {
     pub telemetry_url: &'static str,
     pub version: &'static str,
     pub os: &'static str,
     pub username: String,
     pub uuid: Uuid,
+    /// `None` until the user has made a choice; nothing is sent until then.
+    pub telemetry: Option<TelemetrySettings>,
+    settings_path: Option<PathBuf>,
+    audit_path: Option<PathBuf>,
 }
 
 impl Config {
@@ -35,6 +85,9 @@ impl Config {
                 .or_else(|_| std::env::var("USER"))
                 .unwrap_or_else(|_| "unknown".to_string()),
             uuid: Uuid::new_v4(),
+            telemetry: None,
+            settings_path: None,
+            audit_path: None,
         }
     }
 
@@ -45,59 +98,139 @@ impl Config {
     pub fn new_session(&mut self) {
         self.uuid = Uuid::new_v4();
     }
+
+    fn level(&self) -> TelemetryLevel {
+        self.telemetry.map_or(TelemetryLevel::Off, |t| t.level)
+    }
 }
 
-/// Lazily-initialised, process-wide gRPC channel to the telemetry worker.
-///
-/// `connect_lazy` never blocks: the TCP/HTTP2 connection is established on the
-/// first RPC and transparently re-established on failure. Returns `None` when
-/// telemetry is disabled (debug builds / empty URL) or the URL is invalid, so
-/// every caller becomes a cheap no-op in those cases.
-///
-/// TLS is configured with the bundled webpki roots, which is what lets the
-/// `https://` endpoint connect at all — without it tonic refuses the https
-/// scheme and every RPC fails silently. Plaintext `http://` URLs ignore the
-/// TLS config and connect in cleartext (useful for local dev).
-fn channel() -> Option<Channel> {
-    CHANNEL
-        .get_or_init(|| {
-            let cfg = get_config().lock().unwrap();
-            if !cfg.should_send() {
-                return None;
-            }
-            let endpoint = match Endpoint::from_shared(cfg.telemetry_url.to_string()) {
-                Ok(endpoint) => endpoint,
-                Err(e) => {
-                    eprintln!(
-                        "telemetry: invalid TELEMETRY_URL {:?}: {e}",
-                        cfg.telemetry_url
-                    );
-                    return None;
-                }
-            };
-            let endpoint = match endpoint.tls_config(ClientTlsConfig::new().with_webpki_roots()) {
-                Ok(endpoint) => endpoint,
-                Err(e) => {
-                    eprintln!("telemetry: failed to configure TLS: {e}");
-                    return None;
-                }
-            };
-            Some(endpoint.connect_lazy())
-        })
+/// Loads the saved telemetry choice and points the audit log at the app log
+/// dir, trimming it to the last [`AUDIT_LIMIT`] entries. Call once at startup,
+/// before anything is sent.
+pub fn init(app: &AppHandle) {
+    let settings_path = app
+        .path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(TELEMETRY_FILE));
+    let audit_path = app
+        .path()
+        .app_log_dir()
+        .ok()
+        .map(|dir| dir.join(AUDIT_FILE));
+
+    let mut cfg = get_config().lock().unwrap();
+    cfg.telemetry = settings_path
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok());
+    if let Some(path) = &audit_path
+        && let Ok(text) = std::fs::read_to_string(path)
+    {
+        let lines: Vec<&str> = text.lines().collect();
+        if lines.len() > AUDIT_LIMIT {
+            let kept = lines[lines.len() - AUDIT_LIMIT..].join("\n") + "\n";
+            std::fs::write(path, kept).ok();
+        }
+    }
+    cfg.settings_path = settings_path;
+    cfg.audit_path = audit_path;
+}
+
+pub fn telemetry_settings() -> Option<TelemetrySettings> {
+    get_config().lock().unwrap().telemetry
+}
+
+/// Saves the user's choice; it applies to everything sent from now on.
+pub fn set_telemetry_settings(settings: TelemetrySettings) -> Result<(), String> {
+    let mut cfg = get_config().lock().unwrap();
+    let path = cfg
+        .settings_path
         .clone()
+        .ok_or("Папка настроек недоступна")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())?;
+    cfg.telemetry = Some(settings);
+    Ok(())
 }
 
-/// The shared singleton client. Clone-cheap; call from anywhere.
-fn client() -> Option<WorkerClient<Channel>> {
-    channel().map(WorkerClient::new)
+/// The last [`AUDIT_LIMIT`] requests sent, oldest first.
+pub fn telemetry_audit() -> Result<Vec<TelemetryAuditEntry>, String> {
+    let Some(path) = get_config().lock().unwrap().audit_path.clone() else {
+        return Ok(Vec::new());
+    };
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let entries: Vec<TelemetryAuditEntry> = text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = entries.len().saturating_sub(AUDIT_LIMIT);
+    Ok(entries.into_iter().skip(skip).collect())
 }
 
+pub fn clear_telemetry_audit() -> Result<(), String> {
+    match get_config().lock().unwrap().audit_path.clone() {
+        Some(path) if path.exists() => std::fs::remove_file(path).map_err(|e| e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Appends a request to the audit log.
+pub(crate) fn audit(rpc: &str, request: String, delivered: bool) {
+    let Some(path) = get_config().lock().unwrap().audit_path.clone() else {
+        return;
+    };
+    let entry = TelemetryAuditEntry {
+        time_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as f64,
+        rpc: rpc.to_string(),
+        request,
+        delivered,
+    };
+    let Ok(line) = serde_json::to_string(&entry) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).ok();
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        writeln!(file, "{}", line).ok();
+    }
+}
+
+fn level() -> TelemetryLevel {
+    get_config().lock().unwrap().level()
+}
+
+/// Who is sending; the username only goes out at [`TelemetryLevel::Full`].
 fn client_info() -> ClientInfo {
     let cfg = get_config().lock().unwrap();
     ClientInfo {
         version: cfg.version.to_string(),
         os: cfg.os.to_string(),
-        username: cfg.username.clone(),
+        username: if cfg.level() == TelemetryLevel::Full {
+            cfg.username.clone()
+        } else {
+            String::new()
+        },
+    }
+}
+
+/// `value` at [`TelemetryLevel::Full`], blank below it.
+fn identifying(value: String) -> String {
+    if level() == TelemetryLevel::Full {
+        value
+    } else {
+        String::new()
     }
 }
 
@@ -106,64 +239,73 @@ fn session_id() -> String {
 }
 
 pub async fn send_startup_ping() {
-    let Some(mut client) = client() else { return };
-    let _ = client
-        .ping(PingRequest {
-            client: Some(client_info()),
-        })
-        .await;
+    if level() == TelemetryLevel::Off {
+        return;
+    }
+    let request = PingRequest {
+        client: Some(client_info()),
+    };
+    telemetry::submit(Event::Ping(request)).await;
 }
 
 pub async fn send_start_manual(server_addr: String) {
-    let Some(mut client) = client() else { return };
-    let _ = client
-        .manual_start(ManualStartRequest {
-            client: Some(client_info()),
-            server_addr,
-        })
-        .await;
+    if level() == TelemetryLevel::Off {
+        return;
+    }
+    let request = ManualStartRequest {
+        client: Some(client_info()),
+        server_addr: identifying(server_addr),
+    };
+    telemetry::submit(Event::ManualStart(request)).await;
 }
 
 pub async fn send_start_auto(windivert: bool) {
-    let Some(mut client) = client() else { return };
-    let _ = client
-        .auto_start(AutoStartRequest {
-            client: Some(client_info()),
-            use_windivert: windivert,
-        })
-        .await;
+    if level() == TelemetryLevel::Off {
+        return;
+    }
+    let request = AutoStartRequest {
+        client: Some(client_info()),
+        use_windivert: windivert,
+    };
+    telemetry::submit(Event::AutoStart(request)).await;
 }
 
 pub async fn send_join(server_addr: String, nickname: String, protocol_version: i32) {
-    let Some(mut client) = client() else { return };
-    let _ = client
-        .join(JoinRequest {
-            client: Some(client_info()),
-            session_id: session_id(),
-            server_addr,
-            nickname,
-            protocol_version,
-        })
-        .await;
+    if level() == TelemetryLevel::Off {
+        return;
+    }
+    let request = JoinRequest {
+        client: Some(client_info()),
+        session_id: session_id(),
+        server_addr: identifying(server_addr),
+        nickname: identifying(nickname),
+        protocol_version,
+    };
+    telemetry::submit(Event::Join(request)).await;
 }
 
+/// Chat messages and commands typed in the game; sent only at
+/// [`TelemetryLevel::Full`] with [`TelemetrySettings::include_chat`].
 pub async fn send_protocol_metadata(data: String, custom: bool) {
-    let Some(mut client) = client() else { return };
-    let _ = client
-        .send_protocol_metadata(SendProtocolMetadataRequest {
-            session_id: session_id(),
-            payload: data,
-            custom,
-        })
-        .await;
-}
-
-pub async fn send_developer_message(message: String) {
-    let Some(mut client) = client() else { return };
-    let _ = client
-        .send_developer_message(SendDeveloperMessageRequest {
-            client: Some(client_info()),
-            message,
-        })
-        .await;
+    let allowed =
+        telemetry_settings().is_some_and(|t| t.level == TelemetryLevel::Full && t.include_chat);
+    if !allowed {
+        return;
+    }
+    let request = SendProtocolMetadataRequest {
+        session_id: session_id(),
+        payload: data,
+        custom,
+    };
+    telemetry::submit(Event::ProtocolMetadata(request)).await;
+}
+
+/// Sent at every level, since the user asks for it; below
+/// [`TelemetryLevel::Full`] without the username.
+pub async fn send_developer_message(message: String) -> Delivery {
+    let request = SendDeveloperMessageRequest {
+        client: Some(client_info()),
+        message,
+    };
+    telemetry::submit(Event::DeveloperMessage(request)).await
 }
@@ -1,292 +0,0 @@
-use mc_protocol::packet::RawPacket;
-use tauri::AppHandle;
-use tauri_specta::Event;
-use tokio::{
-    net::tcp::{OwnedReadHalf, OwnedWriteHalf},
-    sync::mpsc::{Receiver, Sender},
-};
-
-use crate::{
-    events::{ClientStatusEvent, WhichClient},
-    logger::Logger,
-    protocols::{ServerBoundEvent, Version, VersionProtocol},
-};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ClientId {
-    Primary,
-    Secondary,
-}
-
-impl ClientId {
-    pub fn opposite(&self) -> ClientId {
-        match self {
-            ClientId::Primary => ClientId::Secondary,
-            ClientId::Secondary => ClientId::Primary,
-        }
-    }
-}
-
-#[derive(Debug)]
-pub enum ControllerEvent {
-    ClientData(ClientId, RawPacket),
-    ClientDisconnected(ClientId),
-    ServerData(RawPacket),
-    ServerDisconnected,
-}
-
-pub struct Controller {
-    active_client: ClientId,
-    /// Channel for sending packets to the Primary client.
-    primary_tx: Sender<RawPacket>,
-    /// Channel for sending packets to the Secondary client.
-    secondary_tx: Sender<RawPacket>,
-    /// Channel for sending packets to the upstream server.
-    remote_tx: Sender<RawPacket>,
-    /// Receives events (client data, disconnections, server data) from I/O tasks.
-    event_rx: Receiver<ControllerEvent>,
-    primary_active: bool,
-    secondary_active: bool,
-    version: Version,
-    app: AppHandle,
-}
-
-impl Controller {
-    pub fn new(
-        active_client: ClientId,
-        primary_tx: Sender<RawPacket>,
-        secondary_tx: Sender<RawPacket>,
-        remote_tx: Sender<RawPacket>,
-        event_rx: Receiver<ControllerEvent>,
-        version: Version,
-        app: AppHandle,
-    ) -> Self {
-        Self {
-            active_client,
-            primary_tx,
-            secondary_tx,
-            remote_tx,
-            event_rx,
-            primary_active: true,
-            secondary_active: true,
-            version,
-            app,
-        }
-    }
-
-    /// Main event loop. Runs until the channel closes (both I/O tasks have exited).
-    ///
-    /// Each iteration handles one of three event types:
-    /// - `ClientData`         — position sync, ping tracking, relay to server
-    /// - `ClientDisconnected` — update state, optionally switch active client & replay sync packets
-    /// - `ServerData`         — track new pings, broadcast to active clients
-    pub async fn run(mut self) {
-        let log = Logger::new(&self.app);
-        while let Some(event) = self.event_rx.recv().await {
-            match event {
-                ControllerEvent::ClientData(client_id, packet) => {
-                    let event = self.version.handle_c2s(
-                        &packet,
-                        client_id,
-                        client_id == self.active_client,
-                        self.both_active(),
-                    );
-
-                    let mut skip = false;
-
-                    if let Some(event) = event {
-                        match event {
-                            ServerBoundEvent::SendToInactive(raw_packet) => {
-                                if self.both_active() {
-                                    match self.active_client.opposite() {
-                                        ClientId::Primary => {
-                                            self.primary_tx.send(raw_packet).await.ok();
-                                        }
-                                        ClientId::Secondary => {
-                                            self.secondary_tx.send(raw_packet).await.ok();
-                                        }
-                                    }
-                                }
-                            }
-                            ServerBoundEvent::SkipRelay => skip = true,
-                        }
-                    }
-
-                    if skip {
-                        continue;
-                    }
-
-                    if client_id == self.active_client {
-                        if let Err(e) = self.remote_tx.send(packet).await {
-                            log.error(format!("Ошибка отправки пакета на сервер: {}", e));
-                            return;
-                        }
-                    }
-                }
-
-                ControllerEvent::ClientDisconnected(client_id) => {
-                    match client_id {
-                        ClientId::Primary => {
-                            self.primary_active = false;
-                            ClientStatusEvent {
-                                which: WhichClient::Primary,
-                                online: false,
-                            }
-                            .emit(&self.app)
-                            .ok();
-                        }
-                        ClientId::Secondary => {
-                            self.secondary_active = false;
-                            ClientStatusEvent {
-                                which: WhichClient::Secondary,
-                                online: false,
-                            }
-                            .emit(&self.app)
-                            .ok();
-                        }
-                    }
-
-                    if !(self.primary_active || self.secondary_active) {
-                        return;
-                    }
-
-                    if self.active_client == client_id {
-                        self.active_client = client_id.opposite();
-                        log.info(format!(
-                            "Активный клиент отключился, управление передано: {}",
-                            match self.active_client {
-                                ClientId::Primary => "основной",
-                                ClientId::Secondary => "дополнительный",
-                            }
-                        ));
-
-                        if let Some(event) =
-                            self.version.handle_client_disconnect(self.active_client)
-                        {
-                            match event {
-                                crate::protocols::ClientDisconnectEvent::SendToServer(packets) => {
-                                    for packet in packets {
-                                        self.remote_tx.send(packet).await.unwrap();
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                ControllerEvent::ServerData(packet) => {
-                    if let Some(event) = self.version.handle_s2c(&packet, self.both_active()) {
-                        match event {}
-                    }
-
-                    if self.primary_active {
-                        let _ = self.primary_tx.send(packet.clone()).await;
-                    }
-                    if self.secondary_active {
-                        let _ = self.secondary_tx.send(packet).await;
-                    }
-                }
-
-                // The upstream server is the session's lifeline: without it the proxy has
-                // nothing to relay, so end the session. `run_proxy_session` then aborts the
-                // client I/O tasks, closing their sockets, and `ClientStatusOfflineGuard`
-                // marks both clients offline. This is what tears down a session whose active
-                // client went half-open (no FIN) and therefore never produced a
-                // `ClientDisconnected` of its own.
-                ControllerEvent::ServerDisconnected => return,
-            }
-        }
-    }
-
-    fn both_active(&self) -> bool {
-        self.primary_active && self.secondary_active
-    }
-}
-
-/// Drives a single client connection using two concurrent tasks:
-/// - **Read task**: reads packets from the TCP socket and sends them to the Controller
-///   as `Event::ClientData`; sends `Event::ClientDisconnected` on any read error.
-/// - **Write task**: receives packets from the Controller via `packet_rx` and writes
-///   them to the TCP socket; exits silently on write error.
-pub async fn run_client(
-    read_half: OwnedReadHalf,
-    write_half: OwnedWriteHalf,
-    client_id: ClientId,
-    event_tx: Sender<ControllerEvent>,
-    mut packet_rx: Receiver<RawPacket>,
-) {
-    let (mut client_read, mut client_write) = (read_half, write_half);
-    let _ = tokio::join!(
-        async move {
-            loop {
-                match RawPacket::read_async(&mut client_read).await {
-                    Ok(packet) => {
-                        if event_tx
-                            .send(ControllerEvent::ClientData(client_id, packet))
-                            .await
-                            .is_err()
-                        {
-                            break;
-                        }
-                    }
-                    Err(_) => {
-                        event_tx
-                            .send(ControllerEvent::ClientDisconnected(client_id))
-                            .await
-                            .ok();
-                        break;
-                    }
-                }
-            }
-        },
-        async move {
-            while let Some(packet) = packet_rx.recv().await {
-                if packet.write_async(&mut client_write).await.is_err() {
-                    break;
-                }
-            }
-        }
-    );
-}
-
-/// Drives the upstream server connection using two concurrent tasks:
-/// - **Read task**: reads packets from the server and sends them to the Controller
-///   as `Event::ServerData`; exits on read error.
-/// - **Write task**: receives packets from the Controller via `packet_rx` and writes
-///   them to the server socket; exits silently on write error.
-pub async fn run_server(
-    read_half: tokio::net::tcp::OwnedReadHalf,
-    write_half: tokio::net::tcp::OwnedWriteHalf,
-    event_tx: Sender<ControllerEvent>,
-    mut packet_rx: Receiver<RawPacket>,
-) {
-    let (mut server_read, mut server_write) = (read_half, write_half);
-    let _ = tokio::join!(
-        async move {
-            loop {
-                match RawPacket::read_async(&mut server_read).await {
-                    Ok(packet) => {
-                        if event_tx
-                            .send(ControllerEvent::ServerData(packet))
-                            .await
-                            .is_err()
-                        {
-                            break;
-                        }
-                    }
-                    Err(_) => {
-                        event_tx.send(ControllerEvent::ServerDisconnected).await.ok();
-                        break;
-                    }
-                }
-            }
-        },
-        async move {
-            while let Some(packet) = packet_rx.recv().await {
-                if packet.write_async(&mut server_write).await.is_err() {
-                    break;
-                }
-            }
-        }
-    );
-}
@@ -1,6 +1,16 @@
-use serde::Serialize;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
 use specta::Type;
 use tauri_specta::Event;
+use voxelproxy_core::{
+    ClientId,
+    capture::Direction,
+    inspector::{Outcome, PacketEvent},
+    latency::Latency,
+    metrics::{Counter, SessionStats, Traffic},
+    protocols::State,
+};
 
 #[derive(Serialize, Clone, Type, Event)]
 pub enum LogLevel {
@@ -22,7 +32,7 @@ pub struct SessionStartedEvent;
 #[derive(Serialize, Clone, Type, Event)]
 pub struct SessionEndedEvent;
 
-#[derive(Serialize, Clone, Type, Event)]
+#[derive(Serialize, Deserialize, Clone, Copy, Type, Event)]
 pub enum WhichClient {
     Primary,
     Secondary,
@@ -34,14 +44,228 @@ pub struct ClientStatusEvent {
     pub online: bool,
 }
 
+/// The client whose input reaches the server changed.
+#[derive(Serialize, Clone, Type, Event)]
+pub struct ActiveClientEvent {
+    pub which: WhichClient,
+}
+
 #[derive(Serialize, Clone, Type, Event)]
 pub struct NickNameEvent(pub String);
 
 #[derive(Serialize, Clone, Type, Event)]
 pub struct ServerAddrEvent(pub String);
 
+#[derive(Serialize, Clone, Type)]
+pub struct AccountInfo {
+    pub name: String,
+    pub uuid: String,
+}
+
+/// The signed-in account changed; `None` after a logout or a failed login.
+#[derive(Serialize, Clone, Type, Event)]
+pub struct AccountEvent(pub Option<AccountInfo>);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Type)]
+pub enum PacketDirection {
+    Serverbound,
+    Clientbound,
+}
+
+#[derive(Serialize, Clone, Copy, Type)]
+pub enum ProtocolState {
+    Login,
+    Configuration,
+    Game,
+}
+
+/// What the session did with an inspected packet.
+#[derive(Serialize, Clone, Copy, Type)]
+pub enum PacketOutcome {
+    Relayed,
+    Mirrored,
+    Skipped,
+    Dropped,
+}
+
+/// A packet the inspector let through. `client` is `None` for the server's.
+#[derive(Serialize, Clone, Type, Event)]
+pub struct PacketInspectEvent {
+    pub direction: PacketDirection,
+    pub client: Option<WhichClient>,
+    pub state: ProtocolState,
+    pub packet_id: i32,
+    pub name: Option<String>,
+    pub size: u32,
+    pub outcome: PacketOutcome,
+    /// Packets the rate limit dropped since the previous event.
+    pub suppressed: u32,
+}
+
+#[derive(Serialize, Clone, Copy, Type)]
+pub struct CounterStats {
+    pub packets: f64,
+    pub bytes: f64,
+}
+
+/// A peer's traffic: for a client, `serverbound` is what it sent; for the
+/// server, what the proxy sent it.
+#[derive(Serialize, Clone, Copy, Type)]
+pub struct TrafficStats {
+    pub serverbound: CounterStats,
+    pub clientbound: CounterStats,
+}
+
+#[derive(Serialize, Clone, Copy, Type)]
+pub struct QueueStats {
+    pub events: u32,
+    pub primary: u32,
+    pub secondary: u32,
+    pub server: u32,
+}
+
+/// Periodic snapshot of the running session.
+#[derive(Serialize, Clone, Type, Event)]
+pub struct SessionStatsEvent {
+    pub uptime_secs: f64,
+    pub primary: TrafficStats,
+    pub secondary: TrafficStats,
+    pub server: TrafficStats,
+    pub queues: QueueStats,
+    pub handovers: u32,
+    pub pending_pings: u32,
+    pub latency: LatencyEvent,
+}
+
+/// Round-trip time of each link in milliseconds; `None` until measured.
+#[derive(Serialize, Clone, Copy, Type, Event)]
+pub struct LatencyEvent {
+    pub upstream_ms: Option<f64>,
+    pub primary_ms: Option<f64>,
+    pub secondary_ms: Option<f64>,
+}
+
 #[derive(Serialize, Clone, Type, Event)]
 pub struct UpdateProgressEvent {
     pub downloaded: u32,
     pub total: u32,
 }
+
+impl From<voxelproxy_core::LogLevel> for LogLevel {
+    fn from(level: voxelproxy_core::LogLevel) -> Self {
+        match level {
+            voxelproxy_core::LogLevel::Info => LogLevel::Info,
+            voxelproxy_core::LogLevel::Success => LogLevel::Success,
+            voxelproxy_core::LogLevel::Warn => LogLevel::Warn,
+            voxelproxy_core::LogLevel::Error => LogLevel::Error,
+        }
+    }
+}
+
+impl From<PacketEvent> for PacketInspectEvent {
+    fn from(event: PacketEvent) -> Self {
+        PacketInspectEvent {
+            direction: event.direction.into(),
+            client: event.client.map(Into::into),
+            state: match event.state {
+                State::Login => ProtocolState::Login,
+                State::Configuration => ProtocolState::Configuration,
+                State::Game => ProtocolState::Game,
+            },
+            packet_id: event.packet_id,
+            name: event.name.map(str::to_string),
+            size: event.size as u32,
+            outcome: match event.outcome {
+                Outcome::Relayed => PacketOutcome::Relayed,
+                Outcome::Mirrored => PacketOutcome::Mirrored,
+                Outcome::Skipped => PacketOutcome::Skipped,
+                Outcome::Dropped => PacketOutcome::Dropped,
+            },
+            suppressed: event.suppressed,
+        }
+    }
+}
+
+impl From<SessionStats> for SessionStatsEvent {
+    fn from(stats: SessionStats) -> Self {
+        let counter = |c: Counter| CounterStats {
+            packets: c.packets as f64,
+            bytes: c.bytes as f64,
+        };
+        let traffic = |t: Traffic| TrafficStats {
+            serverbound: counter(t.serverbound),
+            clientbound: counter(t.clientbound),
+        };
+        SessionStatsEvent {
+            uptime_secs: stats.uptime.as_secs_f64(),
+            primary: traffic(stats.primary),
+            secondary: traffic(stats.secondary),
+            server: traffic(stats.server),
+            queues: QueueStats {
+                events: stats.queues.events as u32,
+                primary: stats.queues.primary as u32,
+                secondary: stats.queues.secondary as u32,
+                server: stats.queues.server as u32,
+            },
+            handovers: stats.handovers as u32,
+            pending_pings: stats.pending_pings as u32,
+            latency: stats.latency.into(),
+        }
+    }
+}
+
+impl From<Latency> for LatencyEvent {
+    fn from(latency: Latency) -> Self {
+        let millis = |rtt: Option<Duration>| rtt.map(|rtt| rtt.as_secs_f64() * 1000.0);
+        LatencyEvent {
+            upstream_ms: millis(latency.upstream),
+            primary_ms: millis(latency.primary),
+            secondary_ms: millis(latency.secondary),
+        }
+    }
+}
+
+impl From<Direction> for PacketDirection {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Serverbound => PacketDirection::Serverbound,
+            Direction::Clientbound => PacketDirection::Clientbound,
+        }
+    }
+}
+
+impl From<PacketDirection> for Direction {
+    fn from(direction: PacketDirection) -> Self {
+        match direction {
+            PacketDirection::Serverbound => Direction::Serverbound,
+            PacketDirection::Clientbound => Direction::Clientbound,
+        }
+    }
+}
+
+impl From<&voxelproxy_core::auth::Account> for AccountInfo {
+    fn from(account: &voxelproxy_core::auth::Account) -> Self {
+        AccountInfo {
+            name: account.name.clone(),
+            uuid: account.uuid.to_string(),
+        }
+    }
+}
+
+impl From<ClientId> for WhichClient {
+    fn from(client: ClientId) -> Self {
+        match client {
+            ClientId::Primary => WhichClient::Primary,
+            ClientId::Secondary => WhichClient::Secondary,
+        }
+    }
+}
+
+impl From<WhichClient> for ClientId {
+    fn from(client: WhichClient) -> Self {
+        match client {
+            WhichClient::Primary => ClientId::Primary,
+            WhichClient::Secondary => ClientId::Secondary,
+        }
+    }
+}
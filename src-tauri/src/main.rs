@@ -2,23 +2,16 @@
 
 use tauri_specta::{Builder, collect_commands, collect_events};
 
+pub mod account;
 pub mod app_state;
 pub mod changelog;
 pub mod commands;
 pub mod config;
-pub mod controller;
 pub mod events;
-#[cfg(target_os = "windows")]
-pub mod hotspot_redirect;
 pub mod local_ip;
-pub mod logger;
-#[allow(dead_code)]
-pub mod packets;
 pub mod prefs;
-pub mod protocols;
-pub mod proxy;
-pub mod resolver;
-pub mod session;
+pub mod sink;
+pub mod telemetry;
 pub mod updater;
 
 fn create_builder() -> Builder<tauri::Wry> {
@@ -26,7 +19,12 @@ fn create_builder() -> Builder<tauri::Wry> {
         .commands(collect_commands![
             commands::start_manual_session,
             commands::start_auto_session,
+            commands::start_profile,
             commands::stop_session,
+            commands::switch_active_client,
+            commands::kick_client,
+            commands::start_inspector,
+            commands::stop_inspector,
             commands::get_version,
             commands::get_supported_versions,
             commands::get_local_ip_addr,
@@ -41,16 +39,41 @@ fn create_builder() -> Builder<tauri::Wry> {
             commands::acknowledge_changelog,
             commands::get_manual_warning_acknowledged,
             commands::acknowledge_manual_warning,
-            commands::send_developer_message
+            commands::list_profiles,
+            commands::save_profile,
+            commands::delete_profile,
+            commands::export_profiles,
+            commands::import_profiles,
+            commands::get_auto_start,
+            commands::set_auto_start,
+            commands::get_auto_start_profile,
+            commands::send_developer_message,
+            commands::get_telemetry_settings,
+            commands::set_telemetry_settings,
+            commands::get_telemetry_audit,
+            commands::clear_telemetry_audit,
+            commands::get_telemetry_sink,
+            commands::set_telemetry_sink,
+            commands::get_telemetry_queued,
+            commands::get_account,
+            commands::start_account_login,
+            commands::logout_account,
+            commands::get_session_server,
+            commands::set_session_server
         ])
         .events(collect_events![
             events::ProxyLogEvent,
             events::SessionStartedEvent,
             events::SessionEndedEvent,
             events::ClientStatusEvent,
+            events::ActiveClientEvent,
             events::NickNameEvent,
             events::ServerAddrEvent,
             events::UpdateProgressEvent,
+            events::AccountEvent,
+            events::PacketInspectEvent,
+            events::SessionStatsEvent,
+            events::LatencyEvent,
         ])
 }
 
@@ -61,6 +84,15 @@ pub fn main() {
     tauri::Builder::default()
         .setup(move |app| {
             builder.mount_events(app);
+            // So `get_supported_versions` lists them before the first session.
+            let sink = sink::AppSink::new(app.handle().clone());
+            commands::load_protocol_definitions(
+                app.handle(),
+                &voxelproxy_core::logger::Logger::new(&sink),
+            );
+            prefs::init(app.handle());
+            config::init(app.handle());
+            tauri::async_runtime::spawn(telemetry::init(app.handle()));
             tauri::async_runtime::spawn(config::send_startup_ping());
             Ok(())
         })
@@ -1,67 +1,383 @@
-#[cfg(windows)]
-const SUBKEY: &str = "Software\\VoxelProxy";
-#[cfg(windows)]
-const MANUAL_WARNING_VALUE: &str = "ManualWarningAcknowledged";
-#[cfg(windows)]
-const LAST_SEEN_VERSION_VALUE: &str = "LastSeenVersion";
+//! Preferences in `prefs.json` in the app config dir, on every platform.
+//! Windows builds before it kept them in the registry; those values are
+//! picked up the first time the file is missing.
 
-#[cfg(windows)]
-pub fn manual_warning_acknowledged() -> bool {
-    use winreg::RegKey;
-    use winreg::enums::HKEY_CURRENT_USER;
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let Ok(key) = hkcu.open_subkey(SUBKEY) else {
-        return false;
+use std::{
+    io::ErrorKind,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use voxelproxy_core::protocols::table;
+
+const PREFS_FILE: &str = "prefs.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Prefs {
+    manual_warning_acknowledged: bool,
+    last_seen_version: Option<String>,
+    profiles: Vec<Profile>,
+    /// Name of the profile started last.
+    last_profile: Option<String>,
+    /// Start the last profile when the app launches.
+    auto_start: bool,
+}
+
+/// How a profile's session finds the clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum ProfileMode {
+    Manual,
+    Auto,
+}
+
+/// A named set of session settings.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct Profile {
+    pub name: String,
+    pub mode: ProfileMode,
+    /// Server for [`ProfileMode::Manual`].
+    #[serde(default)]
+    pub server_addr: String,
+    /// [`ProfileMode::Auto`]: intercept the hotspot's traffic with WinDivert
+    /// on the ports from `port_min` to `port_max`.
+    #[serde(default)]
+    pub use_windivert: bool,
+    #[serde(default = "default_port_min")]
+    pub port_min: u16,
+    #[serde(default = "default_port_max")]
+    pub port_max: u16,
+    /// Where the proxy listens; `0.0.0.0:25565` when `None`.
+    #[serde(default)]
+    pub bind_addr: Option<String>,
+    /// What the inactive client may send to the server too.
+    #[serde(default)]
+    pub routing: Vec<PacketCategory>,
+}
+
+/// A category of the inactive client's packets the user let through to the
+/// server.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, specta::Type)]
+pub enum PacketCategory {
+    Chat,
+    Commands,
+    Movement,
+    Combat,
+    Interaction,
+    Inventory,
+}
+
+impl From<PacketCategory> for table::PacketCategory {
+    fn from(category: PacketCategory) -> Self {
+        match category {
+            PacketCategory::Chat => table::PacketCategory::Chat,
+            PacketCategory::Commands => table::PacketCategory::Commands,
+            PacketCategory::Movement => table::PacketCategory::Movement,
+            PacketCategory::Combat => table::PacketCategory::Combat,
+            PacketCategory::Interaction => table::PacketCategory::Interaction,
+            PacketCategory::Inventory => table::PacketCategory::Inventory,
+        }
+    }
+}
+
+fn default_port_min() -> u16 {
+    25560
+}
+
+fn default_port_max() -> u16 {
+    25570
+}
+
+impl Profile {
+    /// The listen address, if the profile sets a valid one.
+    pub fn bind_addr(&self) -> Result<Option<SocketAddr>, String> {
+        match self.bind_addr.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(addr) => addr
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("Неверный адрес для прослушивания: {}", addr)),
+        }
+    }
+
+    /// Whether the profile can be saved and started.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Укажите название профиля".into());
+        }
+        if self.mode == ProfileMode::Manual && self.server_addr.trim().is_empty() {
+            return Err(format!("Профиль «{}»: не указан сервер", self.name));
+        }
+        if self.port_min == 0 || self.port_min > self.port_max {
+            return Err(format!(
+                "Профиль «{}»: неверный диапазон портов (1-65535, min ≤ max)",
+                self.name
+            ));
+        }
+        self.bind_addr().map(drop)
+    }
+}
+
+struct Store {
+    path: Option<PathBuf>,
+    prefs: Prefs,
+}
+
+static STORE: Mutex<Store> = Mutex::new(Store {
+    path: None,
+    prefs: Prefs {
+        manual_warning_acknowledged: false,
+        last_seen_version: None,
+        profiles: Vec::new(),
+        last_profile: None,
+        auto_start: false,
+    },
+});
+
+/// Loads the prefs. Call once at startup, before anything reads them.
+pub fn init(app: &AppHandle) {
+    let path = app
+        .path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(PREFS_FILE));
+    let (path, prefs) = match path {
+        Some(path) => match load(&path) {
+            Some(prefs) => (Some(path), prefs),
+            None => (None, Prefs::default()),
+        },
+        None => (None, registry::load()),
     };
-    let val: Result<u32, _> = key.get_value(MANUAL_WARNING_VALUE);
-    matches!(val, Ok(v) if v != 0)
+    let mut store = STORE.lock().unwrap();
+    store.path = path;
+    store.prefs = prefs;
 }
 
-#[cfg(windows)]
-pub fn acknowledge_manual_warning() -> Result<(), String> {
-    use winreg::RegKey;
-    use winreg::enums::HKEY_CURRENT_USER;
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey(SUBKEY).map_err(|e| e.to_string())?;
-    key.set_value(MANUAL_WARNING_VALUE, &1u32)
-        .map_err(|e| e.to_string())
+/// Reads the prefs from `path`, or from the registry if there's no file yet.
+/// A file that doesn't parse is moved to `prefs.json.bak` and the defaults
+/// are used. `None` if the file can't be read or moved, so it's never
+/// written over.
+fn load(path: &Path) -> Option<Prefs> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Some(registry::load()),
+        Err(e) => {
+            eprintln!("prefs: can't read {:?} ({e}), not saving", path);
+            return None;
+        }
+    };
+    let error = match serde_json::from_slice(&bytes) {
+        Ok(prefs) => return Some(prefs),
+        Err(e) => e,
+    };
+    let backup = path.with_extension("json.bak");
+    match std::fs::rename(path, &backup) {
+        Ok(()) => {
+            eprintln!(
+                "prefs: {:?} is corrupt ({error}), moved to {:?}",
+                path, backup
+            );
+            Some(Prefs::default())
+        }
+        Err(e) => {
+            eprintln!(
+                "prefs: {:?} is corrupt ({error}) and can't be moved ({e}), not saving",
+                path
+            );
+            None
+        }
+    }
+}
+
+fn read<T>(f: impl FnOnce(&Prefs) -> T) -> T {
+    f(&STORE.lock().unwrap().prefs)
+}
+
+/// Applies `f` and writes the result; the change is kept in memory even if
+/// writing fails.
+fn update<T>(f: impl FnOnce(&mut Prefs) -> Result<T, String>) -> Result<T, String> {
+    let mut store = STORE.lock().unwrap();
+    let value = f(&mut store.prefs)?;
+    let path = store.path.clone().ok_or("Папка настроек недоступна")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(&store.prefs).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())?;
+    Ok(value)
 }
 
-#[cfg(not(windows))]
 pub fn manual_warning_acknowledged() -> bool {
-    true
+    read(|prefs| prefs.manual_warning_acknowledged)
 }
 
-#[cfg(not(windows))]
 pub fn acknowledge_manual_warning() -> Result<(), String> {
-    Ok(())
+    update(|prefs| {
+        prefs.manual_warning_acknowledged = true;
+        Ok(())
+    })
 }
 
-#[cfg(windows)]
 pub fn last_seen_version() -> Option<String> {
-    use winreg::RegKey;
-    use winreg::enums::HKEY_CURRENT_USER;
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let key = hkcu.open_subkey(SUBKEY).ok()?;
-    key.get_value(LAST_SEEN_VERSION_VALUE).ok()
+    read(|prefs| prefs.last_seen_version.clone())
 }
 
-#[cfg(windows)]
 pub fn set_last_seen_version(value: &str) -> Result<(), String> {
-    use winreg::RegKey;
-    use winreg::enums::HKEY_CURRENT_USER;
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey(SUBKEY).map_err(|e| e.to_string())?;
-    key.set_value(LAST_SEEN_VERSION_VALUE, &value.to_string())
-        .map_err(|e| e.to_string())
+    update(|prefs| {
+        prefs.last_seen_version = Some(value.to_string());
+        Ok(())
+    })
 }
 
-#[cfg(not(windows))]
-pub fn last_seen_version() -> Option<String> {
-    None
+pub fn profiles() -> Vec<Profile> {
+    read(|prefs| prefs.profiles.clone())
+}
+
+pub fn profile(name: &str) -> Option<Profile> {
+    read(|prefs| prefs.profiles.iter().find(|p| p.name == name).cloned())
+}
+
+/// Adds `profile`, or replaces the one with the same name.
+pub fn save_profile(profile: Profile) -> Result<(), String> {
+    profile.validate()?;
+    update(|prefs| {
+        upsert(&mut prefs.profiles, profile);
+        Ok(())
+    })
+}
+
+pub fn delete_profile(name: &str) -> Result<(), String> {
+    update(|prefs| {
+        prefs.profiles.retain(|p| p.name != name);
+        if prefs.last_profile.as_deref() == Some(name) {
+            prefs.last_profile = None;
+        }
+        Ok(())
+    })
+}
+
+/// All profiles as a JSON array.
+pub fn export_profiles() -> Result<String, String> {
+    read(|prefs| serde_json::to_string_pretty(&prefs.profiles)).map_err(|e| e.to_string())
+}
+
+/// Adds the profiles from a JSON array, replacing ones with the same names.
+/// Nothing is imported if any of them is invalid. Returns how many there were.
+pub fn import_profiles(json: &str) -> Result<u32, String> {
+    let imported: Vec<Profile> =
+        serde_json::from_str(json).map_err(|e| format!("Неверный файл профилей: {}", e))?;
+    for profile in &imported {
+        profile.validate()?;
+    }
+    let count = imported.len() as u32;
+    update(|prefs| {
+        for profile in imported {
+            upsert(&mut prefs.profiles, profile);
+        }
+        Ok(count)
+    })
+}
+
+fn upsert(profiles: &mut Vec<Profile>, profile: Profile) {
+    match profiles.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => *existing = profile,
+        None => profiles.push(profile),
+    }
+}
+
+pub fn set_last_profile(name: &str) -> Result<(), String> {
+    update(|prefs| {
+        prefs.last_profile = Some(name.to_string());
+        Ok(())
+    })
+}
+
+pub fn auto_start() -> bool {
+    read(|prefs| prefs.auto_start)
+}
+
+pub fn set_auto_start(value: bool) -> Result<(), String> {
+    update(|prefs| {
+        prefs.auto_start = value;
+        Ok(())
+    })
+}
+
+/// The profile to start on launch: the last one started, if auto-start is on
+/// and it still exists.
+pub fn auto_start_profile() -> Option<Profile> {
+    read(|prefs| {
+        let name = prefs.last_profile.as_deref().filter(|_| prefs.auto_start)?;
+        prefs.profiles.iter().find(|p| p.name == name).cloned()
+    })
+}
+
+#[cfg(windows)]
+mod registry {
+    use winreg::{RegKey, enums::HKEY_CURRENT_USER};
+
+    use super::Prefs;
+
+    const SUBKEY: &str = "Software\\VoxelProxy";
+    const MANUAL_WARNING_VALUE: &str = "ManualWarningAcknowledged";
+    const LAST_SEEN_VERSION_VALUE: &str = "LastSeenVersion";
+
+    /// What older versions kept in the registry.
+    pub(super) fn load() -> Prefs {
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let Ok(key) = hkcu.open_subkey(SUBKEY) else {
+            return Prefs::default();
+        };
+        let acknowledged: Result<u32, _> = key.get_value(MANUAL_WARNING_VALUE);
+        Prefs {
+            manual_warning_acknowledged: matches!(acknowledged, Ok(v) if v != 0),
+            last_seen_version: key.get_value(LAST_SEEN_VERSION_VALUE).ok(),
+            ..Default::default()
+        }
+    }
 }
 
 #[cfg(not(windows))]
-pub fn set_last_seen_version(_value: &str) -> Result<(), String> {
-    Ok(())
+mod registry {
+    use super::Prefs;
+
+    pub(super) fn load() -> Prefs {
+        Prefs::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrupt_file_is_kept_as_backup() {
+        let dir = std::env::temp_dir().join(format!("voxelproxy-prefs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(PREFS_FILE);
+        let corrupt = b"{\"profiles\": [{\"name\": ";
+        std::fs::write(&path, corrupt).unwrap();
+
+        let prefs = load(&path).unwrap();
+
+        assert!(prefs.profiles.is_empty());
+        assert!(!path.exists());
+        assert_eq!(std::fs::read(dir.join("prefs.json.bak")).unwrap(), corrupt);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_file_is_not_saved_over() {
+        let dir = std::env::temp_dir().join(format!("voxelproxy-prefs-dir-{}", std::process::id()));
+        // A directory where the file should be can't be read as one.
+        let path = dir.join(PREFS_FILE);
+        std::fs::create_dir_all(&path).unwrap();
+
+        assert!(load(&path).is_none());
+        assert!(path.is_dir());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -1,131 +0,0 @@
-use mc_protocol::{Packet, varint::VarInt};
-
-pub mod c2s {
-    use super::*;
-
-    pub mod login {
-        use uuid::Uuid;
-
-        use super::*;
-
-        #[derive(Packet, Debug)]
-        #[packet(0)] // ServerboundHelloPacket
-        pub struct HelloPacket {
-            pub name: String,
-            pub uuid: Uuid,
-        }
-
-        #[derive(Packet, Debug)]
-        #[packet(3)] // ServerboundLoginAcknowledgedPacket
-        pub struct LoginAcknowledged {}
-    }
-
-    pub mod configuration {
-        use super::*;
-
-        #[derive(Packet, Debug)]
-        #[packet(3)] // ServerboundFinishConfigurationPacket
-        pub struct FinishConfiguration {}
-    }
-
-    pub mod game {
-        use super::*;
-
-        #[derive(Packet, Debug)]
-        #[packet(0)] // ServerboundAcceptTeleportationPacket
-        pub struct AcceptTeleportation {
-            pub id: VarInt,
-        }
-
-        #[derive(Packet, Debug)]
-        #[packet(14)] // ServerboundConfigurationAcknowledgedPacket
-        pub struct ConfigurationAcknowledged {}
-
-        #[derive(Packet, Debug)]
-        #[packet(28)] // ServerboundMovePlayerPacket.Pos
-        pub struct Pos {
-            pub x: f64,
-            pub y: f64,
-            pub z: f64,
-            pub on_ground: bool,
-        }
-
-        #[derive(Packet, Debug)]
-        #[packet(29)] // ServerboundMovePlayerPacket.PosRot
-        pub struct PosRot {
-            pub x: f64,
-            pub y: f64,
-            pub z: f64,
-            pub yaw: f32,
-            pub pitch: f32,
-            pub on_ground: bool,
-        }
-
-        #[derive(Packet, Debug)]
-        #[packet(30)] // ServerboundMovePlayerPacket.Rot
-        pub struct Rot {
-            pub yaw: f32,
-            pub pitch: f32,
-            pub on_ground: bool,
-        }
-
-        #[derive(Packet, Debug)]
-        #[packet(43)] // ServerboundPongPacket
-        pub struct Pong {
-            pub id: i32,
-        }
-
-        #[derive(Packet, Debug)]
-        #[packet(17)] // ServerboundContainerClosePacket
-        pub struct ContainerClose {
-            pub container_id: VarInt,
-        }
-    }
-}
-
-pub mod s2c {
-    use super::*;
-
-    pub mod configuration {
-        use super::*;
-
-        #[derive(Packet, Debug)]
-        #[packet(3)] // ClientboundFinishConfigurationPacket
-        pub struct FinishConfiguration {}
-    }
-
-    pub mod game {
-        use super::*;
-
-        #[derive(Packet, Debug)]
-        #[packet(66)] // ClientboundPlayerPositionPacket
-        pub struct Position {
-            pub id: VarInt,
-            pub x: f64,
-            pub y: f64,
-            pub z: f64,
-            pub delta_x: f64,
-            pub delta_y: f64,
-            pub delta_z: f64,
-            pub yaw: f32,
-            pub pitch: f32,
-            pub relative_flags: i32,
-        }
-
-        #[derive(Packet, Debug)]
-        #[packet(55)] // ClientboundPingPacket
-        pub struct Ping {
-            pub id: i32,
-        }
-
-        #[derive(Packet, Debug)]
-        #[packet(112)] // ClientboundStartConfigurationPacket
-        pub struct StartConfiguration {}
-
-        #[derive(Packet, Debug)]
-        #[packet(18)] // ClientboundContainerClosePacket
-        pub struct ContainerClose {
-            pub container_id: VarInt,
-        }
-    }
-}
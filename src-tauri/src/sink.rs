@@ -0,0 +1,80 @@
+use tauri::AppHandle;
+use tauri_specta::Event;
+use voxelproxy_core::{
+    ClientId, EventSink, LogLevel, inspector::PacketEvent, latency::Latency, metrics::SessionStats,
+};
+
+use crate::{
+    config,
+    events::{
+        ActiveClientEvent, ClientStatusEvent, LatencyEvent, NickNameEvent, PacketInspectEvent,
+        ProxyLogEvent, ServerAddrEvent, SessionStatsEvent,
+    },
+};
+
+/// Forwards engine events to the webview as Tauri events and to telemetry.
+pub struct AppSink {
+    app: AppHandle,
+}
+
+impl AppSink {
+    pub fn new(app: AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+impl EventSink for AppSink {
+    fn log(&self, level: LogLevel, message: String) {
+        ProxyLogEvent {
+            level: level.into(),
+            message,
+        }
+        .emit(&self.app)
+        .ok();
+    }
+
+    fn client_status(&self, which: ClientId, online: bool) {
+        ClientStatusEvent {
+            which: which.into(),
+            online,
+        }
+        .emit(&self.app)
+        .ok();
+    }
+
+    fn active_client(&self, which: ClientId) {
+        ActiveClientEvent {
+            which: which.into(),
+        }
+        .emit(&self.app)
+        .ok();
+    }
+
+    fn nickname(&self, nickname: String) {
+        NickNameEvent(nickname).emit(&self.app).ok();
+    }
+
+    fn server_addr(&self, addr: String) {
+        ServerAddrEvent(addr).emit(&self.app).ok();
+    }
+
+    fn join(&self, server_addr: String, nickname: String, protocol_version: i32) {
+        tokio::spawn(config::send_join(server_addr, nickname, protocol_version));
+    }
+
+    fn protocol_metadata(&self, data: String, custom: bool) {
+        tokio::spawn(config::send_protocol_metadata(data, custom));
+    }
+
+    fn packet(&self, event: PacketEvent) {
+        PacketInspectEvent::from(event).emit(&self.app).ok();
+    }
+
+    fn session_stats(&self, stats: SessionStats) {
+        SessionStatsEvent::from(stats).emit(&self.app).ok();
+    }
+
+    fn latency(&self, latency: Latency) {
+        LatencyEvent::from(latency).emit(&self.app).ok();
+    }
+}
@@ -0,0 +1,405 @@
+//! Where the `worker.v1` requests built in [`config`] go: the worker baked in
+//! at build time, a self-hosted gRPC endpoint, a local JSON-lines file, or
+//! nowhere. The sink is chosen at runtime and saved in the app config dir.
+//!
+//! Requests the sink can't take right now (the endpoint is down, the file
+//! can't be opened) wait in a bounded in-memory queue, which is flushed in
+//! order with the next request and every [`RETRY_INTERVAL`]. A single worker
+//! task owns the queue, so nothing waits on a lock while a request is sent.
+
+use std::{
+    collections::VecDeque,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tokio::sync::{mpsc, oneshot};
+use tonic::{
+    Code, Status,
+    transport::{Channel, ClientTlsConfig, Endpoint},
+};
+
+use crate::config::{
+    self,
+    pb::{worker_client::WorkerClient, *},
+};
+
+const SINK_FILE: &str = "telemetry-sink.json";
+/// Requests kept for a retry; the oldest is given up on when it's full.
+const QUEUE_LIMIT: usize = 256;
+/// How often queued requests are retried.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+/// How long one request may take before it's queued for a retry.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where telemetry goes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum TelemetrySink {
+    /// The worker from `TELEMETRY_URL` at build time; nothing in debug builds.
+    #[default]
+    Builtin,
+    /// A self-hosted `worker.v1` endpoint, `http://` or `https://`.
+    Grpc {
+        url: String,
+    },
+    /// One JSON object per line appended to a local file.
+    File {
+        path: String,
+    },
+    Disabled,
+}
+
+/// One request to the worker.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Ping(PingRequest),
+    ManualStart(ManualStartRequest),
+    AutoStart(AutoStartRequest),
+    Join(JoinRequest),
+    ProtocolMetadata(SendProtocolMetadataRequest),
+    DeveloperMessage(SendDeveloperMessageRequest),
+}
+
+impl Event {
+    /// Name of the RPC, as in `worker.proto`.
+    fn rpc(&self) -> &'static str {
+        match self {
+            Event::Ping(_) => "Ping",
+            Event::ManualStart(_) => "ManualStart",
+            Event::AutoStart(_) => "AutoStart",
+            Event::Join(_) => "Join",
+            Event::ProtocolMetadata(_) => "SendProtocolMetadata",
+            Event::DeveloperMessage(_) => "SendDeveloperMessage",
+        }
+    }
+
+    fn request(&self) -> serde_json::Value {
+        match self {
+            Event::Ping(r) => serde_json::to_value(r),
+            Event::ManualStart(r) => serde_json::to_value(r),
+            Event::AutoStart(r) => serde_json::to_value(r),
+            Event::Join(r) => serde_json::to_value(r),
+            Event::ProtocolMetadata(r) => serde_json::to_value(r),
+            Event::DeveloperMessage(r) => serde_json::to_value(r),
+        }
+        .unwrap_or_default()
+    }
+
+    async fn call(&self, mut client: WorkerClient<Channel>) -> Result<(), Status> {
+        match self.clone() {
+            Event::Ping(r) => client.ping(r).await.map(drop),
+            Event::ManualStart(r) => client.manual_start(r).await.map(drop),
+            Event::AutoStart(r) => client.auto_start(r).await.map(drop),
+            Event::Join(r) => client.join(r).await.map(drop),
+            Event::ProtocolMetadata(r) => client.send_protocol_metadata(r).await.map(drop),
+            Event::DeveloperMessage(r) => client.send_developer_message(r).await.map(drop),
+        }
+    }
+}
+
+/// Why a request didn't reach the sink.
+enum Failure {
+    /// Worth another try later: the endpoint is unreachable or busy.
+    Retry,
+    /// The sink refused it; retrying won't help.
+    Rejected,
+}
+
+/// The configured sink, ready to take requests.
+enum Target {
+    Grpc(Channel),
+    File(PathBuf),
+}
+
+impl Target {
+    async fn deliver(&self, event: &Event) -> Result<(), Failure> {
+        match self {
+            Target::Grpc(channel) => {
+                let client = WorkerClient::new(channel.clone());
+                match tokio::time::timeout(SEND_TIMEOUT, event.call(client)).await {
+                    Ok(Ok(())) => Ok(()),
+                    Ok(Err(status)) if !is_retryable(&status) => Err(Failure::Rejected),
+                    _ => Err(Failure::Retry),
+                }
+            }
+            Target::File(path) => {
+                let line = serde_json::json!({
+                    "time_ms": now_ms(),
+                    "rpc": event.rpc(),
+                    "request": event.request(),
+                });
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).ok();
+                }
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| writeln!(file, "{}", line))
+                    .map_err(|_| Failure::Retry)
+            }
+        }
+    }
+}
+
+fn is_retryable(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unavailable | Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted
+    )
+}
+
+struct State {
+    sink: TelemetrySink,
+    sink_path: Option<PathBuf>,
+    /// The channel for the current gRPC URL, built on first use.
+    channel: Option<(String, Channel)>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    sink: TelemetrySink::Builtin,
+    sink_path: None,
+    channel: None,
+});
+/// Feeds the worker started by [`init`].
+static JOBS: OnceLock<mpsc::UnboundedSender<Job>> = OnceLock::new();
+/// Length of the worker's queue, for [`queued`].
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+
+/// What became of a submitted request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, specta::Type)]
+pub enum Delivery {
+    Delivered,
+    /// The sink can't take it right now; it'll be retried.
+    Queued,
+    /// There's no sink, or the sink refused it.
+    Dropped,
+}
+
+/// Work for the worker, which owns the queue.
+enum Job {
+    Submit(Event, oneshot::Sender<Delivery>),
+    /// The sink was changed: flush the queue to the new one, or drop it.
+    SinkChanged,
+}
+
+/// Loads the saved sink. Call once at startup, before anything is sent, and
+/// spawn the returned worker for the app's lifetime.
+pub fn init(app: &AppHandle) -> impl Future<Output = ()> + use<> {
+    let sink_path = app
+        .path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(SINK_FILE));
+    let mut state = STATE.lock().unwrap();
+    state.sink = sink_path
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+    state.sink_path = sink_path;
+
+    let (jobs, receiver) = mpsc::unbounded_channel();
+    JOBS.set(jobs).ok();
+    work(receiver)
+}
+
+pub fn sink() -> TelemetrySink {
+    STATE.lock().unwrap().sink.clone()
+}
+
+/// Checks and saves `sink`; requests from now on, and the queued ones, go
+/// there. Switching to [`TelemetrySink::Disabled`] drops the queue.
+pub fn set_sink(sink: TelemetrySink) -> Result<(), String> {
+    let sink = match sink {
+        TelemetrySink::Grpc { url } => {
+            let url = url.trim().to_string();
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                return Err("Адрес должен начинаться с http:// или https://".into());
+            }
+            Endpoint::from_shared(url.clone()).map_err(|e| format!("Неверный адрес: {}", e))?;
+            TelemetrySink::Grpc { url }
+        }
+        TelemetrySink::File { path } => {
+            let path = path.trim().to_string();
+            if !PathBuf::from(&path).is_absolute() {
+                return Err("Укажите полный путь к файлу".into());
+            }
+            TelemetrySink::File { path }
+        }
+        sink => sink,
+    };
+
+    let mut state = STATE.lock().unwrap();
+    let path = state.sink_path.clone().ok_or("Папка настроек недоступна")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(&sink).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())?;
+    state.sink = sink;
+    if let Some(jobs) = JOBS.get() {
+        jobs.send(Job::SinkChanged).ok();
+    }
+    Ok(())
+}
+
+/// Requests waiting for a retry.
+pub fn queued() -> usize {
+    QUEUED.load(Ordering::Relaxed)
+}
+
+/// Sends `event` to the sink, after whatever is still queued, and waits to
+/// see what became of it.
+pub async fn submit(event: Event) -> Delivery {
+    let Some(jobs) = JOBS.get() else {
+        return Delivery::Dropped;
+    };
+    let (reply, delivery) = oneshot::channel();
+    if jobs.send(Job::Submit(event, reply)).is_err() {
+        return Delivery::Dropped;
+    }
+    delivery.await.unwrap_or(Delivery::Dropped)
+}
+
+/// Takes jobs one at a time, so requests go out in order, and retries the
+/// queue every [`RETRY_INTERVAL`].
+async fn work(mut jobs: mpsc::UnboundedReceiver<Job>) {
+    let mut queue = VecDeque::new();
+    let mut timer = tokio::time::interval(RETRY_INTERVAL);
+    loop {
+        tokio::select! {
+            job = jobs.recv() => match job {
+                Some(Job::Submit(event, reply)) => {
+                    let delivery = match target() {
+                        Some(target) => {
+                            if queue.len() == QUEUE_LIMIT
+                                && let Some(dropped) = queue.pop_front()
+                            {
+                                audit(&dropped, false);
+                            }
+                            queue.push_back(event);
+                            flush(&mut queue, &target).await
+                        }
+                        None => Delivery::Dropped,
+                    };
+                    reply.send(delivery).ok();
+                }
+                Some(Job::SinkChanged) => match target() {
+                    Some(target) => {
+                        flush(&mut queue, &target).await;
+                    }
+                    None if sink() == TelemetrySink::Disabled => {
+                        for event in queue.drain(..) {
+                            audit(&event, false);
+                        }
+                    }
+                    None => {}
+                },
+                None => return,
+            },
+            _ = timer.tick() => {
+                if !queue.is_empty()
+                    && let Some(target) = target()
+                {
+                    flush(&mut queue, &target).await;
+                }
+            }
+        }
+        QUEUED.store(queue.len(), Ordering::Relaxed);
+    }
+}
+
+/// Sends queued requests in order until the sink fails to take one, and
+/// returns what became of the last one.
+async fn flush(queue: &mut VecDeque<Event>, target: &Target) -> Delivery {
+    let mut last = Delivery::Queued;
+    while let Some(event) = queue.front() {
+        let delivered = match target.deliver(event).await {
+            Ok(()) => true,
+            Err(Failure::Rejected) => false,
+            Err(Failure::Retry) => return Delivery::Queued,
+        };
+        audit(event, delivered);
+        last = if delivered {
+            Delivery::Delivered
+        } else {
+            Delivery::Dropped
+        };
+        queue.pop_front();
+    }
+    last
+}
+
+/// Each request is written to the audit log once: when it's delivered or
+/// given up on.
+fn audit(event: &Event, delivered: bool) {
+    config::audit(event.rpc(), event.request().to_string(), delivered);
+}
+
+fn target() -> Option<Target> {
+    let mut state = STATE.lock().unwrap();
+    let url = match &state.sink {
+        TelemetrySink::Disabled => return None,
+        TelemetrySink::File { path } => return Some(Target::File(PathBuf::from(path))),
+        TelemetrySink::Grpc { url } => url.clone(),
+        TelemetrySink::Builtin => {
+            let cfg = config::get_config().lock().unwrap();
+            if !cfg.should_send() {
+                return None;
+            }
+            cfg.telemetry_url.to_string()
+        }
+    };
+    if let Some((cached, channel)) = &state.channel
+        && *cached == url
+    {
+        return Some(Target::Grpc(channel.clone()));
+    }
+    let channel = channel(&url)?;
+    state.channel = Some((url, channel.clone()));
+    Some(Target::Grpc(channel))
+}
+
+/// gRPC channel to the worker at `url`.
+///
+/// `connect_lazy` never blocks: the TCP/HTTP2 connection is established on the
+/// first RPC and transparently re-established on failure; until then RPCs
+/// fail with `Unavailable` and the requests wait in the queue.
+///
+/// TLS is configured with the bundled webpki roots, which is what lets an
+/// `https://` endpoint connect at all — without it tonic refuses the https
+/// scheme and every RPC fails silently. Plaintext `http://` URLs ignore the
+/// TLS config and connect in cleartext (useful for local dev).
+fn channel(url: &str) -> Option<Channel> {
+    let endpoint = match Endpoint::from_shared(url.to_string()) {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            eprintln!("telemetry: invalid URL {:?}: {e}", url);
+            return None;
+        }
+    };
+    let endpoint = match endpoint.tls_config(ClientTlsConfig::new().with_webpki_roots()) {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            eprintln!("telemetry: failed to configure TLS: {e}");
+            return None;
+        }
+    };
+    Some(endpoint.connect_lazy())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
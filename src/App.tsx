@@ -4,12 +4,15 @@ import { useAppState } from "./hooks/useAppState";
 import { useTauriListeners } from "./hooks/useTauriListeners";
 import { TitleBar } from "./components/TitleBar";
 import { LogPanel } from "./components/LogPanel";
+import { PacketInspector } from "./components/PacketInspector";
 import { ChangelogModal } from "./components/ChangelogModal";
 import { DeveloperMessageModal } from "./components/DeveloperMessageModal";
+import { TelemetryModal } from "./components/TelemetryModal";
+import { applyProfile } from "./components/ProfilesRow";
 import { IdleView } from "./views/IdleView";
 import { RunningView } from "./views/RunningView";
 import * as api from "./bindings";
-import { ChangelogEntry } from "./bindings";
+import { ChangelogEntry, TelemetrySettings } from "./bindings";
 
 export const App = () => {
   const { state, setState, logs, setLogs, addLog } = useAppState();
@@ -17,6 +20,42 @@ export const App = () => {
 
   const [changelog, setChangelog] = useState<ChangelogEntry[]>([]);
   const [showDeveloperMessage, setShowDeveloperMessage] = useState(false);
+  // undefined — ещё не загружено; null — пользователь ещё не выбирал.
+  const [telemetry, setTelemetry] = useState<TelemetrySettings | null>();
+  const [showTelemetry, setShowTelemetry] = useState(false);
+
+  useEffect(() => {
+    (async () => {
+      const settings = await api.commands.getTelemetrySettings();
+      setTelemetry(settings);
+      if (settings === null) setShowTelemetry(true);
+    })();
+  }, []);
+
+  // Автозапуск последнего профиля, если он включён.
+  useEffect(() => {
+    (async () => {
+      const profile = await api.commands.getAutoStartProfile();
+      if (!profile) return;
+      if (
+        profile.mode === "Manual" &&
+        !(await api.commands.getManualWarningAcknowledged())
+      ) {
+        return;
+      }
+      setState((prev) => applyProfile(prev, profile));
+      addLog("Info", `Автозапуск профиля «${profile.name}»`);
+      const result = await api.commands.startProfile(profile.name);
+      if (result.status === "error") {
+        addLog("Error", `Ошибка запуска: ${result.error}`);
+      }
+    })();
+  }, [setState, addLog]);
+
+  const closeTelemetry = useCallback((settings: TelemetrySettings | null) => {
+    setTelemetry(settings);
+    setShowTelemetry(false);
+  }, []);
 
   useEffect(() => {
     (async () => {
@@ -56,6 +95,7 @@ export const App = () => {
           state={state}
           onStop={handleStop}
           onContact={() => setShowDeveloperMessage(true)}
+          onTelemetry={() => setShowTelemetry(true)}
         />
 
         <main className="view-container">
@@ -71,18 +111,32 @@ export const App = () => {
               {isIdle ? (
                 <IdleView state={state} setState={setState} addLog={addLog} />
               ) : (
-                <RunningView state={state} onTogglePanicMode={togglePanicMode} />
+                <RunningView
+                  state={state}
+                  onTogglePanicMode={togglePanicMode}
+                  addLog={addLog}
+                />
               )}
             </motion.div>
           </AnimatePresence>
         </main>
 
+        <PacketInspector />
+
         <LogPanel logs={logs} onClear={() => setLogs([])} />
 
         {changelog.length > 0 && (
           <ChangelogModal entries={changelog} onDismiss={dismissChangelog} />
         )}
 
+        {showTelemetry && telemetry !== undefined && (
+          <TelemetryModal
+            current={telemetry}
+            onClose={closeTelemetry}
+            addLog={addLog}
+          />
+        )}
+
         {showDeveloperMessage && (
           <DeveloperMessageModal
             onClose={() => setShowDeveloperMessage(false)}
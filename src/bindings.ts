@@ -5,9 +5,14 @@ import * as __TAURI_EVENT from "@tauri-apps/api/event";
 
 /** Commands */
 export const commands = {
-	startManualSession: (serverAddr: string) => typedError<null, string>(__TAURI_INVOKE("start_manual_session", { serverAddr })),
-	startAutoSession: (useWindivert: boolean, portMin: number, portMax: number) => typedError<null, string>(__TAURI_INVOKE("start_auto_session", { useWindivert, portMin, portMax })),
+	startManualSession: (serverAddr: string, routing: PacketCategory[]) => typedError<null, string>(__TAURI_INVOKE("start_manual_session", { serverAddr, routing })),
+	startAutoSession: (useWindivert: boolean, portMin: number, portMax: number, routing: PacketCategory[]) => typedError<null, string>(__TAURI_INVOKE("start_auto_session", { useWindivert, portMin, portMax, routing })),
+	startProfile: (name: string) => typedError<null, string>(__TAURI_INVOKE("start_profile", { name })),
 	stopSession: () => typedError<null, string>(__TAURI_INVOKE("stop_session")),
+	switchActiveClient: (which: WhichClient) => typedError<null, string>(__TAURI_INVOKE("switch_active_client", { which })),
+	kickClient: (which: WhichClient) => typedError<null, string>(__TAURI_INVOKE("kick_client", { which })),
+	startInspector: (filter: InspectorFilter) => __TAURI_INVOKE<void>("start_inspector", { filter }),
+	stopInspector: () => __TAURI_INVOKE<void>("stop_inspector"),
 	getVersion: () => __TAURI_INVOKE<string>("get_version"),
 	getSupportedVersions: () => __TAURI_INVOKE<string[]>("get_supported_versions"),
 	getLocalIpAddr: () => __TAURI_INVOKE<string>("get_local_ip_addr"),
@@ -25,21 +30,57 @@ export const commands = {
 	acknowledgeChangelog: () => typedError<null, string>(__TAURI_INVOKE("acknowledge_changelog")),
 	getManualWarningAcknowledged: () => __TAURI_INVOKE<boolean>("get_manual_warning_acknowledged"),
 	acknowledgeManualWarning: () => typedError<null, string>(__TAURI_INVOKE("acknowledge_manual_warning")),
-	sendDeveloperMessage: (message: string) => typedError<null, string>(__TAURI_INVOKE("send_developer_message", { message })),
+	listProfiles: () => __TAURI_INVOKE<Profile[]>("list_profiles"),
+	saveProfile: (profile: Profile) => typedError<null, string>(__TAURI_INVOKE("save_profile", { profile })),
+	deleteProfile: (name: string) => typedError<null, string>(__TAURI_INVOKE("delete_profile", { name })),
+	exportProfiles: () => typedError<string, string>(__TAURI_INVOKE("export_profiles")),
+	importProfiles: (json: string) => typedError<number, string>(__TAURI_INVOKE("import_profiles", { json })),
+	getAutoStart: () => __TAURI_INVOKE<boolean>("get_auto_start"),
+	setAutoStart: (value: boolean) => typedError<null, string>(__TAURI_INVOKE("set_auto_start", { value })),
+	getAutoStartProfile: () => __TAURI_INVOKE<Profile | null>("get_auto_start_profile"),
+	sendDeveloperMessage: (message: string) => typedError<Delivery, string>(__TAURI_INVOKE("send_developer_message", { message })),
+	getTelemetrySettings: () => __TAURI_INVOKE<TelemetrySettings | null>("get_telemetry_settings"),
+	setTelemetrySettings: (settings: TelemetrySettings) => typedError<null, string>(__TAURI_INVOKE("set_telemetry_settings", { settings })),
+	getTelemetryAudit: () => typedError<TelemetryAuditEntry[], string>(__TAURI_INVOKE("get_telemetry_audit")),
+	clearTelemetryAudit: () => typedError<null, string>(__TAURI_INVOKE("clear_telemetry_audit")),
+	getTelemetrySink: () => __TAURI_INVOKE<TelemetrySink>("get_telemetry_sink"),
+	setTelemetrySink: (sink: TelemetrySink) => typedError<null, string>(__TAURI_INVOKE("set_telemetry_sink", { sink })),
+	getTelemetryQueued: () => __TAURI_INVOKE<number>("get_telemetry_queued"),
+	getAccount: () => __TAURI_INVOKE<AccountInfo | null>("get_account"),
+	startAccountLogin: () => typedError<DeviceCode, string>(__TAURI_INVOKE("start_account_login")),
+	logoutAccount: () => typedError<null, string>(__TAURI_INVOKE("logout_account")),
+	getSessionServer: () => __TAURI_INVOKE<string>("get_session_server"),
+	setSessionServer: (url: string | null) => typedError<null, string>(__TAURI_INVOKE("set_session_server", { url })),
 };
 
 /** Events */
 export const events = {
+	accountEvent: makeEvent<AccountEvent>("account-event"),
+	activeClientEvent: makeEvent<ActiveClientEvent>("active-client-event"),
 	clientStatusEvent: makeEvent<ClientStatusEvent>("client-status-event"),
+	latencyEvent: makeEvent<LatencyEvent>("latency-event"),
 	nickNameEvent: makeEvent<NickNameEvent>("nick-name-event"),
+	packetInspectEvent: makeEvent<PacketInspectEvent>("packet-inspect-event"),
 	proxyLogEvent: makeEvent<ProxyLogEvent>("proxy-log-event"),
 	serverAddrEvent: makeEvent<ServerAddrEvent>("server-addr-event"),
 	sessionEndedEvent: makeEvent<SessionEndedEvent>("session-ended-event"),
 	sessionStartedEvent: makeEvent<SessionStartedEvent>("session-started-event"),
+	sessionStatsEvent: makeEvent<SessionStatsEvent>("session-stats-event"),
 	updateProgressEvent: makeEvent<UpdateProgressEvent>("update-progress-event"),
 };
 
 /* Types */
+export type AccountEvent = AccountInfo | null;
+
+export type AccountInfo = {
+	name: string,
+	uuid: string,
+};
+
+export type ActiveClientEvent = {
+	which: WhichClient,
+};
+
 export type ChangelogEntry = {
 	version: string,
 	html: string,
@@ -50,21 +91,118 @@ export type ClientStatusEvent = {
 	online: boolean,
 };
 
+export type CounterStats = {
+	packets: number,
+	bytes: number,
+};
+
+export type Delivery = "Delivered" | "Queued" | "Dropped";
+
+export type DeviceCode = {
+	user_code: string,
+	verification_uri: string,
+};
+
+export type InspectorFilter = {
+	direction: PacketDirection | null,
+	client: WhichClient | null,
+	names: string[],
+	packet_ids: number[],
+	max_per_second: number,
+};
+
+export type LatencyEvent = {
+	upstream_ms: number | null,
+	primary_ms: number | null,
+	secondary_ms: number | null,
+};
+
 export type LogLevel = "Info" | "Success" | "Warn" | "Error";
 
 export type NickNameEvent = string;
 
+export type PacketCategory = "Chat" | "Commands" | "Movement" | "Combat" | "Interaction" | "Inventory";
+
+export type PacketDirection = "Serverbound" | "Clientbound";
+
+export type PacketInspectEvent = {
+	direction: PacketDirection,
+	client: WhichClient | null,
+	state: ProtocolState,
+	packet_id: number,
+	name: string | null,
+	size: number,
+	outcome: PacketOutcome,
+	suppressed: number,
+};
+
+export type PacketOutcome = "Relayed" | "Mirrored" | "Skipped" | "Dropped";
+
+export type Profile = {
+	name: string,
+	mode: ProfileMode,
+	server_addr: string,
+	use_windivert: boolean,
+	port_min: number,
+	port_max: number,
+	bind_addr: string | null,
+	routing: PacketCategory[],
+};
+
+export type ProfileMode = "Manual" | "Auto";
+
+export type ProtocolState = "Login" | "Configuration" | "Game";
+
 export type ProxyLogEvent = {
 	level: LogLevel,
 	message: string,
 };
 
+export type QueueStats = {
+	events: number,
+	primary: number,
+	secondary: number,
+	server: number,
+};
+
 export type ServerAddrEvent = string;
 
 export type SessionEndedEvent = null;
 
 export type SessionStartedEvent = null;
 
+export type SessionStatsEvent = {
+	uptime_secs: number,
+	primary: TrafficStats,
+	secondary: TrafficStats,
+	server: TrafficStats,
+	queues: QueueStats,
+	handovers: number,
+	pending_pings: number,
+	latency: LatencyEvent,
+};
+
+export type TelemetryAuditEntry = {
+	time_ms: number,
+	rpc: string,
+	request: string,
+	delivered: boolean,
+};
+
+export type TelemetryLevel = "Off" | "Basic" | "Full";
+
+export type TelemetrySettings = {
+	level: TelemetryLevel,
+	include_chat: boolean,
+};
+
+export type TelemetrySink = "Builtin" | { Grpc: { url: string } } | { File: { path: string } } | "Disabled";
+
+export type TrafficStats = {
+	serverbound: CounterStats,
+	clientbound: CounterStats,
+};
+
 export type UpdateInfo = {
 	tag: string,
 	link: string,
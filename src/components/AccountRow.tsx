@@ -0,0 +1,69 @@
+import React, { useCallback, useEffect, useState } from "react";
+import { AccountInfo, DeviceCode, commands, events, LogLevel } from "../bindings";
+
+interface Props {
+  disabled: boolean;
+  addLog: (level: LogLevel, message: string) => void;
+}
+
+// Аккаунт Minecraft для лицензионных серверов. Вход через код устройства:
+// пользователь вводит код на сайте Microsoft, а приложение ждёт подтверждения.
+export const AccountRow: React.FC<Props> = ({ disabled, addLog }) => {
+  const [account, setAccount] = useState<AccountInfo | null>(null);
+  const [code, setCode] = useState<DeviceCode | null>(null);
+
+  useEffect(() => {
+    commands.getAccount().then(setAccount);
+    const unlisten = events.accountEvent.listen(({ payload }) => {
+      setAccount(payload);
+      setCode(null);
+    });
+    return () => {
+      unlisten.then((fn) => fn()).catch(() => {});
+    };
+  }, []);
+
+  const login = useCallback(async () => {
+    const result = await commands.startAccountLogin();
+    if (result.status === "error") {
+      addLog("Error", `Ошибка входа: ${result.error}`);
+      return;
+    }
+    setCode(result.data);
+    commands.openUrl(result.data.verification_uri);
+  }, [addLog]);
+
+  const logout = useCallback(async () => {
+    const result = await commands.logoutAccount();
+    if (result.status === "error") {
+      addLog("Error", `Ошибка выхода: ${result.error}`);
+    }
+  }, [addLog]);
+
+  return (
+    <div className="field-row">
+      <span className="field-row__label">Аккаунт</span>
+      <div className="windivert-row">
+        {account ? (
+          <>
+            <span className="idle-view__versions">{account.name}</span>
+            <button className="btn-primary" onClick={logout} disabled={disabled}>
+              Выйти
+            </button>
+          </>
+        ) : code ? (
+          <span className="hint">
+            Введите код <code>{code.user_code}</code> на {code.verification_uri}
+          </span>
+        ) : (
+          <>
+            <span className="hint">Нужен для лицензионных серверов</span>
+            <button className="btn-primary" onClick={login} disabled={disabled}>
+              Войти
+            </button>
+          </>
+        )}
+      </div>
+    </div>
+  );
+};
@@ -1,4 +1,5 @@
 import React, { useEffect, useState } from "react";
+import { commands, LogLevel, SessionStatsEvent, WhichClient } from "../bindings";
 import { ClientStatus } from "../types";
 
 interface Props {
@@ -8,11 +9,19 @@ interface Props {
     primary: ClientStatus;
     secondary: ClientStatus;
   };
+  activeClient: WhichClient;
+  stats: SessionStatsEvent | null;
   showPanicMode: boolean;
   panicMode: boolean;
   onTogglePanicMode: () => void;
+  addLog: (level: LogLevel, message: string) => void;
 }
 
+const CLIENT_NAMES: Record<WhichClient, string> = {
+  Primary: "Основной",
+  Secondary: "Второй",
+};
+
 function formatUptime(totalSeconds: number): string {
   const s = Math.max(0, Math.floor(totalSeconds));
   const hours = Math.floor(s / 3600);
@@ -24,13 +33,26 @@ function formatUptime(totalSeconds: number): string {
     : `${pad(minutes)}:${pad(seconds)}`;
 }
 
+function formatBytes(bytes: number): string {
+  if (bytes < 1024) return `${bytes} Б`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} КБ`;
+  return `${(bytes / 1024 / 1024).toFixed(1)} МБ`;
+}
+
+function formatLatency(ms: number | null): string {
+  return ms === null ? "—" : `${Math.round(ms)} мс`;
+}
+
 export const ActiveSession: React.FC<Props> = ({
   nickName,
   serverAddr,
   clients,
+  activeClient,
+  stats,
   showPanicMode,
   panicMode,
   onTogglePanicMode,
+  addLog,
 }) => {
   const [elapsed, setElapsed] = useState(0);
 
@@ -42,6 +64,19 @@ export const ActiveSession: React.FC<Props> = ({
     return () => clearInterval(id);
   }, []);
 
+  const bothOnline = clients.primary.online && clients.secondary.online;
+  const inactiveClient: WhichClient = activeClient === "Primary" ? "Secondary" : "Primary";
+
+  // Передать управление или отключить неактивного клиента, не завершая сессию.
+  const switchActive = async () => {
+    const result = await commands.switchActiveClient(inactiveClient);
+    if (result.status === "error") addLog("Error", result.error);
+  };
+  const kickInactive = async () => {
+    const result = await commands.kickClient(inactiveClient);
+    if (result.status === "error") addLog("Error", result.error);
+  };
+
   return (
     <div className="panel">
       <div className="panel__header">
@@ -67,21 +102,56 @@ export const ActiveSession: React.FC<Props> = ({
       <div className="field-row">
         <span className="field-row__label">Клиенты</span>
         <span className="client-list">
-          <span className="client-list__item">
+          {(["Primary", "Secondary"] as const).map((which) => (
             <span
-              className={`client-dot ${clients.primary.online ? "client-dot--online" : "client-dot--offline"}`}
-            />
-            Основной
-          </span>
-          <span className="client-list__item">
-            <span
-              className={`client-dot ${clients.secondary.online ? "client-dot--online" : "client-dot--offline"}`}
-            />
-            Второй
-          </span>
+              key={which}
+              className={`client-list__item ${which === activeClient ? "client-list__item--active" : ""}`}
+            >
+              <span
+                className={`client-dot ${clients[which === "Primary" ? "primary" : "secondary"].online ? "client-dot--online" : "client-dot--offline"}`}
+              />
+              {CLIENT_NAMES[which]}
+            </span>
+          ))}
         </span>
       </div>
 
+      {stats && (
+        <div className="field-row">
+          <span className="field-row__label">Трафик</span>
+          <span>
+            ↑ {formatBytes(stats.server.serverbound.bytes)} ↓{" "}
+            {formatBytes(stats.server.clientbound.bytes)}
+            {stats.handovers > 0 && `, передач управления: ${stats.handovers}`}
+          </span>
+        </div>
+      )}
+
+      {stats && (
+        <div className="field-row">
+          <span className="field-row__label">Задержка</span>
+          <span>
+            сервер {formatLatency(stats.latency.upstream_ms)}, основной{" "}
+            {formatLatency(stats.latency.primary_ms)}, второй{" "}
+            {formatLatency(stats.latency.secondary_ms)}
+          </span>
+        </div>
+      )}
+
+      {bothOnline && (
+        <div className="field-row">
+          <span className="field-row__label">Управление</span>
+          <div className="windivert-row">
+            <button className="btn-primary" onClick={switchActive}>
+              Передать: {CLIENT_NAMES[inactiveClient].toLowerCase()}
+            </button>
+            <button className="btn-primary" onClick={kickInactive}>
+              Отключить: {CLIENT_NAMES[inactiveClient].toLowerCase()}
+            </button>
+          </div>
+        </div>
+      )}
+
       {showPanicMode && (
         <button
           className={`panic-mode ${panicMode ? "panic-mode--on" : "panic-mode--off"}`}
@@ -1,5 +1,5 @@
 import { useEffect, useState } from "react";
-import { commands, LogLevel } from "../bindings";
+import { commands, Delivery, LogLevel } from "../bindings";
 
 interface Props {
   onClose: () => void;
@@ -9,7 +9,7 @@ interface Props {
 export const DeveloperMessageModal: React.FC<Props> = ({ onClose, addLog }) => {
   const [message, setMessage] = useState("");
   const [isSending, setIsSending] = useState(false);
-  const [isSent, setIsSent] = useState(false);
+  const [sent, setSent] = useState<Delivery | null>(null);
 
   useEffect(() => {
     const onKey = (e: KeyboardEvent) => {
@@ -19,7 +19,7 @@ export const DeveloperMessageModal: React.FC<Props> = ({ onClose, addLog }) => {
     return () => window.removeEventListener("keydown", onKey);
   }, [onClose]);
 
-  const canSend = message.trim().length > 0 && !isSending && !isSent;
+  const canSend = message.trim().length > 0 && !isSending && !sent;
 
   const send = async () => {
     if (!canSend) return;
@@ -30,7 +30,7 @@ export const DeveloperMessageModal: React.FC<Props> = ({ onClose, addLog }) => {
         addLog("Error", `Не удалось отправить сообщение: ${result.error}`);
         return;
       }
-      setIsSent(true);
+      setSent(result.data);
       window.setTimeout(onClose, 1500);
     } finally {
       setIsSending(false);
@@ -57,9 +57,11 @@ export const DeveloperMessageModal: React.FC<Props> = ({ onClose, addLog }) => {
         </div>
 
         <div className="changelog-card__body">
-          {isSent ? (
+          {sent ? (
             <p className="dev-message__thanks">
-              Спасибо! Сообщение отправлено.
+              {sent === "Queued"
+                ? "Сервер сейчас недоступен — сообщение отправится, как только появится связь."
+                : "Спасибо! Сообщение отправлено."}
             </p>
           ) : (
             <>
@@ -80,7 +82,7 @@ export const DeveloperMessageModal: React.FC<Props> = ({ onClose, addLog }) => {
           )}
         </div>
 
-        {!isSent && (
+        {!sent && (
           <div className="changelog-card__footer warning-card__footer">
             <button className="warning-card__cancel" onClick={onClose}>
               Отмена
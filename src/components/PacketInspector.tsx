@@ -0,0 +1,137 @@
+import React, { useEffect, useRef, useState } from "react";
+import {
+  commands,
+  events,
+  PacketDirection,
+  PacketInspectEvent,
+  PacketOutcome,
+} from "../bindings";
+
+// Сколько последних пакетов держать в списке и сколько в секунду присылает прокси.
+const MAX_ROWS = 300;
+const MAX_PER_SECOND = 50;
+
+const OUTCOMES: Record<PacketOutcome, string> = {
+  Relayed: "передан",
+  Mirrored: "отражён",
+  Skipped: "задержан",
+  Dropped: "отброшен",
+};
+
+const SOURCES = {
+  Primary: "основной",
+  Secondary: "второй",
+};
+
+function parsePackets(text: string): { names: string[]; packet_ids: number[] } {
+  const names: string[] = [];
+  const packet_ids: number[] = [];
+  for (const part of text.split(",").map((p) => p.trim()).filter(Boolean)) {
+    const id = part.startsWith("0x") ? parseInt(part.slice(2), 16) : Number(part);
+    if (Number.isInteger(id)) packet_ids.push(id);
+    else names.push(part);
+  }
+  return { names, packet_ids };
+}
+
+export const PacketInspector: React.FC = () => {
+  const [isExpanded, setIsExpanded] = useState(false);
+  const [running, setRunning] = useState(false);
+  const [packets, setPackets] = useState("");
+  const [direction, setDirection] = useState<PacketDirection | "">("");
+  const [rows, setRows] = useState<PacketInspectEvent[]>([]);
+  const scrollRef = useRef<HTMLDivElement>(null);
+
+  useEffect(() => {
+    if (!running) return;
+    const unlisten = events.packetInspectEvent.listen((e) => {
+      setRows((prev) => [...prev.slice(-(MAX_ROWS - 1)), e.payload]);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [running]);
+
+  // Не оставлять инспектор включённым после закрытия окна.
+  useEffect(() => () => void commands.stopInspector(), []);
+
+  useEffect(() => {
+    if (scrollRef.current) {
+      scrollRef.current.scrollTop = scrollRef.current.scrollHeight;
+    }
+  }, [rows, isExpanded]);
+
+  const toggle = async () => {
+    if (running) {
+      await commands.stopInspector();
+      setRunning(false);
+      return;
+    }
+    await commands.startInspector({
+      direction: direction || null,
+      client: null,
+      ...parsePackets(packets),
+      max_per_second: MAX_PER_SECOND,
+    });
+    setRunning(true);
+  };
+
+  return (
+    <div className={`log-drawer ${isExpanded ? "is-expanded" : ""}`}>
+      <div
+        className="log-drawer__header"
+        onClick={() => setIsExpanded(!isExpanded)}
+        role="button"
+        tabIndex={0}
+        onKeyDown={(e) =>
+          (e.key === "Enter" || e.key === " ") && setIsExpanded(!isExpanded)
+        }
+      >
+        <span className="log-drawer__label">Пакеты</span>
+        <span className="log-drawer__count">({rows.length})</span>
+        <div className="log-drawer__spacer" />
+        <button
+          className="log-drawer__clear"
+          onClick={(e) => {
+            e.stopPropagation();
+            setRows([]);
+          }}
+        >
+          Очистить
+        </button>
+        <span className="log-drawer__chevron">▼</span>
+      </div>
+      <div className="log-drawer__body" ref={scrollRef}>
+        <div className="inspector-controls">
+          <input
+            className="inspector-controls__packets"
+            placeholder="Все пакеты или, например, move_pos, position, 0x1A"
+            value={packets}
+            disabled={running}
+            onChange={(e) => setPackets(e.target.value)}
+          />
+          <select
+            value={direction}
+            disabled={running}
+            onChange={(e) => setDirection(e.target.value as PacketDirection | "")}
+          >
+            <option value="">Оба направления</option>
+            <option value="Serverbound">К серверу</option>
+            <option value="Clientbound">К клиентам</option>
+          </select>
+          <button className="log-drawer__clear" onClick={toggle}>
+            {running ? "Остановить" : "Запустить"}
+          </button>
+        </div>
+        {rows.map((row, i) => (
+          <div key={i} className={`log-entry packet-entry ${row.outcome}`}>
+            {row.client ? `${SOURCES[row.client]} →` : "сервер →"}{" "}
+            {row.state} 0x{row.packet_id.toString(16).toUpperCase().padStart(2, "0")}
+            {row.name && ` ${row.name}`} {row.size} Б: {OUTCOMES[row.outcome]}
+            {row.suppressed > 0 && ` (пропущено ещё ${row.suppressed})`}
+          </div>
+        ))}
+      </div>
+    </div>
+  );
+};
@@ -0,0 +1,208 @@
+import React, { useCallback, useEffect, useRef, useState } from "react";
+import { commands, LogLevel, Profile } from "../bindings";
+import { AppState } from "../types";
+
+interface Props {
+  state: AppState;
+  setState: React.Dispatch<React.SetStateAction<AppState>>;
+  disabled: boolean;
+  addLog: (level: LogLevel, message: string) => void;
+}
+
+// Переносит настройки профиля в форму.
+export function applyProfile(state: AppState, profile: Profile): AppState {
+  return {
+    ...state,
+    profile,
+    mode: profile.mode === "Manual" ? "manual" : "auto",
+    manualServerAddr: profile.server_addr,
+    autoUseWindivert: profile.use_windivert,
+    autoPortMin: profile.port_min,
+    autoPortMax: profile.port_max,
+    routing: profile.routing,
+  };
+}
+
+// Профиль, если форма с ним совпадает: тогда сессия запускается из него,
+// с его адресом для прослушивания.
+export function matchingProfile(state: AppState): Profile | null {
+  const p = state.profile;
+  if (!p) return null;
+  const sameRouting =
+    p.routing.length === state.routing.length &&
+    p.routing.every((c) => state.routing.includes(c));
+  const same =
+    sameRouting &&
+    (p.mode === "Manual"
+      ? state.mode === "manual" && p.server_addr === state.manualServerAddr
+      : state.mode === "auto" &&
+        p.use_windivert === state.autoUseWindivert &&
+        p.port_min === state.autoPortMin &&
+        p.port_max === state.autoPortMax);
+  return same ? p : null;
+}
+
+// Именованные наборы настроек: сохранить текущую форму, выбрать сохранённый,
+// перенести на другой ПК через JSON.
+export const ProfilesRow: React.FC<Props> = ({
+  state,
+  setState,
+  disabled,
+  addLog,
+}) => {
+  const [profiles, setProfiles] = useState<Profile[]>([]);
+  const [name, setName] = useState(state.profile?.name ?? "");
+  const [autoStart, setAutoStart] = useState(false);
+  const fileRef = useRef<HTMLInputElement>(null);
+
+  const reload = useCallback(async () => {
+    setProfiles(await commands.listProfiles());
+  }, []);
+
+  useEffect(() => {
+    reload();
+    commands.getAutoStart().then(setAutoStart);
+  }, [reload]);
+
+  const select = (value: string) => {
+    setName(value);
+    const profile = profiles.find((p) => p.name === value);
+    if (profile) setState((s) => applyProfile(s, profile));
+  };
+
+  const save = async () => {
+    const existing = profiles.find((p) => p.name === name.trim());
+    const profile: Profile = {
+      name: name.trim(),
+      mode: state.mode === "manual" ? "Manual" : "Auto",
+      server_addr: state.manualServerAddr.trim(),
+      use_windivert: state.autoUseWindivert,
+      port_min: state.autoPortMin,
+      port_max: state.autoPortMax,
+      bind_addr: existing?.bind_addr ?? null,
+      routing: state.routing,
+    };
+    const result = await commands.saveProfile(profile);
+    if (result.status === "error") {
+      addLog("Error", `Не удалось сохранить профиль: ${result.error}`);
+      return;
+    }
+    setState((s) => ({ ...s, profile }));
+    addLog("Success", `Профиль «${profile.name}» сохранён`);
+    reload();
+  };
+
+  const remove = async () => {
+    const result = await commands.deleteProfile(name.trim());
+    if (result.status === "error") {
+      addLog("Error", `Не удалось удалить профиль: ${result.error}`);
+      return;
+    }
+    setName("");
+    setState((s) => ({ ...s, profile: null }));
+    reload();
+  };
+
+  const exportProfiles = async () => {
+    const result = await commands.exportProfiles();
+    if (result.status === "error") {
+      addLog("Error", `Не удалось экспортировать профили: ${result.error}`);
+      return;
+    }
+    await navigator.clipboard.writeText(result.data);
+    addLog("Success", "Профили скопированы в буфер обмена (JSON)");
+  };
+
+  const importProfiles = async (file: File) => {
+    const result = await commands.importProfiles(await file.text());
+    if (result.status === "error") {
+      addLog("Error", `Не удалось импортировать профили: ${result.error}`);
+      return;
+    }
+    addLog("Success", `Импортировано профилей: ${result.data}`);
+    reload();
+  };
+
+  const toggleAutoStart = async (value: boolean) => {
+    const result = await commands.setAutoStart(value);
+    if (result.status === "error") {
+      addLog("Error", `Не удалось сохранить настройку: ${result.error}`);
+      return;
+    }
+    setAutoStart(value);
+  };
+
+  const isSaved = profiles.some((p) => p.name === name.trim());
+
+  return (
+    <div className="field-row">
+      <span className="field-row__label">Профиль</span>
+      <div className="profiles-row">
+        <div className="windivert-row">
+          <input
+            type="text"
+            className="text-input"
+            list="profile-names"
+            placeholder="Название профиля"
+            value={name}
+            onChange={(e) => select(e.target.value)}
+            disabled={disabled}
+          />
+          <datalist id="profile-names">
+            {profiles.map((p) => (
+              <option key={p.name} value={p.name} />
+            ))}
+          </datalist>
+          <button
+            className="btn-primary"
+            onClick={save}
+            disabled={disabled || !name.trim()}
+          >
+            Сохранить
+          </button>
+          {isSaved && (
+            <button className="btn-primary" onClick={remove} disabled={disabled}>
+              Удалить
+            </button>
+          )}
+        </div>
+        <div className="windivert-row">
+          <label className="checkbox-label">
+            <input
+              type="checkbox"
+              checked={autoStart}
+              onChange={(e) => toggleAutoStart(e.target.checked)}
+              disabled={disabled}
+            />
+            Запускать последний профиль при старте
+          </label>
+          <button
+            className="log-drawer__clear"
+            onClick={exportProfiles}
+            disabled={profiles.length === 0}
+          >
+            Экспорт
+          </button>
+          <button
+            className="log-drawer__clear"
+            onClick={() => fileRef.current?.click()}
+            disabled={disabled}
+          >
+            Импорт
+          </button>
+          <input
+            ref={fileRef}
+            type="file"
+            accept=".json,application/json"
+            hidden
+            onChange={(e) => {
+              const file = e.target.files?.[0];
+              if (file) importProfiles(file);
+              e.target.value = "";
+            }}
+          />
+        </div>
+      </div>
+    </div>
+  );
+};
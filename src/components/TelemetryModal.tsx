@@ -0,0 +1,261 @@
+import { useEffect, useState } from "react";
+import {
+  commands,
+  LogLevel,
+  TelemetryAuditEntry,
+  TelemetryLevel,
+  TelemetrySettings,
+  TelemetrySink,
+} from "../bindings";
+
+interface Props {
+  // null — пользователь ещё не выбирал, окно показано при первом запуске.
+  current: TelemetrySettings | null;
+  onClose: (settings: TelemetrySettings | null) => void;
+  addLog: (level: LogLevel, message: string) => void;
+}
+
+const LEVELS: { level: TelemetryLevel; title: string; hint: string }[] = [
+  {
+    level: "Off",
+    title: "Выключена",
+    hint: "Ничего не отправляется, кроме сообщений разработчику.",
+  },
+  {
+    level: "Basic",
+    title: "Базовая",
+    hint: "Версия приложения и ОС, запуски сессий и версия игры — без имени пользователя, адреса сервера и ника.",
+  },
+  {
+    level: "Full",
+    title: "Полная",
+    hint: "То же, плюс имя пользователя Windows, адрес сервера и ник.",
+  },
+];
+
+type SinkKind = "Builtin" | "Grpc" | "File" | "Disabled";
+
+const SINKS: Record<SinkKind, string> = {
+  Builtin: "Разработчику",
+  Grpc: "Свой gRPC-сервер",
+  File: "В файл (JSON lines)",
+  Disabled: "Никуда",
+};
+
+function sinkKind(sink: TelemetrySink): SinkKind {
+  if (typeof sink === "string") return sink;
+  return "Grpc" in sink ? "Grpc" : "File";
+}
+
+function sinkTarget(sink: TelemetrySink): string {
+  if (typeof sink === "string") return "";
+  return "Grpc" in sink ? sink.Grpc.url : sink.File.path;
+}
+
+function buildSink(kind: SinkKind, target: string): TelemetrySink {
+  switch (kind) {
+    case "Grpc":
+      return { Grpc: { url: target } };
+    case "File":
+      return { File: { path: target } };
+    default:
+      return kind;
+  }
+}
+
+const formatTime = (ms: number) => new Date(ms).toLocaleString("ru-RU");
+
+export const TelemetryModal: React.FC<Props> = ({
+  current,
+  onClose,
+  addLog,
+}) => {
+  const [level, setLevel] = useState<TelemetryLevel>(
+    current?.level ?? "Basic",
+  );
+  const [includeChat, setIncludeChat] = useState(
+    current?.include_chat ?? false,
+  );
+  const [sink, setSink] = useState<SinkKind>("Builtin");
+  const [target, setTarget] = useState("");
+  const [audit, setAudit] = useState<TelemetryAuditEntry[] | null>(null);
+  const [queued, setQueued] = useState(0);
+  const isFirstRun = current === null;
+
+  useEffect(() => {
+    commands.getTelemetrySink().then((saved) => {
+      setSink(sinkKind(saved));
+      setTarget(sinkTarget(saved));
+    });
+  }, []);
+
+  useEffect(() => {
+    if (isFirstRun) return;
+    const onKey = (e: KeyboardEvent) => {
+      if (e.key === "Escape") onClose(current);
+    };
+    window.addEventListener("keydown", onKey);
+    return () => window.removeEventListener("keydown", onKey);
+  }, [isFirstRun, current, onClose]);
+
+  const save = async () => {
+    const sinkResult = await commands.setTelemetrySink(buildSink(sink, target));
+    if (sinkResult.status === "error") {
+      addLog("Error", `Не удалось сохранить получателя: ${sinkResult.error}`);
+      return;
+    }
+    const settings = { level, include_chat: level === "Full" && includeChat };
+    const result = await commands.setTelemetrySettings(settings);
+    if (result.status === "error") {
+      addLog("Error", `Не удалось сохранить настройки: ${result.error}`);
+      return;
+    }
+    onClose(settings);
+  };
+
+  const showAudit = async () => {
+    const result = await commands.getTelemetryAudit();
+    if (result.status === "error") {
+      addLog("Error", `Не удалось прочитать журнал: ${result.error}`);
+      return;
+    }
+    setAudit(result.data.reverse());
+    setQueued(await commands.getTelemetryQueued());
+  };
+
+  const clearAudit = async () => {
+    const result = await commands.clearTelemetryAudit();
+    if (result.status === "error") {
+      addLog("Error", `Не удалось очистить журнал: ${result.error}`);
+      return;
+    }
+    setAudit([]);
+  };
+
+  return (
+    <div
+      className="changelog-backdrop"
+      onClick={(e) => {
+        if (!isFirstRun && e.target === e.currentTarget) onClose(current);
+      }}
+    >
+      <div className="changelog-card" role="dialog" aria-modal="true">
+        <div className="changelog-card__header">
+          <span className="changelog-card__title">Телеметрия</span>
+          {!isFirstRun && (
+            <button
+              className="changelog-card__close"
+              onClick={() => onClose(current)}
+              aria-label="Закрыть"
+            >
+              ✕
+            </button>
+          )}
+        </div>
+
+        <div className="changelog-card__body">
+          <p className="dev-message__hint">
+            Выберите, что VoxelProxy отправляет разработчику. Настройку можно
+            изменить в любой момент кнопкой со щитом в заголовке окна.
+          </p>
+          {LEVELS.map((option) => (
+            <label key={option.level} className="telemetry__option">
+              <input
+                type="radio"
+                name="telemetry-level"
+                checked={level === option.level}
+                onChange={() => setLevel(option.level)}
+              />
+              <span>
+                <strong>{option.title}</strong>
+                <span className="telemetry__hint">{option.hint}</span>
+              </span>
+            </label>
+          ))}
+          <label className="telemetry__option">
+            <input
+              type="checkbox"
+              checked={level === "Full" && includeChat}
+              disabled={level !== "Full"}
+              onChange={(e) => setIncludeChat(e.target.checked)}
+            />
+            <span>
+              <strong>Отправлять чат</strong>
+              <span className="telemetry__hint">
+                Сообщения и команды, которые вы пишете в игре. Только при
+                полной телеметрии.
+              </span>
+            </span>
+          </label>
+
+          <div className="telemetry__sink">
+            <span>Куда отправлять</span>
+            <select
+              value={sink}
+              onChange={(e) => setSink(e.target.value as SinkKind)}
+            >
+              {(Object.keys(SINKS) as SinkKind[]).map((kind) => (
+                <option key={kind} value={kind}>
+                  {SINKS[kind]}
+                </option>
+              ))}
+            </select>
+            {(sink === "Grpc" || sink === "File") && (
+              <input
+                placeholder={
+                  sink === "Grpc"
+                    ? "https://telemetry.example.com"
+                    : "C:\\Users\\me\\telemetry.jsonl"
+                }
+                value={target}
+                onChange={(e) => setTarget(e.target.value)}
+              />
+            )}
+          </div>
+
+          {!isFirstRun &&
+            (audit === null ? (
+              <button className="log-drawer__clear" onClick={showAudit}>
+                Показать, что отправлено
+              </button>
+            ) : (
+              <div className="telemetry__audit">
+                <div className="telemetry__audit-header">
+                  <span>
+                    Отправлено: {audit.length}
+                    {queued > 0 && `, ждут повтора: ${queued}`}
+                  </span>
+                  <button className="log-drawer__clear" onClick={clearAudit}>
+                    Очистить
+                  </button>
+                </div>
+                {audit.length === 0 && (
+                  <p className="telemetry__hint">Журнал пуст.</p>
+                )}
+                {audit.map((entry, i) => (
+                  <div key={i} className="log-entry">
+                    {formatTime(entry.time_ms)} {entry.rpc}
+                    {!entry.delivered && " (не доставлено)"}: {entry.request}
+                  </div>
+                ))}
+              </div>
+            ))}
+        </div>
+
+        <div className="changelog-card__footer warning-card__footer">
+          {!isFirstRun && (
+            <button
+              className="warning-card__cancel"
+              onClick={() => onClose(current)}
+            >
+              Отмена
+            </button>
+          )}
+          <button className="changelog-card__ok" onClick={save}>
+            Сохранить
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+};
@@ -1,15 +1,21 @@
 import React from "react";
 import { AppState } from "../types";
 import { commands } from "../bindings";
-import { MailIcon } from "./Icons";
+import { MailIcon, ShieldIcon } from "./Icons";
 
 interface Props {
   state: AppState;
   onStop: () => void;
   onContact: () => void;
+  onTelemetry: () => void;
 }
 
-export const TitleBar: React.FC<Props> = ({ state, onStop, onContact }) => (
+export const TitleBar: React.FC<Props> = ({
+  state,
+  onStop,
+  onContact,
+  onTelemetry,
+}) => (
   <header className="titlebar">
     <button
       className="titlebar__logo"
@@ -23,6 +29,14 @@ export const TitleBar: React.FC<Props> = ({ state, onStop, onContact }) => (
       {state.version ? `v${state.version}` : ""}
     </span>
     <span className="titlebar__spacer" />
+    <button
+      className="titlebar__contact"
+      onClick={onTelemetry}
+      title="Телеметрия"
+      aria-label="Телеметрия"
+    >
+      <ShieldIcon />
+    </button>
     <button
       className="titlebar__contact"
       onClick={onContact}
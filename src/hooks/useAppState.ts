@@ -9,6 +9,8 @@ const initialState: AppState = {
   autoUseWindivert: true,
   autoPortMin: 25560,
   autoPortMax: 25570,
+  routing: [],
+  profile: null,
   localIp: "...",
   version: "",
   updateInfo: null,
@@ -18,10 +20,12 @@ const initialState: AppState = {
   updateProgress: null,
   updateInstallError: null,
   clients: { primary: { online: false }, secondary: { online: false } },
+  activeClient: "Primary",
   platform: '',
   panicMode: false,
   nickName: '',
   serverAddr: '',
+  stats: null,
 };
 
 export function useAppState() {
@@ -16,6 +16,8 @@ export function useTauriListeners(
         setState((s) => ({ ...s,
           phase: "running",
           clients: { primary: { online: false }, secondary: { online: false } },
+          activeClient: "Primary",
+          stats: null,
         }));
       });
 
@@ -36,6 +38,10 @@ export function useTauriListeners(
         });
       });
 
+      const unactive = await events.activeClientEvent.listen((e) => {
+        setState((s) => ({ ...s, activeClient: e.payload.which }));
+      });
+
       const unnickname = await events.nickNameEvent.listen((e) => {
         setState((s) => ({...s, nickName: e.payload}))
       })
@@ -44,7 +50,11 @@ export function useTauriListeners(
         setState((s) => ({...s, serverAddr: e.payload}))
       })
 
-      unlisteners = [unlog, unstart, unend, unclient, unnickname, unserveraddr];
+      const unstats = await events.sessionStatsEvent.listen((e) => {
+        setState((s) => ({...s, stats: e.payload}))
+      })
+
+      unlisteners = [unlog, unstart, unend, unclient, unactive, unnickname, unserveraddr, unstats];
     };
 
     setup();
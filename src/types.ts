@@ -1,3 +1,10 @@
+import {
+  PacketCategory,
+  Profile,
+  SessionStatsEvent,
+  WhichClient,
+} from "./bindings";
+
 export type Phase = 'idle' | 'running';
 export type Mode = 'manual' | 'auto';
 
@@ -12,6 +19,9 @@ export interface AppState {
   autoUseWindivert: boolean;
   autoPortMin: number;
   autoPortMax: number;
+  routing: PacketCategory[];
+  // Выбранный профиль; сессия запускается из него, пока форма с ним совпадает.
+  profile: Profile | null;
   localIp: string;
   version: string;
   updateInfo: { tag: string; link: string } | null;
@@ -24,8 +34,10 @@ export interface AppState {
     primary: ClientStatus;
     secondary: ClientStatus;
   };
+  activeClient: WhichClient;
   platform: string;
   panicMode: boolean,
   nickName: string,
   serverAddr: string,
+  stats: SessionStatsEvent | null,
 }
\ No newline at end of file
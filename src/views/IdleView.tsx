@@ -1,9 +1,11 @@
 import React, { useState, useCallback, useEffect } from "react";
 import { AnimatePresence, motion } from "motion/react";
 import { AppState } from "../types";
-import { commands, LogLevel } from "../bindings";
+import { commands, LogLevel, PacketCategory } from "../bindings";
 import { ManualWarningModal } from "../components/ManualWarningModal";
 import { AdminRequiredModal } from "../components/AdminRequiredModal";
+import { AccountRow } from "../components/AccountRow";
+import { matchingProfile, ProfilesRow } from "../components/ProfilesRow";
 
 const validateManualAddr = (addr: string): boolean => {
   const trimmed = addr.trim();
@@ -35,6 +37,15 @@ const validateManualAddr = (addr: string): boolean => {
   return domainRegex.test(host);
 };
 
+const ROUTING_CATEGORIES: { category: PacketCategory; label: string }[] = [
+  { category: "Chat", label: "Чат" },
+  { category: "Commands", label: "Команды" },
+  { category: "Movement", label: "Движение" },
+  { category: "Combat", label: "Бой" },
+  { category: "Interaction", label: "Взаимодействие" },
+  { category: "Inventory", label: "Инвентарь" },
+];
+
 interface Props {
   state: AppState;
   setState: React.Dispatch<React.SetStateAction<AppState>>;
@@ -52,11 +63,17 @@ export const IdleView: React.FC<Props> = ({ state, setState, addLog }) => {
   }, []);
 
   const launchManualSession = useCallback(async () => {
-    const result = await commands.startManualSession(state.manualServerAddr);
+    const profile = matchingProfile(state);
+    const result = profile
+      ? await commands.startProfile(profile.name)
+      : await commands.startManualSession(
+          state.manualServerAddr,
+          state.routing,
+        );
     if (result.status === "error") {
       addLog("Error", `Ошибка запуска: ${result.error}`);
     }
-  }, [state.manualServerAddr, addLog]);
+  }, [state, addLog]);
 
   const start = useCallback(async () => {
     if (isStarting) return;
@@ -114,11 +131,15 @@ export const IdleView: React.FC<Props> = ({ state, setState, addLog }) => {
           );
         }
 
-        const result = await commands.startAutoSession(
-          autoUseWindivert,
-          autoPortMin,
-          autoPortMax,
-        );
+        const profile = matchingProfile(state);
+        const result = profile
+          ? await commands.startProfile(profile.name)
+          : await commands.startAutoSession(
+              autoUseWindivert,
+              autoPortMin,
+              autoPortMax,
+              state.routing,
+            );
 
         if (result.status === "error") {
           addLog("Error", `Ошибка запуска: ${result.error}`);
@@ -215,6 +236,13 @@ export const IdleView: React.FC<Props> = ({ state, setState, addLog }) => {
           <div className="panel__subtitle">{subtitle}</div>
         </div>
 
+        <ProfilesRow
+          state={state}
+          setState={setState}
+          disabled={isBlocked}
+          addLog={addLog}
+        />
+
         <div className="field-row">
           <span className="field-row__label">Режим</span>
           <div className="mode-tabs">
@@ -415,8 +443,8 @@ export const IdleView: React.FC<Props> = ({ state, setState, addLog }) => {
                         </p>
                         <p>
                           <strong>Лицензионный (online-mode) сервер.</strong>{" "}
-                          Используйте ViaProxy перед VoxelProxy с настроенным
-                          аккаунтом.
+                          Войдите в аккаунт Minecraft в строке «Аккаунт» — на
+                          сервер вы зайдёте под его ником.
                         </p>
                       </div>
                     </Collapsible>
@@ -467,6 +495,41 @@ export const IdleView: React.FC<Props> = ({ state, setState, addLog }) => {
           </motion.div>
         </AnimatePresence>
 
+        <div className="field-row">
+          <span className="field-row__label">Второй клиент</span>
+          <div className="routing-row">
+            {ROUTING_CATEGORIES.map(({ category, label }) => (
+              <label key={category} className="checkbox-label">
+                <input
+                  type="checkbox"
+                  checked={state.routing.includes(category)}
+                  onChange={(e) =>
+                    setState((s) => ({
+                      ...s,
+                      routing: e.target.checked
+                        ? [...s.routing, category]
+                        : s.routing.filter((c) => c !== category),
+                    }))
+                  }
+                  disabled={isBlocked}
+                />
+                {label}
+              </label>
+            ))}
+            <span
+              className="help-icon"
+              tabIndex={0}
+              role="img"
+              aria-label="Подсказка"
+              title="Отмеченные действия неактивного клиента тоже уходят на сервер. Например, второй клиент пишет в чат и вводит команды, пока основной ходит и сражается."
+            >
+              ?
+            </span>
+          </div>
+        </div>
+
+        <AccountRow disabled={isBlocked} addLog={addLog} />
+
         {supportedVersions.length > 0 && (
           <div className="field-row">
             <span className="field-row__label">Версии</span>
@@ -1,5 +1,6 @@
 import React, { useEffect, useState } from "react";
 import { AnimatePresence, motion } from "motion/react";
+import { LogLevel } from "../bindings";
 import { AppState } from "../types";
 import { ActiveSession } from "../components/ActiveSession";
 import { CheckIcon, CopyIcon } from "../components/Icons";
@@ -7,9 +8,10 @@ import { CheckIcon, CopyIcon } from "../components/Icons";
 interface Props {
   state: AppState;
   onTogglePanicMode: () => void;
+  addLog: (level: LogLevel, message: string) => void;
 }
 
-export const RunningView: React.FC<Props> = ({ state, onTogglePanicMode }) => {
+export const RunningView: React.FC<Props> = ({ state, onTogglePanicMode, addLog }) => {
   const anyOnline =
     state.clients.primary.online || state.clients.secondary.online;
   const bothOnline =
@@ -39,9 +41,12 @@ export const RunningView: React.FC<Props> = ({ state, onTogglePanicMode }) => {
             nickName={state.nickName}
             serverAddr={state.serverAddr}
             clients={state.clients}
+            activeClient={state.activeClient}
+            stats={state.stats}
             showPanicMode={showPanicMode}
             panicMode={state.panicMode}
             onTogglePanicMode={onTogglePanicMode}
+            addLog={addLog}
           />
         );
       case "auto-steps":
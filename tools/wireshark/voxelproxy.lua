@@ -0,0 +1,72 @@
+-- Wireshark dissector for VoxelProxy session recordings (`--capture-dir`).
+--
+-- Copy this file to the Wireshark personal plugins folder (Help → About
+-- Wireshark → Folders) and open a recording. The record layout is described
+-- in src-tauri/core/src/capture.rs.
+
+local voxelproxy = Proto("voxelproxy", "VoxelProxy")
+
+local directions = { [0] = "Serverbound", [1] = "Clientbound" }
+local clients = { [0] = "Primary", [1] = "Secondary", [255] = "Server" }
+local states = { [0] = "Login", [1] = "Configuration", [2] = "Game" }
+
+local f_direction = ProtoField.uint8("voxelproxy.direction", "Direction", base.DEC, directions)
+local f_client = ProtoField.uint8("voxelproxy.client", "Client", base.DEC, clients)
+local f_state = ProtoField.uint8("voxelproxy.state", "State", base.DEC, states)
+local f_protocol = ProtoField.uint32("voxelproxy.protocol", "Protocol", base.DEC)
+local f_packet_id = ProtoField.uint32("voxelproxy.packet_id", "Packet ID", base.HEX)
+local f_payload = ProtoField.bytes("voxelproxy.payload", "Payload")
+
+voxelproxy.fields = { f_direction, f_client, f_state, f_protocol, f_packet_id, f_payload }
+
+-- Reads a VarInt at `offset`; returns its value and length in bytes.
+local function read_varint(buffer, offset)
+    local value, length = 0, 0
+    repeat
+        if offset + length >= buffer:len() or length == 5 then
+            return nil, length
+        end
+        local byte = buffer(offset + length, 1):uint()
+        value = value + bit.lshift(bit.band(byte, 0x7F), 7 * length)
+        length = length + 1
+    until bit.band(byte, 0x80) == 0
+    return value, length
+end
+
+function voxelproxy.dissector(buffer, pinfo, tree)
+    if buffer:len() < 9 then
+        return 0
+    end
+    pinfo.cols.protocol = "Minecraft"
+
+    local direction = buffer(0, 1):uint()
+    local client = buffer(1, 1):uint()
+    local state = buffer(2, 1):uint()
+    local packet_id, id_length = read_varint(buffer, 8)
+
+    local subtree = tree:add(voxelproxy, buffer(), "VoxelProxy")
+    subtree:add(f_direction, buffer(0, 1))
+    subtree:add(f_client, buffer(1, 1))
+    subtree:add(f_state, buffer(2, 1))
+    subtree:add(f_protocol, buffer(4, 4))
+    if packet_id == nil then
+        return buffer:len()
+    end
+    subtree:add(f_packet_id, buffer(8, id_length), packet_id)
+    local payload_offset = 8 + id_length
+    if payload_offset < buffer:len() then
+        subtree:add(f_payload, buffer(payload_offset))
+    end
+
+    pinfo.cols.src = clients[client] or tostring(client)
+    pinfo.cols.info = string.format(
+        "%s %s 0x%02X, %d bytes",
+        states[state] or "?",
+        directions[direction] or "?",
+        packet_id,
+        buffer:len() - payload_offset
+    )
+    return buffer:len()
+end
+
+DissectorTable.get("wtap_encap"):add(wtap.USER0, voxelproxy)